@@ -12,6 +12,7 @@ pub mod focused_window;
 pub mod gdq;
 pub mod github;
 pub mod hueshift;
+pub mod hwmon;
 pub mod ibus;
 pub mod kdeconnect;
 pub mod keyboard_layout;
@@ -54,6 +55,7 @@ use self::focused_window::*;
 use self::gdq::*;
 use self::github::*;
 use self::hueshift::*;
+use self::hwmon::*;
 use self::ibus::*;
 use self::kdeconnect::*;
 use self::keyboard_layout::*;
@@ -82,15 +84,18 @@ use self::watson::*;
 use self::weather::*;
 use self::xrandr::*;
 
+use std::collections::HashMap;
 use std::time::Duration;
 
 use crossbeam_channel::Sender;
-use serde::de::Deserialize;
+use serde::de::{Deserialize, DeserializeOwned};
 use toml::value::Value;
 
 use crate::config::SharedConfig;
 use crate::errors::*;
-use crate::protocol::i3bar_event::I3BarEvent;
+use crate::formatting::schema::{self, with_schema, Schema};
+use crate::formatting::value::Value as FormatValue;
+use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
 use crate::widgets::I3BarWidget;
 
@@ -125,6 +130,10 @@ impl Into<Update> for Duration {
 pub trait ConfigBlock: Block {
     type Config;
 
+    /// The placeholders the block renders its formats with. The formats in the config of the
+    /// block may only use these.
+    const PLACEHOLDERS: Schema;
+
     /// Creates a new block from the relevant configuration.
     fn new(
         id: usize,
@@ -179,107 +188,179 @@ pub trait Block {
     fn click(&mut self, _event: &I3BarEvent) -> Result<()> {
         Ok(())
     }
+
+    /// Performs a named action, like "toggle_format", that the user bound to a mouse button in
+    /// the `click` table of the block. `event` is the click that triggered the action.
+    /// Returns `false` if the block does not know the action.
+    fn action(&mut self, _action: &str, _event: &I3BarEvent) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// The placeholder values the block rendered last, which the `state_rules` of the block are
+    /// evaluated against. Blocks with placeholders return those of their format here.
+    fn values(&self) -> HashMap<String, FormatValue> {
+        HashMap::new()
+    }
 }
 
-macro_rules! block {
-    ($block_type:ident, $id:expr, $block_config:expr, $shared_config:expr, $update_request:expr) => {{
-        // Extract base(common) config
-        let common_config = BaseBlockConfig::extract(&mut $block_config);
-        let mut common_config = BaseBlockConfig::deserialize(common_config)
-            .configuration_error("Failed to deserialize common block config.")?;
+/// Read the common and the block-specific config of a block of type `B`, and apply the common
+/// config to `shared_config`. This has no side effects beyond that.
+fn read_config<B>(
+    mut block_config: Value,
+    shared_config: &mut SharedConfig,
+) -> Result<(BaseBlockConfig, B::Config)>
+where
+    B: ConfigBlock,
+    B::Config: DeserializeOwned,
+{
+    // Extract base(common) config
+    let common_config = BaseBlockConfig::extract(&mut block_config);
+    let common_config = BaseBlockConfig::deserialize(common_config)
+        .configuration_error("Failed to deserialize common block config.")?;
 
-        // Apply theme overrides if presented
-        if let Some(ref overrides) = common_config.theme_overrides {
-            $shared_config.theme_override(overrides)?;
-        }
-        if let Some(overrides) = common_config.icons_format {
-            $shared_config.icons_format_override(overrides);
+    // Apply theme overrides if presented
+    if let Some(ref overrides) = common_config.theme_overrides {
+        shared_config.theme_override(overrides)?;
+    }
+    for rule in &common_config.state_rules {
+        schema::field(B::PLACEHOLDERS, &rule.condition.name)
+            .configuration_error("Invalid state rule.")?;
+        if !shared_config.theme.has_state(rule.state) {
+            return Err(ConfigurationError(
+                format!("State '{}' is not declared in the theme", rule.state.name()),
+                String::new(),
+            ));
         }
+    }
+    if let Some(ref overrides) = common_config.icons_format {
+        shared_config.icons_format_override(overrides.clone());
+    }
 
-        // Extract block-specific config
-        let block_config = <$block_type as ConfigBlock>::Config::deserialize($block_config)
-            .configuration_error("Failed to deserialize block config.")?;
+    // Extract block-specific config
+    let block_config = with_schema(B::PLACEHOLDERS, || B::Config::deserialize(block_config))
+        .configuration_error("Failed to deserialize block config.")?;
 
-        let mut block = $block_type::new($id, block_config, $shared_config, $update_request)?;
+    Ok((common_config, block_config))
+}
+
+macro_rules! block {
+    ($block_type:ident, $id:expr, $block_config:expr, $shared_config:expr, $update_request:expr) => {{
+        let (mut common_config, block_config) =
+            read_config::<$block_type>($block_config, &mut $shared_config)?;
+
+        let mut block =
+            $block_type::new($id, block_config, $shared_config.clone(), $update_request)?;
         if let Some(overrided) = block.override_on_click() {
             *overrided = common_config.on_click.take();
         }
+        // `on_click` is a shorthand for a command bound to the left button
+        if let Some(cmd) = common_config.on_click {
+            common_config
+                .click
+                .entry(MouseButton::Left)
+                .or_insert_with(|| {
+                    vec![ClickHandler {
+                        cmd: Some(cmd),
+                        ..ClickHandler::default()
+                    }]
+                });
+        }
 
         Ok(Box::new(BaseBlock {
             name: stringify!($block_type).to_string(),
             inner: block,
-            on_click: common_config.on_click,
+            click: common_config.click,
+            state_rules: common_config.state_rules,
+            shared_config: $shared_config,
+            overridden: None,
         }) as Box<dyn Block>)
     }};
 }
 
+/// Call `$action!` with the type of the block called `$name` and `$arg`s
+macro_rules! dispatch {
+    ($name:expr, $action:ident!($($arg:expr),*)) => {
+        match $name {
+            // Please keep these in alphabetical order.
+            "apt" => $action!(Apt, $($arg),*),
+            "backlight" => $action!(Backlight, $($arg),*),
+            "battery" => $action!(Battery, $($arg),*),
+            "bluetooth" => $action!(Bluetooth, $($arg),*),
+            "cpu" => $action!(Cpu, $($arg),*),
+            "custom" => $action!(Custom, $($arg),*),
+            "custom_dbus" => $action!(CustomDBus, $($arg),*),
+            "disk_space" => $action!(DiskSpace, $($arg),*),
+            "docker" => $action!(Docker, $($arg),*),
+            "gdq" => $action!(GDQ, $($arg),*),
+            "focused_window" => $action!(FocusedWindow, $($arg),*),
+            "github" => $action!(Github, $($arg),*),
+            "hueshift" => $action!(Hueshift, $($arg),*),
+            "hwmon" => $action!(Hwmon, $($arg),*),
+            "ibus" => $action!(IBus, $($arg),*),
+            "kdeconnect" => $action!(KDEConnect, $($arg),*),
+            "keyboard_layout" => $action!(KeyboardLayout, $($arg),*),
+            "load" => $action!(Load, $($arg),*),
+            #[cfg(feature = "maildir")]
+            "maildir" => $action!(Maildir, $($arg),*),
+            "memory" => $action!(Memory, $($arg),*),
+            "music" => $action!(Music, $($arg),*),
+            "net" => $action!(Net, $($arg),*),
+            "networkmanager" => $action!(NetworkManager, $($arg),*),
+            "notify" => $action!(Notify, $($arg),*),
+            #[cfg(feature = "notmuch")]
+            "notmuch" => $action!(Notmuch, $($arg),*),
+            "nvidia_gpu" => $action!(NvidiaGpu, $($arg),*),
+            "pacman" => $action!(Pacman, $($arg),*),
+            "pomodoro" => $action!(Pomodoro, $($arg),*),
+            "sound" => $action!(Sound, $($arg),*),
+            "speedtest" => $action!(SpeedTest, $($arg),*),
+            "taskwarrior" => $action!(Taskwarrior, $($arg),*),
+            "temperature" => $action!(Temperature, $($arg),*),
+            "template" => $action!(Template, $($arg),*),
+            "time" => $action!(Time, $($arg),*),
+            "toggle" => $action!(Toggle, $($arg),*),
+            "uptime" => $action!(Uptime, $($arg),*),
+            "watson" => $action!(Watson, $($arg),*),
+            "weather" => $action!(Weather, $($arg),*),
+            "xrandr" => $action!(Xrandr, $($arg),*),
+            other => Err(BlockError(other.to_string(), "Unknown block!".to_string())),
+        }
+    };
+}
+
 pub fn create_block(
     id: usize,
     name: &str,
-    mut block_config: Value,
+    block_config: Value,
     mut shared_config: SharedConfig,
     update_request: Sender<Task>,
 ) -> Result<Box<dyn Block>> {
-    match name {
-        // Please keep these in alphabetical order.
-        "apt" => block!(Apt, id, block_config, shared_config, update_request),
-        "backlight" => block!(Backlight, id, block_config, shared_config, update_request),
-        "battery" => block!(Battery, id, block_config, shared_config, update_request),
-        "bluetooth" => block!(Bluetooth, id, block_config, shared_config, update_request),
-        "cpu" => block!(Cpu, id, block_config, shared_config, update_request),
-        "custom" => block!(Custom, id, block_config, shared_config, update_request),
-        "custom_dbus" => block!(CustomDBus, id, block_config, shared_config, update_request),
-        "disk_space" => block!(DiskSpace, id, block_config, shared_config, update_request),
-        "docker" => block!(Docker, id, block_config, shared_config, update_request), ///////
-        "gdq" => block!(GDQ, id, block_config, shared_config, update_request),       ///////
-        "focused_window" => block!(
-            FocusedWindow,
-            id,
-            block_config,
-            shared_config,
-            update_request
-        ),
-        "github" => block!(Github, id, block_config, shared_config, update_request),
-        "hueshift" => block!(Hueshift, id, block_config, shared_config, update_request),
-        "ibus" => block!(IBus, id, block_config, shared_config, update_request),
-        "kdeconnect" => block!(KDEConnect, id, block_config, shared_config, update_request),
-        "keyboard_layout" => block!(
-            KeyboardLayout,
-            id,
-            block_config,
-            shared_config,
-            update_request
-        ),
-        "load" => block!(Load, id, block_config, shared_config, update_request),
-        #[cfg(feature = "maildir")]
-        "maildir" => block!(Maildir, id, block_config, shared_config, update_request),
-        "memory" => block!(Memory, id, block_config, shared_config, update_request),
-        "music" => block!(Music, id, block_config, shared_config, update_request),
-        "net" => block!(Net, id, block_config, shared_config, update_request),
-        "networkmanager" => block!(
-            NetworkManager,
-            id,
-            block_config,
-            shared_config,
-            update_request
-        ),
-        "notify" => block!(Notify, id, block_config, shared_config, update_request),
-        #[cfg(feature = "notmuch")]
-        "notmuch" => block!(Notmuch, id, block_config, shared_config, update_request),
-        "nvidia_gpu" => block!(NvidiaGpu, id, block_config, shared_config, update_request),
-        "pacman" => block!(Pacman, id, block_config, shared_config, update_request),
-        "pomodoro" => block!(Pomodoro, id, block_config, shared_config, update_request),
-        "sound" => block!(Sound, id, block_config, shared_config, update_request),
-        "speedtest" => block!(SpeedTest, id, block_config, shared_config, update_request),
-        "taskwarrior" => block!(Taskwarrior, id, block_config, shared_config, update_request),
-        "temperature" => block!(Temperature, id, block_config, shared_config, update_request),
-        "template" => block!(Template, id, block_config, shared_config, update_request),
-        "time" => block!(Time, id, block_config, shared_config, update_request), /////////
-        "toggle" => block!(Toggle, id, block_config, shared_config, update_request),
-        "uptime" => block!(Uptime, id, block_config, shared_config, update_request),
-        "watson" => block!(Watson, id, block_config, shared_config, update_request),
-        "weather" => block!(Weather, id, block_config, shared_config, update_request),
-        "xrandr" => block!(Xrandr, id, block_config, shared_config, update_request),
-        other => Err(BlockError(other.to_string(), "Unknown block!".to_string())),
-    }
+    dispatch!(
+        name,
+        block!(id, block_config, shared_config, update_request)
+    )
+}
+
+macro_rules! placeholders {
+    ($block_type:ident $(,)?) => {
+        Ok(<$block_type as ConfigBlock>::PLACEHOLDERS)
+    };
+}
+
+/// The placeholders of the block called `name`
+pub fn block_placeholders(name: &str) -> Result<Schema> {
+    dispatch!(name, placeholders!())
+}
+
+/// Validate the config of a block by creating it in a dry run, in which it has no side effects
+pub fn check_block(name: &str, block_config: Value, mut shared_config: SharedConfig) -> Result<()> {
+    shared_config.dry_run = true;
+    create_block(
+        0,
+        name,
+        block_config,
+        shared_config,
+        crossbeam_channel::unbounded().0,
+    )
+    .map(|_| ())
 }
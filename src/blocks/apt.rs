@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::io::Write;
@@ -12,6 +13,8 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
+use crate::formatting::unit::Unit;
 use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
 use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
@@ -73,6 +76,8 @@ impl Default for AptConfig {
 impl ConfigBlock for Apt {
     type Config = AptConfig;
 
+    const PLACEHOLDERS: Schema = &[Field::integer("count", Unit::None)];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -81,32 +86,38 @@ impl ConfigBlock for Apt {
     ) -> Result<Self> {
         let mut cache_dir = env::temp_dir();
         cache_dir.push("i3rs-apt");
-        if !cache_dir.exists() {
-            fs::create_dir(cache_dir.clone()).block_error("apt", "Failed to create temp dir")?;
-        }
+        let config_path = cache_dir.join("apt.conf");
+        if !shared_config.dry_run {
+            if !cache_dir.exists() {
+                fs::create_dir(cache_dir.clone())
+                    .block_error("apt", "Failed to create temp dir")?;
+            }
 
-        let apt_conf = format!(
-            "Dir::State \"{}\";\n
-             Dir::State::lists \"lists\";\n
-             Dir::Cache \"{}\";\n
-             Dir::Cache::srcpkgcache \"srcpkgcache.bin\";\n
-             Dir::Cache::pkgcache \"pkgcache.bin\";",
-            cache_dir.clone().into_os_string().into_string().unwrap(),
-            cache_dir.clone().into_os_string().into_string().unwrap()
-        );
-        cache_dir.push("apt.conf");
-        let mut config_file = fs::File::create(cache_dir.clone())
-            .block_error("apt", "Failed to create config file")?;
-        write!(config_file, "{}", apt_conf).block_error("apt", "Failed to write to config file")?;
+            let apt_conf = format!(
+                "Dir::State \"{}\";\n
+                 Dir::State::lists \"lists\";\n
+                 Dir::Cache \"{}\";\n
+                 Dir::Cache::srcpkgcache \"srcpkgcache.bin\";\n
+                 Dir::Cache::pkgcache \"pkgcache.bin\";",
+                cache_dir.clone().into_os_string().into_string().unwrap(),
+                cache_dir.clone().into_os_string().into_string().unwrap()
+            );
+            let mut config_file = fs::File::create(config_path.clone())
+                .block_error("apt", "Failed to create config file")?;
+            write!(config_file, "{}", apt_conf)
+                .block_error("apt", "Failed to write to config file")?;
+        }
 
         let output = TextWidget::new(id, 0, shared_config).with_icon("update")?;
 
+        let format = block_config.format.with_default("{count:1}")?;
+
         Ok(Apt {
             id,
             update_interval: block_config.interval,
-            format: block_config.format.with_default("{count:1}")?,
-            format_singular: block_config.format_singular.with_default("{count:1}")?,
-            format_up_to_date: block_config.format_up_to_date.with_default("{count:1}")?,
+            format_singular: block_config.format_singular.or(&format),
+            format_up_to_date: block_config.format_up_to_date.or(&format),
+            format,
             output,
             warning_updates_regex: match block_config.warning_updates_regex {
                 None => None, // no regex configured
@@ -132,7 +143,7 @@ impl ConfigBlock for Apt {
                     Some(regex)
                 }
             },
-            config_path: cache_dir.into_os_string().into_string().unwrap(),
+            config_path: config_path.into_os_string().into_string().unwrap(),
         })
     }
 }
@@ -172,6 +183,10 @@ fn get_update_count(updates: &str) -> usize {
 }
 
 impl Block for Apt {
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[&self.format, &self.format_singular, &self.format_up_to_date])
+    }
+
     fn id(&self) -> usize {
         self.id
     }
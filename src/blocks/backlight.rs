@@ -21,6 +21,8 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::config::{LogicalDirection, Scrolling};
 use crate::errors::*;
+use crate::formatting::schema::Schema;
+use crate::pause;
 use crate::protocol::i3bar_event::I3BarEvent;
 use crate::scheduler::Task;
 use crate::widgets::text::TextWidget;
@@ -224,6 +226,8 @@ impl Default for BacklightConfig {
 impl ConfigBlock for Backlight {
     type Config = BacklightConfig;
 
+    const PLACEHOLDERS: Schema = &[];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -236,6 +240,7 @@ impl ConfigBlock for Backlight {
         }?;
 
         let brightness_file = device.brightness_file();
+        let dry_run = shared_config.dry_run;
 
         let backlight = Backlight {
             id,
@@ -246,6 +251,10 @@ impl ConfigBlock for Backlight {
             invert_icons: block_config.invert_icons,
         };
 
+        if dry_run {
+            return Ok(backlight);
+        }
+
         // Spin up a thread to watch for changes to the brightness file for the
         // device, and schedule an update if needed.
         thread::Builder::new()
@@ -258,17 +267,21 @@ impl ConfigBlock for Backlight {
 
                 let mut buffer = [0; 1024];
                 loop {
+                    pause::wait();
                     let mut events = notify
                         .read_events_blocking(&mut buffer)
                         .expect("Error while reading inotify events");
 
-                    if events.any(|event| event.mask.contains(EventMask::MODIFY)) {
-                        tx_update_request
+                    if events.any(|event| event.mask.contains(EventMask::MODIFY))
+                        && tx_update_request
                             .send(Task {
                                 id,
                                 update_time: Instant::now(),
                             })
-                            .unwrap();
+                            .is_err()
+                    {
+                        // The block is gone
+                        break;
                     }
 
                     // Avoid update spam.
@@ -333,7 +346,62 @@ impl Block for Backlight {
         Ok(())
     }
 
+    fn action(&mut self, action: &str, event: &I3BarEvent) -> Result<bool> {
+        match action {
+            // Clicking at 70% of the width of the block sets the brightness to 70%
+            "set_by_position" => {
+                if let Some(position) = event.relative_position() {
+                    let brightness = (position * 100.).round() as u64;
+                    self.device.set_brightness(brightness)?;
+                }
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
     fn id(&self) -> usize {
         self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    use crate::config::Config;
+
+    #[test]
+    fn set_by_position() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let mut backlight = Backlight {
+            id: 0,
+            output: TextWidget::new(0, 0, SharedConfig::new(&Config::default())),
+            device: BacklitDevice {
+                max_brightness: 1000,
+                device_path: dir.path().to_path_buf(),
+                root_scaling: 1.0,
+            },
+            step_width: 5,
+            scrolling: Scrolling::Reverse,
+            invert_icons: false,
+        };
+        let mut click = |relative_x: f64| {
+            let event = I3BarEvent {
+                relative_x: Some(relative_x),
+                width: Some(80.),
+                ..I3BarEvent::default()
+            };
+            // Like sysfs, the file is not truncated when written to
+            fs::write(dir.path().join("brightness"), "").unwrap();
+            assert!(backlight.action("set_by_position", &event).unwrap());
+            fs::read_to_string(dir.path().join("brightness")).unwrap()
+        };
+
+        // The device is never turned off completely
+        assert_eq!(click(0.), "1");
+        assert_eq!(click(56.), "700");
+        assert_eq!(click(80.), "1000");
+    }
+}
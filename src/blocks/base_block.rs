@@ -1,18 +1,87 @@
 //! A Base block for common behavior for all blocks
 
 use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
 
+use crate::blocks::{Block, Update};
+use crate::config::SharedConfig;
+use crate::de::{deserialize_opt_duration, struct_fields};
 use crate::errors::*;
+use crate::formatting::condition::Condition;
+use crate::formatting::value::Value as FormatValue;
+use crate::protocol::i3bar_block::I3BarBlock;
 use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
-use crate::{blocks::Update, subprocess::spawn_child_async, widgets::I3BarWidget, Block};
+use crate::subprocess::{run_child, spawn_child_async};
+use crate::widgets::{I3BarWidget, State};
 
+use serde::de::{self, Deserialize, Deserializer};
 use serde_derive::Deserialize;
 use toml::{value::Table, Value};
 
 pub(super) struct BaseBlock<T: Block> {
     pub name: String,
     pub inner: T,
-    pub on_click: Option<String>,
+    pub click: HashMap<MouseButton, Vec<ClickHandler>>,
+    pub state_rules: Vec<StateRule>,
+    pub shared_config: SharedConfig,
+    /// The widgets of the block, recolored according to the first matching state rule
+    pub overridden: Option<Vec<I3BarBlock>>,
+}
+
+impl<T: Block> BaseBlock<T> {
+    /// Call into the inner block and apply the state rules to the widgets it ends up with
+    fn with_state_rules<R>(&mut self, f: impl FnOnce(&mut T) -> R) -> R {
+        if self.state_rules.is_empty() {
+            return f(&mut self.inner);
+        }
+
+        let result = f(&mut self.inner);
+
+        let values = self.inner.values();
+        let state = self
+            .state_rules
+            .iter()
+            .find(|rule| rule.condition.eval_value(values.get(&rule.condition.name)))
+            .map(|rule| rule.state);
+        let theme = &self.shared_config.theme;
+        self.overridden = state.map(|state| {
+            self.inner
+                .view()
+                .iter()
+                .map(|widget| {
+                    let mut widget = widget.get_data();
+                    state.apply(theme, &mut widget);
+                    widget
+                })
+                .collect()
+        });
+        result
+    }
+
+    /// Perform a built-in action of the block. The name of a mouse button stands for the
+    /// block's own handling of that button.
+    fn perform_action(&mut self, action: &str, event: &I3BarEvent) -> Result<()> {
+        match action.parse::<MouseButton>() {
+            Ok(button) => {
+                let event = I3BarEvent {
+                    button,
+                    ..event.clone()
+                };
+                self.with_state_rules(|inner| inner.click(&event))
+            }
+            Err(_) => {
+                if self.with_state_rules(|inner| inner.action(action, event))? {
+                    Ok(())
+                } else {
+                    Err(BlockError(
+                        self.name.clone(),
+                        format!("unknown action '{}'", action),
+                    ))
+                }
+            }
+        }
+    }
 }
 
 impl<T: Block> Block for BaseBlock<T> {
@@ -21,48 +90,112 @@ impl<T: Block> Block for BaseBlock<T> {
     }
 
     fn view(&self) -> Vec<&dyn I3BarWidget> {
-        self.inner.view()
+        match &self.overridden {
+            Some(widgets) => widgets
+                .iter()
+                .map(|widget| widget as &dyn I3BarWidget)
+                .collect(),
+            None => self.inner.view(),
+        }
     }
 
     fn update(&mut self) -> Result<Option<Update>> {
-        self.inner.update()
+        self.with_state_rules(|inner| inner.update())
     }
 
     fn signal(&mut self, signal: i32) -> Result<()> {
-        self.inner.signal(signal)
+        self.with_state_rules(|inner| inner.signal(signal))
     }
 
     fn click(&mut self, e: &I3BarEvent) -> Result<()> {
-        match &self.on_click {
-            Some(cmd) => {
-                if let MouseButton::Left = e.button {
-                    spawn_child_async("sh", &["-c", &cmd])
-                        .block_error(&self.name, "could not spawn child")?;
-                }
-                Ok(())
+        let handler = self.click.get(&e.button).and_then(|handlers| {
+            handlers
+                .iter()
+                .find(|handler| {
+                    (handler.instance.is_none() || handler.instance == e.instance)
+                        && handler
+                            .modifiers
+                            .iter()
+                            .all(|modifier| e.has_modifier(modifier))
+                })
+                .cloned()
+        });
+        let handler = match handler {
+            Some(handler) => handler,
+            None => return self.with_state_rules(|inner| inner.click(e)),
+        };
+
+        if let Some(cmd) = &handler.cmd {
+            if handler.update {
+                run_child("sh", &["-c", cmd]).block_error(&self.name, "could not run child")?;
+            } else {
+                spawn_child_async("sh", &["-c", cmd])
+                    .block_error(&self.name, "could not spawn child")?;
             }
-            None => self.inner.click(e),
         }
+        if let Some(action) = &handler.action {
+            self.perform_action(action, e)?;
+        }
+        if handler.update {
+            self.update()?;
+        }
+        Ok(())
+    }
+
+    fn action(&mut self, action: &str, event: &I3BarEvent) -> Result<bool> {
+        self.with_state_rules(|inner| inner.action(action, event))
+    }
+
+    fn values(&self) -> HashMap<String, FormatValue> {
+        self.inner.values()
     }
 }
 
 #[derive(Deserialize, Debug, Default, Clone)]
-pub(super) struct BaseBlockConfig {
-    /// Command to execute when the button is clicked
+pub(crate) struct BaseBlockConfig {
+    /// Stable name of the block, used as the `name` of its widgets in the i3bar protocol
+    pub id: Option<String>,
+
+    /// Command to execute when the block is left-clicked
     pub on_click: Option<String>,
 
+    /// What to do when the block is clicked, by mouse button
+    #[serde(default, deserialize_with = "deserialize_click")]
+    pub click: HashMap<MouseButton, Vec<ClickHandler>>,
+
     pub theme_overrides: Option<HashMap<String, String>>,
     pub icons_format: Option<String>,
+
+    /// How long a single update/click/signal may take before the block is restarted
+    #[serde(default, deserialize_with = "deserialize_opt_duration")]
+    pub timeout: Option<Duration>,
+
+    /// Override the state of the block with the first rule whose condition holds
+    #[serde(default)]
+    pub state_rules: Vec<StateRule>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct StateRule {
+    /// A condition over the placeholders of the block's format, like `utilization > 90`
+    #[serde(rename = "if")]
+    pub condition: Condition,
+    pub state: State,
 }
 
 impl BaseBlockConfig {
-    const FIELDS: &'static [&'static str] = &["on_click", "theme_overrides", "icons_format"];
+    /// Read the common config of a block without removing it from the block's config
+    pub(crate) fn peek(config: &Value) -> Result<Self> {
+        Self::deserialize(Self::extract(&mut config.clone()))
+            .configuration_error("Failed to deserialize common block config.")
+    }
 
     // FIXME: this function is to paper over https://github.com/serde-rs/serde/issues/1957
     pub(super) fn extract(config: &mut Value) -> Value {
         let mut common_table = Table::new();
         if let Some(table) = config.as_table_mut() {
-            for &field in Self::FIELDS {
+            for &field in struct_fields::<Self>() {
                 if let Some(it) = table.remove(field) {
                     common_table.insert(field.to_string(), it);
                 }
@@ -71,3 +204,318 @@ impl BaseBlockConfig {
         common_table.into()
     }
 }
+
+/// What to do when the block is clicked with a certain mouse button
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ClickHandler {
+    /// Only handle clicks on the widget with this instance
+    pub instance: Option<usize>,
+    /// Only handle clicks while all of these modifier keys are held
+    #[serde(default)]
+    pub modifiers: Vec<String>,
+    /// Shell command to run
+    pub cmd: Option<String>,
+    /// Built-in action of the block to perform (after `cmd` was started)
+    pub action: Option<String>,
+    /// Wait for `cmd` to finish and update the block afterwards
+    #[serde(default)]
+    pub update: bool,
+}
+
+/// Each button can be bound to a shell command, a single handler or a list of handlers for
+/// different widgets
+fn deserialize_click<'de, D>(
+    deserializer: D,
+) -> StdResult<HashMap<MouseButton, Vec<ClickHandler>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    enum ClickHandlers {
+        Cmd(String),
+        One(ClickHandler),
+        Many(Vec<ClickHandler>),
+    }
+
+    // Not `untagged`, so that the errors of a handler table are reported as they are, instead of
+    // "data did not match any variant"
+    impl<'de> Deserialize<'de> for ClickHandlers {
+        fn deserialize<D>(deserializer: D) -> StdResult<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            struct Visitor;
+
+            impl<'de> de::Visitor<'de> for Visitor {
+                type Value = ClickHandlers;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str("a shell command, a click handler or a list of them")
+                }
+
+                fn visit_str<E>(self, value: &str) -> StdResult<Self::Value, E>
+                where
+                    E: de::Error,
+                {
+                    Ok(ClickHandlers::Cmd(value.to_string()))
+                }
+
+                fn visit_map<A>(self, map: A) -> StdResult<Self::Value, A::Error>
+                where
+                    A: de::MapAccess<'de>,
+                {
+                    Deserialize::deserialize(de::value::MapAccessDeserializer::new(map))
+                        .map(ClickHandlers::One)
+                }
+
+                fn visit_seq<A>(self, seq: A) -> StdResult<Self::Value, A::Error>
+                where
+                    A: de::SeqAccess<'de>,
+                {
+                    Deserialize::deserialize(de::value::SeqAccessDeserializer::new(seq))
+                        .map(ClickHandlers::Many)
+                }
+            }
+
+            deserializer.deserialize_any(Visitor)
+        }
+    }
+
+    let handlers: HashMap<MouseButton, ClickHandlers> = Deserialize::deserialize(deserializer)?;
+    handlers
+        .into_iter()
+        .map(|(button, handlers)| {
+            let handlers = match handlers {
+                ClickHandlers::Cmd(cmd) => vec![ClickHandler {
+                    cmd: Some(cmd),
+                    ..ClickHandler::default()
+                }],
+                ClickHandlers::One(handler) => vec![handler],
+                ClickHandlers::Many(handlers) => handlers,
+            };
+            if handlers
+                .iter()
+                .any(|handler| handler.cmd.is_none() && handler.action.is_none())
+            {
+                return Err(de::Error::custom(
+                    "a click handler needs a `cmd`, an `action` or both",
+                ));
+            }
+            Ok((button, handlers))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::check_block;
+    use crate::config::Config;
+    use crate::formatting::FormatTemplate;
+    use crate::widgets::text::TextWidget;
+
+    /// A block that shows a number
+    struct Gauge {
+        value: i64,
+        format: FormatTemplate,
+        output: TextWidget,
+    }
+
+    impl Block for Gauge {
+        fn id(&self) -> usize {
+            0
+        }
+
+        fn view(&self) -> Vec<&dyn I3BarWidget> {
+            vec![&self.output]
+        }
+
+        fn update(&mut self) -> Result<Option<Update>> {
+            let values = map!("value" => FormatValue::from_integer(self.value));
+            self.output.set_texts(self.format.render(&values)?);
+            Ok(None)
+        }
+
+        fn click(&mut self, _: &I3BarEvent) -> Result<()> {
+            self.value = 0;
+            Ok(())
+        }
+
+        fn action(&mut self, action: &str, _: &I3BarEvent) -> Result<bool> {
+            if action == "increment" {
+                self.value += 1;
+                Ok(true)
+            } else {
+                Ok(false)
+            }
+        }
+
+        fn values(&self) -> HashMap<String, FormatValue> {
+            FormatTemplate::last_values(&[&self.format])
+        }
+    }
+
+    fn gauge(config: &str) -> BaseBlock<Gauge> {
+        let config = BaseBlockConfig::peek(&toml::from_str(config).unwrap()).unwrap();
+        let mut shared_config = SharedConfig::new(&Config::default());
+        shared_config
+            .theme_override(&map_to_owned!("critical_bg" => "#ff0000"))
+            .unwrap();
+        BaseBlock {
+            name: "gauge".to_string(),
+            inner: Gauge {
+                value: 0,
+                format: FormatTemplate::new("{value}", None).unwrap(),
+                output: TextWidget::new(0, 0, shared_config.clone()),
+            },
+            click: config.click,
+            state_rules: config.state_rules,
+            shared_config,
+            overridden: None,
+        }
+    }
+
+    fn click(button: MouseButton) -> I3BarEvent {
+        I3BarEvent {
+            button,
+            ..I3BarEvent::default()
+        }
+    }
+
+    fn background(block: &BaseBlock<Gauge>) -> Option<String> {
+        block.view()[0].get_data().background
+    }
+
+    #[test]
+    fn parse_state_rules() {
+        let block = gauge(
+            r#"state_rules = [
+                { if = "value > 90", state = "critical" },
+                { if = "!value", state = "idle" },
+            ]"#,
+        );
+        assert_eq!(block.state_rules.len(), 2);
+        assert_eq!(block.state_rules[0].condition.name, "value");
+        assert_eq!(block.state_rules[0].state, State::Critical);
+        assert_eq!(block.state_rules[1].state, State::Idle);
+
+        let peek = |rules: &str| BaseBlockConfig::peek(&toml::from_str(rules).unwrap());
+        assert!(peek(r#"state_rules = [{ if = "value >", state = "critical" }]"#).is_err());
+        assert!(peek(r#"state_rules = [{ if = "value", state = "critical", x = 1 }]"#).is_err());
+
+        // The placeholder and the state are checked against the block and the theme
+        let check = |rules: &str| {
+            check_block(
+                "load",
+                toml::from_str(rules).unwrap(),
+                SharedConfig::new(&Config::default()),
+            )
+        };
+        assert!(check(r#"state_rules = [{ if = "1m > 4", state = "critical" }]"#).is_ok());
+        assert!(check(r#"state_rules = [{ if = "nope > 4", state = "critical" }]"#).is_err());
+        assert!(check(r#"state_rules = [{ if = "1m > 4", state = "gone" }]"#).is_err());
+    }
+
+    #[test]
+    fn recolor_by_state_rules() {
+        let mut block = gauge(r#"state_rules = [{ if = "value > 90", state = "critical" }]"#);
+        block.update().unwrap();
+        assert_eq!(background(&block), None);
+
+        block.inner.value = 95;
+        block.update().unwrap();
+        assert_eq!(block.values()["value"].as_number(), Some(95.));
+        assert_eq!(background(&block).as_deref(), Some("#ff0000"));
+
+        block.inner.value = 10;
+        block.update().unwrap();
+        assert_eq!(background(&block), None);
+    }
+
+    fn handlers(config: &str) -> StdResult<HashMap<MouseButton, Vec<ClickHandler>>, String> {
+        BaseBlockConfig::peek(&toml::from_str(config).unwrap())
+            .map(|config| config.click)
+            .map_err(|error| format!("{:?}", error))
+    }
+
+    #[test]
+    fn parse_click_handlers() {
+        let click = handlers(
+            r#"[click]
+            left = "xdg-open https://example.org"
+            right = { action = "increment", update = true }
+            middle = [
+                { instance = 1, cmd = "true" },
+                { modifiers = ["Shift"], cmd = "false", action = "right" },
+            ]"#,
+        )
+        .unwrap();
+        let left = &click[&MouseButton::Left];
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].cmd.as_deref(), Some("xdg-open https://example.org"));
+        assert!(left[0].action.is_none() && !left[0].update);
+        let right = &click[&MouseButton::Right];
+        assert_eq!(right[0].action.as_deref(), Some("increment"));
+        assert!(right[0].cmd.is_none() && right[0].update);
+        let middle = &click[&MouseButton::Middle];
+        assert_eq!(middle.len(), 2);
+        assert_eq!(middle[0].instance, Some(1));
+        assert_eq!(middle[1].modifiers, ["Shift"]);
+        assert_eq!(middle[1].action.as_deref(), Some("right"));
+
+        // Mistakes in a handler are reported as such
+        let error = handlers("click.left = { cmd = \"true\", updat = true }").unwrap_err();
+        assert!(error.contains("unknown field `updat`"), "{}", error);
+        let error =
+            handlers("click.left = [{ cmd = \"true\" }, { instance = \"x\" }]").unwrap_err();
+        assert!(error.contains("invalid type: string \"x\""), "{}", error);
+        let error = handlers("click.left = 1").unwrap_err();
+        assert!(
+            error.contains("a shell command, a click handler or a list of them"),
+            "{}",
+            error
+        );
+        let error = handlers("click.left = { update = true }").unwrap_err();
+        assert!(
+            error.contains("needs a `cmd`, an `action` or both"),
+            "{}",
+            error
+        );
+        let error = handlers("click.top = \"true\"").unwrap_err();
+        assert!(error.contains("unknown mouse button 'top'"), "{}", error);
+    }
+
+    #[test]
+    fn dispatch_click_actions() {
+        let mut block = gauge(
+            r#"[click]
+            left = { action = "increment" }
+            right = [
+                { modifiers = ["Shift"], action = "nope" },
+                { action = "left" },
+            ]
+            wheel_up = { cmd = "true", action = "increment", update = true }"#,
+        );
+        block.inner.value = 5;
+
+        block.click(&click(MouseButton::Left)).unwrap();
+        assert_eq!(block.inner.value, 6);
+        // The widget only changes once the block is rendered again
+        assert!(block.view()[0].get_data().full_text.is_empty());
+        block.click(&click(MouseButton::WheelUp)).unwrap();
+        assert_eq!(block.inner.value, 7);
+        assert!(block.view()[0].get_data().full_text.contains('7'));
+
+        // Buttons without handlers and actions named after buttons go to the block's own click
+        block.click(&click(MouseButton::Middle)).unwrap();
+        assert_eq!(block.inner.value, 0);
+        block.inner.value = 3;
+        block.click(&click(MouseButton::Right)).unwrap();
+        assert_eq!(block.inner.value, 0);
+
+        let mut shifted = click(MouseButton::Right);
+        shifted.modifiers = vec!["shift".to_string()];
+        let error = block.click(&shifted).unwrap_err();
+        assert!(format!("{}", error).contains("unknown action 'nope'"));
+    }
+}
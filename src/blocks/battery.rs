@@ -4,6 +4,7 @@
 //! display the status, capacity, and time remaining for (dis)charge for an
 //! internal power supply.
 
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::thread;
 use std::time::{Duration, Instant};
@@ -17,8 +18,11 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
+use crate::formatting::unit::Unit;
 use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
+use crate::pause;
 use crate::scheduler::Task;
 use crate::util::{battery_level_to_icon, read_file};
 use crate::widgets::text::TextWidget;
@@ -388,13 +392,16 @@ impl UpowerDevice {
                     .expect("Failed to add D-Bus match rule.");
 
                 loop {
+                    pause::wait();
                     if con.incoming(10_000).next().is_some() {
-                        update_request
-                            .send(Task {
-                                id,
-                                update_time: Instant::now(),
-                            })
-                            .unwrap();
+                        let task = Task {
+                            id,
+                            update_time: Instant::now(),
+                        };
+                        if update_request.send(task).is_err() {
+                            // The block is gone
+                            break;
+                        }
                         // Avoid update spam.
                         // TODO: Is this necessary?
                         thread::sleep(Duration::from_millis(1000))
@@ -596,6 +603,12 @@ impl Default for BatteryConfig {
 impl ConfigBlock for Battery {
     type Config = BatteryConfig;
 
+    const PLACEHOLDERS: Schema = &[
+        Field::integer("percentage", Unit::Percents),
+        Field::text("time"),
+        Field::float("power", Unit::Watts),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -605,7 +618,9 @@ impl ConfigBlock for Battery {
         let device: Box<dyn BatteryDevice> = match block_config.driver {
             BatteryDriver::Upower => {
                 let out = UpowerDevice::from_device(&block_config.device)?;
-                out.monitor(id, update_request);
+                if !shared_config.dry_run {
+                    out.monitor(id, update_request);
+                }
                 Box::new(out)
             }
             BatteryDriver::Sysfs => Box::new(PowerSupplyDevice::from_device(
@@ -695,11 +710,11 @@ impl Block for Battery {
         } else {
             self.output.set_texts(self.format.render(&values)?);
 
-            // Check if the battery is in charging mode and change the state to Good.
+            // Check if the battery is in charging mode and change the state to charging.
             // Otherwise, adjust the state depeding the power percentance.
             match status.as_str() {
                 "Charging" => {
-                    self.output.set_state(State::Good);
+                    self.output.set_state(State::Custom("charging"));
                 }
                 _ => {
                     self.output.set_state(match capacity {
@@ -744,6 +759,10 @@ impl Block for Battery {
         vec![&self.output]
     }
 
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[&self.format, &self.full_format, &self.missing_format])
+    }
+
     fn id(&self) -> usize {
         self.id
     }
@@ -1,8 +1,9 @@
 use serde_derive::Deserialize;
 
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use crossbeam_channel::Sender;
 use dbus::{
@@ -14,10 +15,13 @@ use dbus::{
 use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
+use crate::formatting::unit::Unit;
 use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
+use crate::pause;
 use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
-use crate::scheduler::Task;
+use crate::scheduler::{Task, UpdateRequester};
 use crate::widgets::text::TextWidget;
 use crate::widgets::{I3BarWidget, State};
 
@@ -154,9 +158,10 @@ impl BluetoothDevice {
         let path_copy2 = self.path.clone();
         let avail_copy1 = self.available.clone();
         let avail_copy2 = self.available.clone();
+        let update_request = UpdateRequester::new(id, update_request);
         let update_request_copy1 = update_request.clone();
         let update_request_copy2 = update_request.clone();
-        let update_request_copy3 = update_request;
+        let update_request_copy3 = update_request.clone();
 
         thread::Builder::new().name("bluetooth".into()).spawn(move || {
             let c = dbus::blocking::Connection::new_system().unwrap();
@@ -166,12 +171,7 @@ impl BluetoothDevice {
                 if ia.object == path_copy1.clone().into() {
                     let mut avail = avail_copy1.lock().unwrap();
                     *avail = true;
-                    update_request_copy1
-                        .send(Task {
-                            id,
-                            update_time: Instant::now(),
-                        })
-                        .unwrap();
+                    update_request_copy1.request();
                 }
                 true
             })
@@ -183,12 +183,7 @@ impl BluetoothDevice {
                 if ir.object == path_copy2.clone().into() {
                     let mut avail = avail_copy2.lock().unwrap();
                     *avail = false;
-                    update_request_copy2
-                        .send(Task {
-                            id,
-                            update_time: Instant::now(),
-                        })
-                        .unwrap();
+                    update_request_copy2.request();
                 }
                 true
             })
@@ -198,17 +193,14 @@ impl BluetoothDevice {
             let mr = PPC::match_rule(Some(&"org.bluez".into()), None).static_clone();
             // TODO: get updated values from the signal message
             c.add_match(mr, move |_ppc: PPC, _, _| {
-                update_request_copy3
-                    .send(Task {
-                        id,
-                        update_time: Instant::now(),
-                    })
-                    .unwrap();
+                update_request_copy3.request();
                 true
             })
             .unwrap();
 
-            loop {
+            // Until the block is gone
+            while !update_request.is_gone() {
+                pause::wait();
                 c.process(Duration::from_millis(1000)).unwrap();
             }
         }).unwrap();
@@ -242,6 +234,11 @@ pub struct BluetoothConfig {
 impl ConfigBlock for Bluetooth {
     type Config = BluetoothConfig;
 
+    const PLACEHOLDERS: Schema = &[
+        Field::text("label"),
+        Field::integer("percentage", Unit::Percents),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -249,7 +246,9 @@ impl ConfigBlock for Bluetooth {
         send: Sender<Task>,
     ) -> Result<Self> {
         let device = BluetoothDevice::new(block_config.mac, block_config.label)?;
-        device.monitor(id, send);
+        if !shared_config.dry_run {
+            device.monitor(id, send);
+        }
 
         Ok(Bluetooth {
             id,
@@ -269,6 +268,10 @@ impl ConfigBlock for Bluetooth {
 }
 
 impl Block for Bluetooth {
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[&self.format, &self.format_unavailable])
+    }
+
     fn id(&self) -> usize {
         self.id
     }
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fs::{read_to_string, File};
 use std::io::prelude::*;
 use std::io::BufReader;
@@ -10,6 +11,8 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
+use crate::formatting::unit::Unit;
 use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
 use crate::scheduler::Task;
@@ -64,6 +67,15 @@ impl Default for CpuConfig {
 impl ConfigBlock for Cpu {
     type Config = CpuConfig;
 
+    const PLACEHOLDERS: Schema = &[
+        Field::integer("utilization", Unit::Percents),
+        Field::integer("utilization<n>", Unit::Percents),
+        Field::float("frequency", Unit::Hertz),
+        Field::float("frequency<n>", Unit::Hertz),
+        Field::text("barchart"),
+        Field::text("boost"),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -212,6 +224,10 @@ impl Block for Cpu {
         vec![&self.output]
     }
 
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[&self.format])
+    }
+
     fn id(&self) -> usize {
         self.id
     }
@@ -11,6 +11,7 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_update;
 use crate::errors::*;
+use crate::formatting::schema::Schema;
 use crate::protocol::i3bar_event::I3BarEvent;
 use crate::scheduler::Task;
 use crate::signals::convert_to_valid_signal;
@@ -31,6 +32,7 @@ pub struct Custom {
     hide_when_empty: bool,
     is_empty: bool,
     shell: String,
+    shared_config: SharedConfig,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -75,6 +77,8 @@ impl Default for CustomConfig {
 impl ConfigBlock for Custom {
     type Config = CustomConfig;
 
+    const PLACEHOLDERS: Schema = &[];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -84,7 +88,7 @@ impl ConfigBlock for Custom {
         let mut custom = Custom {
             id,
             update_interval: block_config.interval,
-            output: TextWidget::new(id, 0, shared_config),
+            output: TextWidget::new(id, 0, shared_config.clone()),
             command: None,
             on_click: None,
             cycle: None,
@@ -94,6 +98,7 @@ impl ConfigBlock for Custom {
             hide_when_empty: block_config.hide_when_empty,
             is_empty: true,
             shell: block_config.shell,
+            shared_config,
         };
 
         if let Some(signal) = block_config.signal {
@@ -164,6 +169,15 @@ impl Block for Custom {
             let output: Output = serde_json::from_str(&*raw_output).map_err(|e| {
                 BlockError("custom".to_string(), format!("Error parsing JSON: {}", e))
             })?;
+            if !self.shared_config.theme.has_state(output.state) {
+                return Err(BlockError(
+                    "custom".to_string(),
+                    format!(
+                        "State '{}' is not declared in the theme",
+                        output.state.name()
+                    ),
+                ));
+            }
             if output.icon.is_empty() {
                 self.output.unset_icon();
             } else {
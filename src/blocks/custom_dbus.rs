@@ -1,18 +1,20 @@
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use crossbeam_channel::Sender;
 use dbus::blocking::LocalConnection;
 use dbus::strings::Signature;
+use dbus::MethodErr;
 use dbus_tree::Factory;
 use serde_derive::Deserialize;
 
 use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::errors::*;
-use crate::scheduler::Task;
+use crate::formatting::schema::Schema;
+use crate::scheduler::{Task, UpdateRequester};
 use crate::widgets::text::TextWidget;
 use crate::widgets::{I3BarWidget, State};
 
@@ -38,6 +40,8 @@ pub struct CustomDBusConfig {
 impl ConfigBlock for CustomDBus {
     type Config = CustomDBusConfig;
 
+    const PLACEHOLDERS: Schema = &[];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -51,6 +55,15 @@ impl ConfigBlock for CustomDBus {
         }));
         let status = status_original.clone();
         let name = block_config.name;
+        let dry_run = shared_config.dry_run;
+        let theme = shared_config.theme.clone();
+        let text = TextWidget::new(id, 0, shared_config).with_text("CustomDBus");
+        if dry_run {
+            return Ok(CustomDBus { id, text, status });
+        }
+        let send = UpdateRequester::new(id, send);
+        let update_request = send.clone();
+
         thread::Builder::new()
             .name("custom_dbus".into())
             .spawn(move || {
@@ -74,6 +87,20 @@ impl ConfigBlock for CustomDBus {
                                         // messages to send back.
 
                                         let args = m.msg.get3::<&str, &str, &str>();
+                                        let new_state = match args.2 {
+                                            Some(new_state) => Some(
+                                                State::from_str(new_state)
+                                                    .ok()
+                                                    .filter(|state| theme.has_state(*state))
+                                                    .ok_or_else(|| {
+                                                        MethodErr::failed(&format!(
+                                                        "State '{}' is not declared in the theme",
+                                                        new_state
+                                                    ))
+                                                    })?,
+                                            ),
+                                            None => None,
+                                        };
                                         let mut status = status_original.lock().unwrap();
 
                                         if let Some(new_content) = args.0 {
@@ -84,17 +111,15 @@ impl ConfigBlock for CustomDBus {
                                             status.icon = String::from(new_icon);
                                         }
 
-                                        if let Some(new_state) = args.2 {
-                                            status.state =
-                                                State::from_str(new_state).unwrap_or(status.state);
+                                        if let Some(new_state) = new_state {
+                                            status.state = new_state;
                                         }
 
                                         // Tell block to update now.
-                                        send.send(Task {
-                                            id,
-                                            update_time: Instant::now(),
-                                        })
-                                        .unwrap();
+                                        send.request();
+                                        if send.is_gone() {
+                                            return Err(MethodErr::failed(&"the block is gone"));
+                                        }
 
                                         Ok(vec![m.msg.method_return()])
                                     })
@@ -112,14 +137,14 @@ impl ConfigBlock for CustomDBus {
                 // We add the tree to the connection so that incoming method calls will be handled.
                 tree.start_receive(&c);
 
-                // Serve clients forever.
-                loop {
+                // Serve clients until the block is gone. This gives up the bus name, so that a
+                // block that replaces this one on a config reload gets it.
+                while !update_request.is_gone() {
                     c.process(Duration::from_millis(1000)).unwrap();
                 }
             })
             .unwrap();
 
-        let text = TextWidget::new(id, 0, shared_config).with_text("CustomDBus");
         Ok(CustomDBus { id, text, status })
     }
 }
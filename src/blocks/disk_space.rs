@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::path::Path;
 use std::time::Duration;
 
@@ -9,6 +10,8 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
+use crate::formatting::unit::Unit;
 use crate::formatting::FormatTemplate;
 use crate::formatting::{prefix::Prefix, value::Value};
 use crate::scheduler::Task;
@@ -126,6 +129,17 @@ impl DiskSpace {
 impl ConfigBlock for DiskSpace {
     type Config = DiskSpaceConfig;
 
+    const PLACEHOLDERS: Schema = &[
+        Field::text("alias"),
+        Field::text("path"),
+        Field::text("icon"),
+        Field::float("available", Unit::Bytes),
+        Field::float("free", Unit::Bytes),
+        Field::float("used", Unit::Bytes),
+        Field::float("total", Unit::Bytes),
+        Field::float("percentage", Unit::Percents),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -230,6 +244,10 @@ impl Block for DiskSpace {
         vec![&self.disk_space]
     }
 
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[&self.format])
+    }
+
     fn id(&self) -> usize {
         self.id
     }
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::time::Duration;
 
 use crossbeam_channel::Sender;
@@ -7,6 +8,8 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
+use crate::formatting::unit::Unit;
 use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
 use crate::http;
@@ -62,6 +65,14 @@ impl Default for DockerConfig {
 impl ConfigBlock for Docker {
     type Config = DockerConfig;
 
+    const PLACEHOLDERS: Schema = &[
+        Field::integer("total", Unit::None),
+        Field::integer("running", Unit::None),
+        Field::integer("paused", Unit::None),
+        Field::integer("stopped", Unit::None),
+        Field::integer("images", Unit::None),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -110,6 +121,10 @@ impl Block for Docker {
         vec![&self.text]
     }
 
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[&self.format])
+    }
+
     fn id(&self) -> usize {
         self.id
     }
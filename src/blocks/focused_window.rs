@@ -10,6 +10,8 @@ use swayipc::{Connection, EventType};
 use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::errors::*;
+use crate::formatting::schema::Schema;
+use crate::pause;
 use crate::scheduler::Task;
 use crate::util::escape_pango_text;
 use crate::widgets::text::TextWidget;
@@ -54,6 +56,8 @@ impl Default for FocusedWindowConfig {
 impl ConfigBlock for FocusedWindow {
     type Config = FocusedWindowConfig;
 
+    const PLACEHOLDERS: Schema = &[];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -126,6 +130,21 @@ impl ConfigBlock for FocusedWindow {
         let _test_conn =
             Connection::new().block_error("focused_window", "failed to acquire connect to IPC")?;
 
+        let dry_run = shared_config.dry_run;
+        let text = TextWidget::new(id, 0, shared_config);
+        let focused_window = FocusedWindow {
+            id,
+            text,
+            max_width: block_config.max_width,
+            show_marks: block_config.show_marks,
+            title,
+            marks,
+        };
+
+        if dry_run {
+            return Ok(focused_window);
+        }
+
         thread::Builder::new()
             .name("focused_window".into())
             .spawn(move || {
@@ -136,6 +155,7 @@ impl ConfigBlock for FocusedWindow {
                     .expect("could not subscribe to window events");
 
                 for event in events {
+                    pause::wait();
                     let updated = match event.expect("could not read event in `window` block") {
                         Event::Window(e) => match (e.change, e.container) {
                             (WindowChange::Mark, Node { marks, .. }) => update_marks(marks),
@@ -166,26 +186,22 @@ impl ConfigBlock for FocusedWindow {
                         _ => false,
                     };
 
-                    if updated {
-                        tx.send(Task {
-                            id,
-                            update_time: Instant::now(),
-                        })
-                        .expect("could not communicate with channel in `window` block");
+                    if updated
+                        && tx
+                            .send(Task {
+                                id,
+                                update_time: Instant::now(),
+                            })
+                            .is_err()
+                    {
+                        // The block is gone
+                        break;
                     }
                 }
             })
             .expect("failed to start watching thread for `window` block");
 
-        let text = TextWidget::new(id, 0, shared_config);
-        Ok(FocusedWindow {
-            id,
-            text,
-            max_width: block_config.max_width,
-            show_marks: block_config.show_marks,
-            title,
-            marks,
-        })
+        Ok(focused_window)
     }
 }
 
@@ -196,6 +212,7 @@ impl Block for FocusedWindow {
             .lock()
             .block_error("focused_window", "failed to acquire lock")?)
         .clone();
+        // This block has no format to apply the `trunc` modifier to, so `max_width` stays
         marks_string = marks_string.chars().take(self.max_width).collect();
         let mut title_string = (*self
             .title
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
 use std::io::{self, Cursor, ErrorKind};
 use std::ops::{Add, Sub};
@@ -11,6 +12,8 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
+use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
 use crate::scheduler::Task;
 use crate::widgets::text::TextWidget;
@@ -99,6 +102,8 @@ impl Default for GDQConfig {
 impl ConfigBlock for GDQ {
     type Config = GDQConfig;
 
+    const PLACEHOLDERS: Schema = &[Field::text("name")];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -118,6 +123,10 @@ impl ConfigBlock for GDQ {
 }
 
 impl Block for GDQ {
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[&self.format])
+    }
+
     fn update(&mut self) -> Result<Option<Update>> {
         let r = match ureq::get("https://gamesdonequick.com/schedule").call() {
             Ok(r) => r,
@@ -206,11 +215,15 @@ impl Block for GDQ {
         };
         let current = entries.remove(current_index);
         let next = entries.iter().find(|e| e.start_time.gt(&now));
-        self.text.set_text(format!(
+        let name = format!(
             "{} -> {}",
             current.title,
             next.map(|c| c.title.clone()).unwrap_or("None".to_string()),
-        ));
+        );
+        let values = map!(
+            "name" => Value::from_string(name)
+        );
+        self.text.set_texts(self.format.render(&values)?);
 
         Ok(Some(self.update_interval.into()))
     }
@@ -10,6 +10,8 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
+use crate::formatting::unit::Unit;
 use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
 use crate::http;
@@ -59,6 +61,21 @@ impl Default for GithubConfig {
 impl ConfigBlock for Github {
     type Config = GithubConfig;
 
+    const PLACEHOLDERS: Schema = &[
+        Field::integer("total", Unit::None),
+        Field::integer("author", Unit::None),
+        Field::integer("comment", Unit::None),
+        Field::integer("mention", Unit::None),
+        Field::integer("review_requested", Unit::None),
+        Field::integer("team_mention", Unit::None),
+        Field::integer("state_change", Unit::None),
+        Field::integer("subscribed", Unit::None),
+        Field::integer("manual", Unit::None),
+        Field::integer("invitation", Unit::None),
+        Field::integer("assign", Unit::None),
+        Field::integer("security_alert", Unit::None),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -137,6 +154,10 @@ impl Block for Github {
         }
     }
 
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[&self.format])
+    }
+
     fn id(&self) -> usize {
         self.id
     }
@@ -9,6 +9,7 @@ use crate::config::SharedConfig;
 use crate::config::{LogicalDirection, Scrolling};
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::schema::Schema;
 use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
 use crate::util::has_command;
@@ -157,6 +158,8 @@ impl Default for HueshiftConfig {
 impl ConfigBlock for Hueshift {
     type Config = HueshiftConfig;
 
+    const PLACEHOLDERS: Schema = &[];
+
     fn new(
         id: usize,
         block_config: Self::Config,
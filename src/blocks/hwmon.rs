@@ -0,0 +1,369 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
+use crate::formatting::unit::Unit;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::hwmon::{self, Chip, Kind, Sensor};
+use crate::scheduler::Task;
+use crate::widgets::{text::TextWidget, I3BarWidget, State};
+
+/// An input the block shows
+struct Input {
+    /// The lm-sensors name of the chip
+    chip: String,
+    /// The name of the input, like `fan1`
+    name: String,
+    output: TextWidget,
+}
+
+pub struct Hwmon {
+    id: usize,
+    update_interval: Duration,
+    format: FormatTemplate,
+    chip: Option<String>,
+    inputs: Option<Vec<String>>,
+    /// The kinds of inputs shown, with energy counters if power is shown
+    kinds: Vec<Kind>,
+    /// The inputs shown, one widget each
+    shown: Vec<Input>,
+    /// The widget instance of the next input
+    next_instance: usize,
+    /// The last reading of every energy counter, when it was read and the power it gave, by chip
+    /// and input
+    energy: HashMap<(String, String), (f64, Instant, f64)>,
+    shared_config: SharedConfig,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct HwmonConfig {
+    /// Update interval in seconds
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// Format override
+    pub format: FormatTemplate,
+
+    /// Chip override, a name or glob
+    pub chip: Option<String>,
+
+    /// Inputs whitelist, names, labels or globs
+    pub inputs: Option<Vec<String>>,
+
+    /// The kinds of inputs to show. Energy counters are shown as power.
+    pub kinds: Vec<Kind>,
+}
+
+impl Default for HwmonConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(5),
+            format: FormatTemplate::default(),
+            chip: None,
+            inputs: None,
+            kinds: vec![Kind::Fan, Kind::Voltage, Kind::Current, Kind::Power],
+        }
+    }
+}
+
+impl ConfigBlock for Hwmon {
+    type Config = HwmonConfig;
+
+    const PLACEHOLDERS: Schema = &[
+        Field::text("label"),
+        Field::text("chip"),
+        // The unit is that of the kind of the input, so it can't be converted
+        Field::float("value", Unit::None),
+        // Only the placeholder of the kind of the input is set
+        Field::float("degrees", Unit::Degrees),
+        Field::integer("rpm", Unit::Rpm),
+        Field::float("volts", Unit::Volts),
+        Field::float("amperes", Unit::Amperes),
+        Field::float("watts", Unit::Watts),
+        Field::float("humidity", Unit::Percents),
+    ];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        let format = block_config.format.with_default("{label} {value}")?;
+        let mut kinds = block_config.kinds;
+        if kinds.contains(&Kind::Power) && !kinds.contains(&Kind::Energy) {
+            kinds.push(Kind::Energy);
+        }
+        // The placeholder of a kind is missing for the inputs of the other kinds
+        for &kind in &kinds {
+            if let Some(other) = kinds
+                .iter()
+                .map(|&other| placeholder(other))
+                .find(|&other| other != placeholder(kind) && format.contains(other))
+            {
+                return Err(ConfigurationError(
+                    format!(
+                        "'{}' can't be used in the format of {} inputs, limit 'kinds' or use \
+                         'value'",
+                        other,
+                        format!("{:?}", kind).to_lowercase()
+                    ),
+                    String::new(),
+                ));
+            }
+        }
+
+        let mut hwmon = Hwmon {
+            id,
+            update_interval: block_config.interval,
+            format,
+            chip: block_config.chip,
+            inputs: block_config.inputs,
+            kinds,
+            shown: Vec::new(),
+            next_instance: 0,
+            energy: HashMap::new(),
+            shared_config,
+        };
+        // Read the energy counters once, so that the first update can already tell the power
+        if hwmon.kinds.contains(&Kind::Energy) {
+            if let Ok(chips) = hwmon.chips() {
+                let counters: Vec<(&str, &Sensor)> = hwmon::select(
+                    &chips,
+                    &[Kind::Energy],
+                    hwmon.chip.as_deref(),
+                    hwmon.inputs.as_deref(),
+                )
+                .collect();
+                for (chip, sensor) in counters {
+                    hwmon.power(chip, sensor);
+                }
+            }
+        }
+        Ok(hwmon)
+    }
+}
+
+/// The placeholder with the value of an input of `kind`
+fn placeholder(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Temperature => "degrees",
+        Kind::Fan => "rpm",
+        Kind::Voltage => "volts",
+        Kind::Current => "amperes",
+        Kind::Power | Kind::Energy => "watts",
+        Kind::Humidity => "humidity",
+    }
+}
+
+impl Hwmon {
+    /// The chips of hwmon and, if energy counters are shown, the power capping zones
+    fn chips(&self) -> Result<Vec<Chip>> {
+        let mut chips = hwmon::chips(Path::new(hwmon::HWMON))?;
+        if self.kinds.contains(&Kind::Energy) {
+            chips.push(hwmon::powercap(Path::new(hwmon::POWERCAP)));
+        }
+        Ok(chips)
+    }
+
+    /// The power the energy counter `sensor` was drawn at since it was last read, in watts
+    fn power(&mut self, chip: &str, sensor: &Sensor) -> f64 {
+        let now = Instant::now();
+        let key = (chip.to_string(), sensor.name.clone());
+        let power = match self.energy.get(&key) {
+            Some(&(last, at, _)) if sensor.input >= last => {
+                (sensor.input - last) / now.duration_since(at).as_secs_f64().max(0.001)
+            }
+            // The counter wrapped around
+            Some(&(_, _, power)) => power,
+            None => 0.,
+        };
+        self.energy.insert(key, (sensor.input, now, power));
+        power
+    }
+
+    /// The value of `sensor` as a placeholder, in the unit of its kind
+    fn value(&mut self, chip: &str, sensor: &Sensor) -> Value {
+        match sensor.kind {
+            Kind::Temperature => Value::from_float(sensor.input).degrees(),
+            Kind::Fan => Value::from_integer(sensor.input as i64).rpm(),
+            Kind::Voltage => Value::from_float(sensor.input).volts(),
+            Kind::Current => Value::from_float(sensor.input).amperes(),
+            Kind::Power => Value::from_float(sensor.input).watts(),
+            Kind::Energy => Value::from_float(self.power(chip, sensor)).watts(),
+            Kind::Humidity => Value::from_float(sensor.input).percents(),
+        }
+    }
+}
+
+impl Block for Hwmon {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let chips = self.chips()?;
+        let mut shown = std::mem::take(&mut self.shown);
+
+        let inputs: Vec<(&str, &Sensor)> = hwmon::select(
+            &chips,
+            &self.kinds,
+            self.chip.as_deref(),
+            self.inputs.as_deref(),
+        )
+        .collect();
+        for (chip, sensor) in inputs {
+            let mut input = match shown
+                .iter()
+                .position(|input| input.chip == chip && input.name == sensor.name)
+            {
+                Some(i) => shown.remove(i),
+                None => {
+                    self.next_instance += 1;
+                    Input {
+                        chip: chip.to_string(),
+                        name: sensor.name.clone(),
+                        output: TextWidget::new(
+                            self.id,
+                            self.next_instance - 1,
+                            self.shared_config.clone(),
+                        ),
+                    }
+                }
+            };
+
+            let value = self.value(chip, sensor);
+            let values = map!(
+                "label" => Value::from_string(sensor.label().to_string()),
+                "chip" => Value::from_string(chip.to_string()),
+                placeholder(sensor.kind) => value.clone(),
+                "value" => value
+            );
+            input.output.set_texts(self.format.render(&values)?);
+            input.output.set_state(match (sensor.max, sensor.crit) {
+                (_, Some(crit)) if sensor.input > crit => State::Critical,
+                (Some(max), _) if sensor.input > max => State::Warning,
+                _ => State::Idle,
+            });
+            self.shown.push(input);
+        }
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        self.shown
+            .iter()
+            .map(|input| &input.output as &dyn I3BarWidget)
+            .collect()
+    }
+
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[&self.format])
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::check_block;
+    use crate::config::Config;
+    use crate::hwmon::tests::{block, hwmon_tree};
+
+    #[test]
+    fn select_inputs() {
+        let sys = hwmon_tree();
+        let mut chips = hwmon::chips(&sys.path().join("hwmon")).unwrap();
+        chips.push(hwmon::powercap(&sys.path().join("powercap")));
+        let select = |config: &str| -> Vec<String> {
+            let block: Hwmon = block(config);
+            hwmon::select(
+                &chips,
+                &block.kinds,
+                block.chip.as_deref(),
+                block.inputs.as_deref(),
+            )
+            .map(|(chip, sensor)| format!("{} {}", chip, sensor.label()))
+            .collect()
+        };
+
+        assert_eq!(
+            select(""),
+            [
+                "ina219-i2c-1-48 fan1",
+                "ina219-i2c-1-48 in0",
+                "ina219-i2c-1-48 in1",
+                "ina219-i2c-1-48 curr1",
+                "ina219-i2c-1-48 power1",
+                "powercap package-0",
+                "powercap core",
+            ]
+        );
+        assert_eq!(
+            select("kinds = [\"voltage\"]\ninputs = [\"in1\"]"),
+            ["ina219-i2c-1-48 in1"]
+        );
+        assert_eq!(
+            select("chip = \"powercap\"\ninputs = [\"package-*\"]"),
+            ["powercap package-0"]
+        );
+        assert_eq!(
+            select("kinds = [\"temperature\"]\nchip = \"nvme\"").len(),
+            1
+        );
+    }
+
+    #[test]
+    fn kind_placeholders() {
+        let check = |config: &str| {
+            check_block(
+                "hwmon",
+                toml::from_str(config).unwrap(),
+                SharedConfig::new(&Config::default()),
+            )
+        };
+        assert!(check("format = \"{label} {value}\"").is_ok());
+        assert!(check("kinds = [\"power\"]\nformat = \"{label} {watts*W;m}\"").is_ok());
+        assert!(check("kinds = [\"fan\"]\nformat = \"{rpm:5}\"").is_ok());
+        // The value of the input is in the unit of its kind
+        assert!(check("kinds = [\"power\"]\nformat = \"{value*W}\"").is_err());
+        // Fans have no watts
+        assert!(check("kinds = [\"fan\", \"power\"]\nformat = \"{watts}\"").is_err());
+        assert!(check("format = \"{volts}\"").is_err());
+    }
+
+    #[test]
+    fn energy_to_power() {
+        let mut block: Hwmon = block("");
+        let mut package = Sensor {
+            kind: Kind::Energy,
+            name: "intel-rapl:0".to_string(),
+            label: Some("package-0".to_string()),
+            input: 1000.,
+            max: None,
+            crit: None,
+        };
+        assert_eq!(block.power("powercap", &package), 0.);
+
+        // 30 J in the two seconds since the last reading
+        let key = ("powercap".to_string(), package.name.clone());
+        block.energy.get_mut(&key).unwrap().1 -= Duration::from_secs(2);
+        package.input = 1030.;
+        let power = block.power("powercap", &package);
+        assert!((power - 15.).abs() < 0.1, "{}", power);
+
+        // The counter wrapped around, so the last power is kept
+        package.input = 12.;
+        assert_eq!(block.power("powercap", &package), power);
+    }
+}
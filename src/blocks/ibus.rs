@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fs::{read_dir, File};
 use std::io::prelude::*;
@@ -21,8 +21,10 @@ use serde_derive::Deserialize;
 use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
 use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
+use crate::pause;
 use crate::protocol::i3bar_event::I3BarEvent;
 use crate::scheduler::Task;
 use crate::util::xdg_config_home;
@@ -56,6 +58,8 @@ impl Default for IBusConfig {
 impl ConfigBlock for IBus {
     type Config = IBusConfig;
 
+    const PLACEHOLDERS: Schema = &[Field::text("engine")];
+
     #[allow(clippy::many_single_char_names)]
     fn new(
         id: usize,
@@ -91,44 +95,6 @@ impl ConfigBlock for IBus {
         let available = Arc::new((Mutex::new(running), Condvar::new()));
         let available_copy = available.clone();
         let engine_copy = engine_original.clone();
-        thread::Builder::new().name("ibus-daemon-monitor".into()).spawn(move || {
-            let c = Connection::get_private(BusType::Session).unwrap();
-            c.add_match("interface='org.freedesktop.DBus',member='NameOwnerChanged',path='/org/freedesktop/DBus',arg0namespace='org.freedesktop.IBus'")
-                .unwrap();
-            // Skip the NameAcquired event.
-            c.incoming(10_000).next();
-            loop {
-                for ci in c.iter(100_000) {
-                    if let ConnectionItem::Signal(x) = ci {
-                    	let (name, old_owner, new_owner): (&str, &str, &str) = x.read3().unwrap();
-						if name.contains("IBus") && !old_owner.is_empty() && new_owner.is_empty() {
-							let (lock, cvar) = &*available_copy;
-							let mut available = lock.lock().unwrap();
-							*available = false;
-							cvar.notify_one();
-                            let mut engine = engine_copy.lock().unwrap();
-                            // see comment on L167
-                            *engine = "Reload the bar!".to_string();
-							send2.send(Task {
-								id,
-								update_time: Instant::now(),
-							}).unwrap();
-						} else if name.contains("IBus") && old_owner.is_empty() && !new_owner.is_empty() {
-							let (lock, cvar) = &*available_copy;
-							let mut available = lock.lock().unwrap();
-							*available = true;
-							cvar.notify_one();
-
-							send2.send(Task {
-						   		id,
-						   		update_time: Instant::now(),
-							}).unwrap();
-						}
-                    }
-                }
-            }
-        }).unwrap();
-
         let current_engine: String = if running {
             let ibus_address = get_ibus_address()?;
             let c = Connection::open_private(&ibus_address).block_error(
@@ -169,6 +135,67 @@ impl ConfigBlock for IBus {
         let mut engine = engine_copy2.lock().unwrap();
         *engine = current_engine;
 
+        let dry_run = shared_config.dry_run;
+        let text = TextWidget::new(id, 0, shared_config).with_text("IBus");
+        let ibus = IBus {
+            id,
+            text,
+            engine: engine_original.clone(),
+            mappings: block_config.mappings,
+            format: block_config.format.with_default("{engine}")?,
+        };
+
+        if dry_run {
+            return Ok(ibus);
+        }
+
+        thread::Builder::new().name("ibus-daemon-monitor".into()).spawn(move || {
+            let c = Connection::get_private(BusType::Session).unwrap();
+            c.add_match("interface='org.freedesktop.DBus',member='NameOwnerChanged',path='/org/freedesktop/DBus',arg0namespace='org.freedesktop.IBus'")
+                .unwrap();
+            // Skip the NameAcquired event.
+            c.incoming(10_000).next();
+            loop {
+                for ci in c.iter(100_000) {
+                    pause::wait();
+                    if let ConnectionItem::Signal(x) = ci {
+                    	let (name, old_owner, new_owner): (&str, &str, &str) = x.read3().unwrap();
+						if name.contains("IBus") && !old_owner.is_empty() && new_owner.is_empty() {
+							let (lock, cvar) = &*available_copy;
+							let mut available = lock.lock().unwrap();
+							*available = false;
+							cvar.notify_one();
+                            let mut engine = engine_copy.lock().unwrap();
+                            // see comment on L167
+                            *engine = "Reload the bar!".to_string();
+							let task = Task {
+							    id,
+							    update_time: Instant::now(),
+							};
+							if send2.send(task).is_err() {
+							    // The block is gone
+							    return;
+							}
+						} else if name.contains("IBus") && old_owner.is_empty() && !new_owner.is_empty() {
+							let (lock, cvar) = &*available_copy;
+							let mut available = lock.lock().unwrap();
+							*available = true;
+							cvar.notify_one();
+
+							let task = Task {
+							    id,
+							    update_time: Instant::now(),
+							};
+							if send2.send(task).is_err() {
+							    // The block is gone
+							    return;
+							}
+						}
+                    }
+                }
+            }
+        }).unwrap();
+
         let engine_copy3 = engine_original.clone();
         thread::Builder::new()
             .name("ibus-engine-monitor".into())
@@ -192,33 +219,34 @@ impl ConfigBlock for IBus {
                     .expect("Failed to add D-Bus message rule - has IBus interface changed?");
                 loop {
                     for ci in c.iter(100_000) {
+                        pause::wait();
                         if let Some(engine_name) = parse_msg(&ci) {
                             let mut engine = engine_copy3.lock().unwrap();
                             *engine = engine_name.to_string();
                             // Tell block to update now.
-                            send.send(Task {
+                            let task = Task {
                                 id,
                                 update_time: Instant::now(),
-                            })
-                            .unwrap();
+                            };
+                            if send.send(task).is_err() {
+                                // The block is gone
+                                return;
+                            }
                         };
                     }
                 }
             })
             .unwrap();
 
-        let text = TextWidget::new(id, 0, shared_config).with_text("IBus");
-        Ok(IBus {
-            id,
-            text,
-            engine: engine_original,
-            mappings: block_config.mappings,
-            format: block_config.format.with_default("{engine}")?,
-        })
+        Ok(ibus)
     }
 }
 
 impl Block for IBus {
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[&self.format])
+    }
+
     fn id(&self) -> usize {
         self.id
     }
@@ -1,7 +1,8 @@
+use std::collections::HashMap;
 use std::process::Command;
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use crossbeam_channel::Sender;
 use dbus::arg;
@@ -12,9 +13,12 @@ use serde_derive::Deserialize;
 use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
+use crate::formatting::unit::Unit;
 use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
-use crate::scheduler::Task;
+use crate::pause;
+use crate::scheduler::{Task, UpdateRequester};
 use crate::util::battery_level_to_icon;
 use crate::widgets::text::TextWidget;
 use crate::widgets::{I3BarWidget, State};
@@ -80,12 +84,24 @@ impl Default for KDEConnectConfig {
 impl ConfigBlock for KDEConnect {
     type Config = KDEConnectConfig;
 
+    const PLACEHOLDERS: Schema = &[
+        Field::text("bat_icon"),
+        Field::integer("bat_charge", Unit::Percents),
+        Field::text("bat_state"),
+        Field::text("notif_icon"),
+        Field::integer("notif_count", Unit::None),
+        Field::text("name"),
+        Field::text("id"),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
         shared_config: SharedConfig,
         send: Sender<Task>,
     ) -> Result<Self> {
+        let send = UpdateRequester::new(id, send);
+        let update_request = send.clone();
         let send2 = send.clone();
         let send3 = send.clone();
         let send4 = send.clone();
@@ -140,20 +156,22 @@ impl ConfigBlock for KDEConnect {
         //
         // Starting with kdeconnect v20.11.80, the version output by the cli
         // matches the versioning scheme used by Ubuntu, where as before that it
-        // was  1.3.x or 1.4.x.
-        let old_kdeconnect = Command::new("kdeconnect-cli")
-            .args(&["--version"])
-            .output()
-            .block_error(
-                "kdeconnect",
-                "Failed to check kdeconnect version. Is it installed?",
-            )
-            .and_then(|raw_output| {
-                String::from_utf8(raw_output.stdout)
-                    .block_error("kdeconnect", "Failed to check kdeconnect version.")
-            })
-            .unwrap()
-            .contains("kdeconnect-cli 1.");
+        // was  1.3.x or 1.4.x. A dry run does not start kdeconnect-cli and assumes a newer
+        // version.
+        let old_kdeconnect = !shared_config.dry_run
+            && Command::new("kdeconnect-cli")
+                .args(&["--version"])
+                .output()
+                .block_error(
+                    "kdeconnect",
+                    "Failed to check kdeconnect version. Is it installed?",
+                )
+                .and_then(|raw_output| {
+                    String::from_utf8(raw_output.stdout)
+                        .block_error("kdeconnect", "Failed to check kdeconnect version.")
+                })
+                .unwrap()
+                .contains("kdeconnect-cli 1.");
 
         let initial_charge = if old_kdeconnect {
             let (charge,): (i32,) = p2
@@ -243,6 +261,32 @@ impl ConfigBlock for KDEConnect {
         //    Arc::new(Mutex::new(initial_notifications.get(0).unwrap().to_string()))
         //};
 
+        let kdeconnect = KDEConnect {
+            id,
+            device_id,
+            device_name,
+            battery_charge: charge,
+            battery_state: charging,
+            notif_count,
+            // TODO
+            //notif_text,
+            phone_reachable: reachable,
+            bat_good: block_config.bat_good,
+            bat_info: block_config.bat_info,
+            bat_warning: block_config.bat_warning,
+            bat_critical: block_config.bat_critical,
+            format: block_config
+                .format
+                .with_default("{name} {bat_icon}{bat_charge} {notif_icon}{notif_count}")?,
+            format_disconnected: block_config.format_disconnected.with_default("{name}")?,
+            output: TextWidget::new(id, 0, shared_config.clone()).with_icon("phone")?,
+            shared_config,
+        };
+
+        if kdeconnect.shared_config.dry_run {
+            return Ok(kdeconnect);
+        }
+
         thread::Builder::new()
             .name("kdeconnect".into())
             .spawn(move || {
@@ -261,12 +305,7 @@ impl ConfigBlock for KDEConnect {
                         *name = s.name;
 
                         // Tell block to update now.
-                        send2
-                            .send(Task {
-                                id,
-                                update_time: Instant::now(),
-                            })
-                            .unwrap();
+                        send2.request();
 
                         true
                     },
@@ -279,12 +318,7 @@ impl ConfigBlock for KDEConnect {
                         let mut reachable = reachable_copy1.lock().unwrap();
                         *reachable = s.reachable;
 
-                        send6
-                            .send(Task {
-                                id,
-                                update_time: Instant::now(),
-                            })
-                            .unwrap();
+                        send6.request();
 
                         true
                     },
@@ -303,11 +337,7 @@ impl ConfigBlock for KDEConnect {
                             // whenever there is an update regardless of whether or
                             // not they both changed. So we only need to send updates
                             // in one of the two battery signal handlers.
-                            send.send(Task {
-                                id,
-                                update_time: Instant::now(),
-                            })
-                            .unwrap();
+                            send.request();
 
                             true
                         },
@@ -339,11 +369,7 @@ impl ConfigBlock for KDEConnect {
                             let mut charge = charge_copy.lock().unwrap();
                             *charge = s.charge;
 
-                            send.send(Task {
-                                id,
-                                update_time: Instant::now(),
-                            })
-                            .unwrap();
+                            send.request();
 
                             true
                         },
@@ -359,12 +385,7 @@ impl ConfigBlock for KDEConnect {
                             *notif_count += 1;
 
                             // Tell block to update now.
-                            send3
-                                .send(Task {
-                                    id,
-                                    update_time: Instant::now(),
-                                })
-                                .unwrap();
+                            send3.request();
 
                             true
                         },
@@ -382,12 +403,7 @@ impl ConfigBlock for KDEConnect {
                             };
 
                             // Tell block to update now.
-                            send4
-                                .send(Task {
-                                    id,
-                                    update_time: Instant::now(),
-                                })
-                                .unwrap();
+                            send4.request();
 
                             true
                         },
@@ -401,12 +417,7 @@ impl ConfigBlock for KDEConnect {
                             *notif_count = 0;
 
                             // Tell block to update now.
-                            send5
-                                .send(Task {
-                                    id,
-                                    update_time: Instant::now(),
-                                })
-                                .unwrap();
+                            send5.request();
 
                             true
                         },
@@ -429,12 +440,7 @@ impl ConfigBlock for KDEConnect {
                             *notif_count += 1;
 
                             // Tell block to update now.
-                            send3
-                                .send(Task {
-                                    id,
-                                    update_time: Instant::now(),
-                                })
-                                .unwrap();
+                            send3.request();
 
                             true
                         },
@@ -452,12 +458,7 @@ impl ConfigBlock for KDEConnect {
                             };
 
                             // Tell block to update now.
-                            send4
-                                .send(Task {
-                                    id,
-                                    update_time: Instant::now(),
-                                })
-                                .unwrap();
+                            send4.request();
 
                             true
                         },
@@ -471,12 +472,7 @@ impl ConfigBlock for KDEConnect {
                             *notif_count = 0;
 
                             // Tell block to update now.
-                            send5
-                                .send(Task {
-                                    id,
-                                    update_time: Instant::now(),
-                                })
-                                .unwrap();
+                            send5.request();
 
                             true
                         },
@@ -501,48 +497,29 @@ impl ConfigBlock for KDEConnect {
                         *reachable = s.is_visible;
 
                         // Tell block to update now.
-                        send7
-                            .send(Task {
-                                id,
-                                update_time: Instant::now(),
-                            })
-                            .unwrap();
+                        send7.request();
 
                         true
                     },
                 );
 
-                loop {
+                // Until the block is gone
+                while !update_request.is_gone() {
+                    pause::wait();
                     c.process(Duration::from_millis(1000)).unwrap();
                 }
             })
             .unwrap();
 
-        Ok(KDEConnect {
-            id,
-            device_id,
-            device_name,
-            battery_charge: charge,
-            battery_state: charging,
-            notif_count,
-            // TODO
-            //notif_text,
-            phone_reachable: reachable,
-            bat_good: block_config.bat_good,
-            bat_info: block_config.bat_info,
-            bat_warning: block_config.bat_warning,
-            bat_critical: block_config.bat_critical,
-            format: block_config
-                .format
-                .with_default("{name} {bat_icon}{bat_charge} {notif_icon}{notif_count}")?,
-            format_disconnected: block_config.format_disconnected.with_default("{name}")?,
-            output: TextWidget::new(id, 0, shared_config.clone()).with_icon("phone")?,
-            shared_config,
-        })
+        Ok(kdeconnect)
     }
 }
 
 impl Block for KDEConnect {
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[&self.format, &self.format_disconnected])
+    }
+
     fn id(&self) -> usize {
         self.id
     }
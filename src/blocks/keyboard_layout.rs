@@ -19,8 +19,10 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
 use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
+use crate::pause;
 use crate::scheduler::Task;
 use crate::widgets::text::TextWidget;
 use crate::widgets::I3BarWidget;
@@ -153,15 +155,18 @@ impl KeyboardLayoutMonitor for LocaleBus {
                     .expect("Failed to add D-Bus match rule.");
 
                 loop {
+                    pause::wait();
                     // TODO: This actually seems to trigger twice for each localectl
                     // change.
                     if con.incoming(10_000).next().is_some() {
-                        update_request
-                            .send(Task {
-                                id,
-                                update_time: Instant::now(),
-                            })
-                            .unwrap();
+                        let task = Task {
+                            id,
+                            update_time: Instant::now(),
+                        };
+                        if update_request.send(task).is_err() {
+                            // The block is gone
+                            return;
+                        }
                     }
                 }
             })
@@ -271,13 +276,16 @@ impl KeyboardLayoutMonitor for KbdDaemonBus {
                 c.add_handler(KbddMessageHandler(arc));
                 loop {
                     for ci in c.iter(100_000) {
+                        pause::wait();
                         if let dbus::ffidisp::ConnectionItem::Signal(_) = ci {
-                            update_request
-                                .send(Task {
-                                    id,
-                                    update_time: Instant::now(),
-                                })
-                                .unwrap();
+                            let task = Task {
+                                id,
+                                update_time: Instant::now(),
+                            };
+                            if update_request.send(task).is_err() {
+                                // The block is gone
+                                return;
+                            }
                         }
                     }
                 }
@@ -377,6 +385,7 @@ impl KeyboardLayoutMonitor for Sway {
                     .subscribe(&[EventType::Input])
                     .unwrap()
                 {
+                    pause::wait();
                     match event.unwrap() {
                         Event::Input(e) => match e.change {
                             InputChange::XkbLayout => {
@@ -384,24 +393,28 @@ impl KeyboardLayoutMonitor for Sway {
                                     let mut layout = arc.lock().unwrap();
                                     *layout = name;
                                 }
-                                update_request
-                                    .send(Task {
-                                        id,
-                                        update_time: Instant::now(),
-                                    })
-                                    .unwrap();
+                                let task = Task {
+                                    id,
+                                    update_time: Instant::now(),
+                                };
+                                if update_request.send(task).is_err() {
+                                    // The block is gone
+                                    return;
+                                }
                             }
                             InputChange::XkbKeymap => {
                                 if let Some(name) = e.input.xkb_active_layout_name {
                                     let mut layout = arc.lock().unwrap();
                                     *layout = name;
                                 }
-                                update_request
-                                    .send(Task {
-                                        id,
-                                        update_time: Instant::now(),
-                                    })
-                                    .unwrap();
+                                let task = Task {
+                                    id,
+                                    update_time: Instant::now(),
+                                };
+                                if update_request.send(task).is_err() {
+                                    // The block is gone
+                                    return;
+                                }
                             }
                             _ => {}
                         },
@@ -452,19 +465,26 @@ pub struct KeyboardLayout {
 impl ConfigBlock for KeyboardLayout {
     type Config = KeyboardLayoutConfig;
 
+    const PLACEHOLDERS: Schema = &[Field::text("layout"), Field::text("variant")];
+
     fn new(
         id: usize,
         block_config: Self::Config,
         shared_config: SharedConfig,
         send: Sender<Task>,
     ) -> Result<Self> {
+        let dry_run = shared_config.dry_run;
         let monitor: Box<dyn KeyboardLayoutMonitor> = match block_config.driver {
             KeyboardLayoutDriver::SetXkbMap => Box::new(SetXkbMap::new()?),
             KeyboardLayoutDriver::LocaleBus => {
                 let monitor = LocaleBus::new()?;
-                monitor.monitor(id, send);
+                if !dry_run {
+                    monitor.monitor(id, send);
+                }
                 Box::new(monitor)
             }
+            // Looking for kbdd starts setxkbmap, which a dry run doesn't
+            KeyboardLayoutDriver::KbddBus if dry_run => Box::new(SetXkbMap::new()?),
             KeyboardLayoutDriver::KbddBus => {
                 let monitor = KbdDaemonBus::new()?;
                 monitor.monitor(id, send);
@@ -472,7 +492,9 @@ impl ConfigBlock for KeyboardLayout {
             }
             KeyboardLayoutDriver::Sway => {
                 let monitor = Sway::new(block_config.sway_kb_identifier)?;
-                monitor.monitor(id, send);
+                if !dry_run {
+                    monitor.monitor(id, send);
+                }
                 Box::new(monitor)
             }
         };
@@ -494,6 +516,10 @@ impl ConfigBlock for KeyboardLayout {
 }
 
 impl Block for KeyboardLayout {
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[&self.format])
+    }
+
     fn id(&self) -> usize {
         self.id
     }
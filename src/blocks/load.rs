@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fs::{read_to_string, OpenOptions};
 use std::io::prelude::*;
 use std::time::Duration;
@@ -9,6 +10,8 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
+use crate::formatting::unit::Unit;
 use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
 use crate::scheduler::Task;
@@ -58,6 +61,12 @@ impl Default for LoadConfig {
 impl ConfigBlock for Load {
     type Config = LoadConfig;
 
+    const PLACEHOLDERS: Schema = &[
+        Field::float("1m", Unit::None),
+        Field::float("5m", Unit::None),
+        Field::float("15m", Unit::None),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -132,6 +141,10 @@ impl Block for Load {
         vec![&self.text]
     }
 
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[&self.format])
+    }
+
     fn id(&self) -> usize {
         self.id
     }
@@ -1,4 +1,5 @@
 use regex::Regex;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::str::FromStr;
@@ -11,6 +12,8 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
+use crate::formatting::unit::Unit;
 use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
 use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
@@ -238,6 +241,27 @@ impl Memory {
 impl ConfigBlock for Memory {
     type Config = MemoryConfig;
 
+    const PLACEHOLDERS: Schema = &[
+        Field::float("mem_total", Unit::Bytes),
+        Field::float("mem_free", Unit::Bytes),
+        Field::float("mem_avail", Unit::Bytes),
+        Field::float("mem_total_used", Unit::Bytes),
+        Field::float("mem_used", Unit::Bytes),
+        Field::float("buffers", Unit::Bytes),
+        Field::float("cached", Unit::Bytes),
+        Field::float("swap_total", Unit::Bytes),
+        Field::float("swap_free", Unit::Bytes),
+        Field::float("swap_used", Unit::Bytes),
+        Field::float("mem_free_percents", Unit::Percents),
+        Field::float("mem_avail_percents", Unit::Percents),
+        Field::float("mem_total_used_percents", Unit::Percents),
+        Field::float("mem_used_percents", Unit::Percents),
+        Field::float("buffers_percent", Unit::Percents),
+        Field::float("cached_percent", Unit::Percents),
+        Field::float("swap_free_percents", Unit::Percents),
+        Field::float("swap_used_percents", Unit::Percents),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -274,6 +298,10 @@ impl ConfigBlock for Memory {
 }
 
 impl Block for Memory {
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[&self.format.0, &self.format.1])
+    }
+
     fn id(&self) -> usize {
         self.id
     }
@@ -388,17 +416,25 @@ impl Block for Memory {
 
     fn click(&mut self, event: &I3BarEvent) -> Result<()> {
         if event.button == MouseButton::Left && self.clickable {
-            self.switch();
-            self.update()?;
-            self.tx_update_request.send(Task {
-                id: self.id,
-                update_time: Instant::now(),
-            })?;
+            self.action("toggle_format", event)?;
         }
 
         Ok(())
     }
 
+    fn action(&mut self, action: &str, _event: &I3BarEvent) -> Result<bool> {
+        if action != "toggle_format" {
+            return Ok(false);
+        }
+        self.switch();
+        self.update()?;
+        self.tx_update_request.send(Task {
+            id: self.id,
+            update_time: Instant::now(),
+        })?;
+        Ok(true)
+    }
+
     fn view(&self) -> Vec<&dyn I3BarWidget> {
         vec![match self.memtype {
             Memtype::Memory => &self.output.0,
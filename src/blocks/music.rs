@@ -1,4 +1,5 @@
 use std::boxed::Box;
+use std::collections::HashMap;
 use std::result;
 use std::sync::{Arc, Mutex};
 use std::thread;
@@ -19,8 +20,10 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::{LogicalDirection, Scrolling, SharedConfig};
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
 use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
+use crate::pause;
 use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
 use crate::subprocess::spawn_child_async;
@@ -256,6 +259,14 @@ impl Default for MusicConfig {
 impl ConfigBlock for Music {
     type Config = MusicConfig;
 
+    const PLACEHOLDERS: Schema = &[
+        Field::text("artist"),
+        Field::text("title"),
+        Field::text("combo"),
+        Field::text("player"),
+        Field::text("avail"),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -328,6 +339,94 @@ impl ConfigBlock for Music {
         let send_clone = send.clone();
         let preferred_player = block_config.player.clone();
 
+        let mut play: Option<TextWidget> = None;
+        let mut prev: Option<TextWidget> = None;
+        let mut next: Option<TextWidget> = None;
+        for button in block_config.buttons {
+            match &*button {
+                "play" => {
+                    play = Some(
+                        TextWidget::new(id, play_id, shared_config.clone())
+                            .with_icon("music_play")?
+                            .with_state(State::Info)
+                            .with_spacing(Spacing::Hidden),
+                    )
+                }
+                "next" => {
+                    next = Some(
+                        TextWidget::new(id, next_id, shared_config.clone())
+                            .with_icon("music_next")?
+                            .with_state(State::Info)
+                            .with_spacing(Spacing::Hidden),
+                    )
+                }
+                "prev" => {
+                    prev = Some(
+                        TextWidget::new(id, prev_id, shared_config.clone())
+                            .with_icon("music_prev")?
+                            .with_state(State::Info)
+                            .with_spacing(Spacing::Hidden),
+                    )
+                }
+                x => {
+                    return Err(BlockError(
+                        "music".to_owned(),
+                        format!("unknown music button identifier: '{}'", x),
+                    ))
+                }
+            };
+        }
+
+        fn compile_regexps(patterns: Vec<String>) -> result::Result<Vec<Regex>, regex::Error> {
+            patterns.iter().map(|p| Regex::new(&p)).collect()
+        }
+
+        let music = Music {
+            id,
+            play_id,
+            prev_id,
+            next_id,
+            collapsed_id,
+            current_song_widget: RotatingTextWidget::new(
+                id,
+                id,
+                Duration::new(block_config.marquee_interval.as_secs(), 0),
+                Duration::new(0, block_config.marquee_speed.subsec_nanos()),
+                block_config.max_width,
+                block_config.dynamic_width,
+                shared_config.clone(),
+            )
+            .with_icon("music")?
+            .with_state(State::Info)
+            .with_spacing(Spacing::Hidden),
+            prev,
+            play,
+            next,
+            on_click: None,
+            on_collapsed_click_widget: TextWidget::new(id, collapsed_id, shared_config.clone())
+                .with_icon("music")?
+                .with_state(State::Info)
+                .with_spacing(Spacing::Hidden),
+            on_collapsed_click: block_config.on_collapsed_click,
+            dbus_conn: Connection::get_private(BusType::Session)
+                .block_error("music", "failed to establish D-Bus connection")?,
+            marquee: block_config.marquee,
+            marquee_interval: block_config.marquee_interval,
+            smart_trim: block_config.smart_trim,
+            max_width: block_config.max_width,
+            separator: block_config.separator,
+            seek_step: block_config.seek_step,
+            players,
+            hide_when_empty: block_config.hide_when_empty,
+            send,
+            format: block_config.format.with_default("{combo}")?,
+            scrolling: shared_config.scrolling,
+        };
+
+        if shared_config.dry_run {
+            return Ok(music);
+        }
+
         thread::Builder::new()
             .name("music".into())
             .spawn(move || {
@@ -343,6 +442,7 @@ impl ConfigBlock for Music {
 
                 loop {
                     for ref signal in dbus_conn.incoming(60_000) {
+                        pause::wait();
                         let mut players = players_clone
                             .lock()
                             .expect("failed to acquire lock for `players`");
@@ -411,99 +511,21 @@ impl ConfigBlock for Music {
 
                         // Request to update the block
                         if updated {
-                            send_clone.send(Task {
+                            let task = Task {
                                 id,
                                 update_time: Instant::now(),
-                            }).unwrap();
+                            };
+                            if send_clone.send(task).is_err() {
+                                // The block is gone
+                                return;
+                            }
                         }
                     }
                 }
             })
             .unwrap();
 
-        let mut play: Option<TextWidget> = None;
-        let mut prev: Option<TextWidget> = None;
-        let mut next: Option<TextWidget> = None;
-        for button in block_config.buttons {
-            match &*button {
-                "play" => {
-                    play = Some(
-                        TextWidget::new(id, play_id, shared_config.clone())
-                            .with_icon("music_play")?
-                            .with_state(State::Info)
-                            .with_spacing(Spacing::Hidden),
-                    )
-                }
-                "next" => {
-                    next = Some(
-                        TextWidget::new(id, next_id, shared_config.clone())
-                            .with_icon("music_next")?
-                            .with_state(State::Info)
-                            .with_spacing(Spacing::Hidden),
-                    )
-                }
-                "prev" => {
-                    prev = Some(
-                        TextWidget::new(id, prev_id, shared_config.clone())
-                            .with_icon("music_prev")?
-                            .with_state(State::Info)
-                            .with_spacing(Spacing::Hidden),
-                    )
-                }
-                x => {
-                    return Err(BlockError(
-                        "music".to_owned(),
-                        format!("unknown music button identifier: '{}'", x),
-                    ))
-                }
-            };
-        }
-
-        fn compile_regexps(patterns: Vec<String>) -> result::Result<Vec<Regex>, regex::Error> {
-            patterns.iter().map(|p| Regex::new(&p)).collect()
-        }
-
-        Ok(Music {
-            id,
-            play_id,
-            prev_id,
-            next_id,
-            collapsed_id,
-            current_song_widget: RotatingTextWidget::new(
-                id,
-                id,
-                Duration::new(block_config.marquee_interval.as_secs(), 0),
-                Duration::new(0, block_config.marquee_speed.subsec_nanos()),
-                block_config.max_width,
-                block_config.dynamic_width,
-                shared_config.clone(),
-            )
-            .with_icon("music")?
-            .with_state(State::Info)
-            .with_spacing(Spacing::Hidden),
-            prev,
-            play,
-            next,
-            on_click: None,
-            on_collapsed_click_widget: TextWidget::new(id, collapsed_id, shared_config.clone())
-                .with_icon("music")?
-                .with_state(State::Info)
-                .with_spacing(Spacing::Hidden),
-            on_collapsed_click: block_config.on_collapsed_click,
-            dbus_conn: Connection::get_private(BusType::Session)
-                .block_error("music", "failed to establish D-Bus connection")?,
-            marquee: block_config.marquee,
-            marquee_interval: block_config.marquee_interval,
-            smart_trim: block_config.smart_trim,
-            max_width: block_config.max_width,
-            separator: block_config.separator,
-            seek_step: block_config.seek_step,
-            players,
-            hide_when_empty: block_config.hide_when_empty,
-            send,
-            format: block_config.format.with_default("{combo}")?,
-            scrolling: shared_config.scrolling,
-        })
+        Ok(music)
     }
 
     fn override_on_click(&mut self) -> Option<&mut Option<String>> {
@@ -512,11 +534,17 @@ impl ConfigBlock for Music {
 }
 
 impl Block for Music {
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[&self.format])
+    }
+
     fn id(&self) -> usize {
         self.id
     }
 
     fn update(&mut self) -> Result<Option<Update>> {
+        // Kept next to the `scroll` modifier for existing configs: it has no pauses between
+        // rotations and no `smart_trim`
         let (rotation_in_progress, time_to_next_rotation) = if self.marquee {
             self.current_song_widget.next()?
         } else {
@@ -1,34 +1,32 @@
+use std::collections::HashMap;
 use std::fmt;
 use std::fs::{read_to_string, OpenOptions};
 use std::io::prelude::*;
+use std::net::IpAddr;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::result;
+use std::thread;
 use std::time::{Duration, Instant};
 
 use crossbeam_channel::Sender;
-use lazy_static::lazy_static;
-use regex::bytes::Regex;
+use regex::Regex;
 use serde_derive::Deserialize;
 
 use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
+use crate::formatting::unit::Unit as FormatUnit;
 use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
+use crate::netlink::{self, Link, Route};
+use crate::pause;
 use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
 use crate::util::{escape_pango_text, format_vec_to_bar_graph};
 use crate::widgets::{text::TextWidget, I3BarWidget, Spacing};
 
-lazy_static! {
-    static ref DEFAULT_DEV_REGEX: Regex = Regex::new("default.*dev (\\w*).*").unwrap();
-    static ref WHITESPACE_REGEX: Regex = Regex::new("\\s+").unwrap();
-    static ref ETHTOOL_SPEED_REGEX: Regex = Regex::new("Speed: (\\d+\\w\\w/s)").unwrap();
-    static ref IW_BITRATE_REGEX: Regex =
-        Regex::new("tx bitrate: (\\d+(?:\\.?\\d+) [[:alpha:]]+/s)").unwrap();
-}
-
 #[derive(Debug)]
 pub struct NetworkDevice {
     device: String,
@@ -77,25 +75,11 @@ impl NetworkDevice {
         self.device.clone()
     }
 
-    /// Grab the name of the 'default' device.
-    /// A default device is usually selected by the network manager
-    /// and will change when the status of devices change.
-    pub fn default_device() -> Option<String> {
-        String::from_utf8(
-            Command::new("ip")
-                .args(&["route", "show", "default"])
-                .output()
-                .ok()
-                .and_then(|o| {
-                    let mut captures = DEFAULT_DEV_REGEX.captures_iter(&o.stdout);
-                    if let Some(cap) = captures.next() {
-                        cap.get(1).map(|x| x.as_bytes().to_vec())
-                    } else {
-                        None
-                    }
-                })?,
-        )
-        .ok()
+    /// The index of the device, as used by netlink
+    fn index(&self) -> Result<u32> {
+        read_file(&self.device_path.join("ifindex"))?
+            .parse::<u32>()
+            .block_error("net", "Failed to parse ifindex")
     }
 
     /// Check whether the device exists.
@@ -160,106 +144,51 @@ impl NetworkDevice {
             return Ok((None, None, None));
         }
 
-        let interfaces = nl80211::Socket::connect()
-            .block_error("net", "nl80211: failed to connect to the socket")?
-            .get_interfaces_info()
-            .block_error("net", "nl80211: failed to get interfaces' information")?;
-
-        for interface in interfaces {
-            if let Ok(ap) = interface.get_station_info() {
-                // SSID is `None` when not connected
-                if let (Some(ssid), Some(device)) = (interface.ssid, interface.name) {
-                    let device = String::from_utf8_lossy(&device);
-                    let device = device.trim_matches(char::from(0));
-                    if device != self.device {
-                        continue;
-                    }
-
-                    let ssid = Some(escape_pango_text(decode_escaped_unicode(&ssid)));
-                    let freq = interface
-                        .frequency
-                        .map(|f| nl80211::parse_u32(&f) as f64 * 1e6);
-                    let signal = ap.signal.map(|s| signal_percents(nl80211::parse_i8(&s)));
-
-                    return Ok((ssid, freq, signal));
-                }
-            }
-        }
-
-        Ok((None, None, None))
-    }
-
-    /// Queries the inet IP of this device (using `ip`).
-    pub fn ip_addr(&self) -> Result<Option<String>> {
-        if !self.is_up()? {
-            return Ok(None);
-        }
-        let output = Command::new("ip")
-            .args(&["-json", "-family", "inet", "address", "show", &self.device])
-            .output()
-            .block_error("net", "Failed to execute IP address query.")
-            .and_then(|raw_output| {
-                String::from_utf8(raw_output.stdout)
-                    .block_error("net", "Response contained non-UTF8 characters.")
-            })?;
-
-        let ip_devs: Vec<IpDev> =
-            serde_json::from_str(&output).block_error("net", "Failed to parse JSON response")?;
-
-        if ip_devs.is_empty() {
-            return Ok(Some("".to_string()));
+        let info = netlink::wireless_info(self.index()?)?;
+        // SSID is `None` when not connected
+        match info.ssid {
+            Some(ssid) => Ok((
+                Some(escape_pango_text(decode_escaped_unicode(&ssid))),
+                info.frequency.map(|f| f as f64 * 1e6),
+                info.signal.map(signal_percents),
+            )),
+            None => Ok((None, None, None)),
         }
-
-        let ip = ip_devs
-            .iter()
-            .filter(|dev| dev.addr_info.is_some())
-            .flat_map(|dev| &dev.addr_info)
-            .flatten()
-            .filter_map(|addr| addr.local.clone())
-            .next();
-
-        Ok(match ip {
-            Some(addr) => Some(addr),
-            _ => Some("".to_string()),
-        })
     }
 
-    /// Queries the inet IPv6 of this device (using `ip`).
-    pub fn ipv6_addr(&self) -> Result<Option<String>> {
+    /// Queries the addresses, default gateways and MTU of this device. Nothing is known about
+    /// devices that are not up.
+    pub fn addressing(&self) -> Result<Addressing> {
         if !self.is_up()? {
-            return Ok(None);
-        }
-        let output = Command::new("ip")
-            .args(&["-json", "-family", "inet6", "address", "show", &self.device])
-            .output()
-            .block_error("net", "Failed to execute IP address query.")
-            .and_then(|raw_output| {
-                String::from_utf8(raw_output.stdout)
-                    .block_error("net", "Response contained non-UTF8 characters.")
-            })?;
-
-        let ip_devs: Vec<IpDev> =
-            serde_json::from_str(&output).block_error("net", "Failed to parse JSON response")?;
-
-        if ip_devs.is_empty() {
-            return Ok(Some("".to_string()));
+            return Ok(Addressing::default());
         }
-
-        let ip = ip_devs
-            .iter()
-            .filter(|dev| dev.addr_info.is_some())
-            .flat_map(|dev| &dev.addr_info)
-            .flatten()
-            .filter_map(|addr| addr.local.clone())
-            .next();
-
-        Ok(match ip {
-            Some(addr) => Some(addr),
-            _ => Some("".to_string()),
+        let index = self.index()?;
+        let mut socket = netlink::Socket::route()?;
+        let addresses = socket
+            .addresses()?
+            .into_iter()
+            .filter(|address| address.index == index)
+            .collect();
+        let mut routes: Vec<_> = socket
+            .routes()?
+            .into_iter()
+            .filter(|route| route.is_default() && route.interface == Some(index))
+            .collect();
+        routes.sort_by_key(|route| route.metric);
+        Ok(Addressing {
+            addresses,
+            gateways: routes
+                .into_iter()
+                .filter_map(|route| route.gateway)
+                .collect(),
+            mtu: read_file(&self.device_path.join("mtu"))
+                .ok()
+                .and_then(|mtu| mtu.parse().ok()),
         })
     }
 
-    /// Queries the bitrate of this device
+    /// Queries the bitrate of this device: the transmit bitrate of wireless devices and the
+    /// link speed of wired ones
     pub fn bitrate(&self) -> Result<Option<String>> {
         let up = self.is_up()?;
         // Doesn't really make sense to crash the bar here
@@ -267,67 +196,365 @@ impl NetworkDevice {
             return Ok(None);
         }
         if self.wireless {
-            let bitrate_output = Command::new("iw")
-                .args(&["dev", &self.device, "link"])
-                .output()
-                .block_error("net", "Failed to execute bitrate query with iw.")?
-                .stdout;
-
-            if let Some(rate) = IW_BITRATE_REGEX
-                .captures_iter(&bitrate_output)
-                .next()
-                .and_then(|x| x.get(1))
-            {
-                String::from_utf8(rate.as_bytes().to_vec())
-                    .block_error("net", "Non-UTF8 bitrate")
-                    .map(Some)
+            let info = netlink::wireless_info(self.index()?)?;
+            // In units of 100 kbit/s
+            Ok(info
+                .bitrate
+                .map(|rate| format!("{}.{} MBit/s", rate / 10, rate % 10)))
+        } else {
+            // The speed is unknown (and reading it fails) for devices without a link speed,
+            // like virtual ones
+            Ok(read_file(&self.device_path.join("speed"))
+                .ok()
+                .and_then(|speed| speed.parse::<i32>().ok())
+                .filter(|&speed| speed > 0)
+                .map(|speed| format!("{}Mb/s", speed)))
+        }
+    }
+}
+
+/// The addresses of a device and how it reaches other networks
+#[derive(Debug, Default)]
+pub struct Addressing {
+    pub addresses: Vec<netlink::Address>,
+    /// The gateways of the default routes through the device, the one with the lowest metric
+    /// first
+    pub gateways: Vec<IpAddr>,
+    pub mtu: Option<i64>,
+}
+
+impl Addressing {
+    /// The address the `ip` placeholder shows
+    fn main_address(&self, family: IpFamily) -> Option<&netlink::Address> {
+        let ipv4 = self.addresses.iter().find(|a| a.address.is_ipv4());
+        let ipv6 = self.addresses.iter().find(|a| a.address.is_ipv6());
+        match family {
+            IpFamily::Ipv4 => ipv4,
+            IpFamily::Ipv6 => ipv6,
+            IpFamily::Auto => ipv4.or(ipv6),
+        }
+    }
+}
+
+/// The address family of the `ip` placeholder
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum IpFamily {
+    Ipv4,
+    Ipv6,
+    /// IPv4 if the device has an IPv4 address, IPv6 otherwise
+    Auto,
+}
+
+impl Default for IpFamily {
+    fn default() -> Self {
+        IpFamily::Ipv4
+    }
+}
+
+/// Whether an address can be reached from the internet: `public`, `private` (including
+/// carrier-grade NAT and IPv6 unique local addresses), `link_local` or `loopback`
+fn address_scope(address: &IpAddr) -> &'static str {
+    match address {
+        IpAddr::V4(address) => {
+            let [a, b, ..] = address.octets();
+            if address.is_loopback() {
+                "loopback"
+            } else if address.is_link_local() {
+                "link_local"
+            } else if address.is_private() || (a == 100 && (64..128).contains(&b)) {
+                "private"
             } else {
-                Ok(None)
+                "public"
             }
-        } else {
-            let output = Command::new("ethtool")
-                .arg(&self.device)
-                .output()
-                .block_error("net", "Failed to execute bitrate query with ethtool")?
-                .stdout;
-            if let Some(rate) = ETHTOOL_SPEED_REGEX.captures_iter(&output).next() {
-                let rate = rate
-                    .get(1)
-                    .block_error("net", "Invalid ethtool output: no speed")?;
-                String::from_utf8(rate.as_bytes().to_vec())
-                    .block_error("net", "Non-UTF8 bitrate")
-                    .map(Some)
+        }
+        IpAddr::V6(address) => {
+            let first = address.segments()[0];
+            if address.is_loopback() {
+                "loopback"
+            } else if first & 0xffc0 == 0xfe80 {
+                "link_local"
+            } else if first & 0xfe00 == 0xfc00 {
+                "private"
             } else {
-                Ok(None)
+                "public"
             }
         }
     }
 }
 
-pub struct Net {
-    id: usize,
-    format: FormatTemplate,
-    format_alt: Option<FormatTemplate>,
+/// The name servers of `/etc/resolv.conf`
+fn dns_servers() -> Vec<String> {
+    read_to_string("/etc/resolv.conf")
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some("nameserver"), Some(server)) => Some(server.to_string()),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// The interface of the default route with the lowest metric that `included` accepts. IPv4
+/// routes are preferred. The default route is usually set by the network manager and changes
+/// when devices come and go.
+fn default_interface(
+    links: &[Link],
+    routes: &[Route],
+    included: impl Fn(&str) -> bool,
+) -> Option<String> {
+    let mut routes: Vec<&Route> = routes.iter().filter(|route| route.is_default()).collect();
+    routes.sort_by_key(|route| (route.ipv6, route.metric));
+    routes
+        .into_iter()
+        .filter_map(|route| {
+            links
+                .iter()
+                .find(|link| Some(link.index) == route.interface)
+        })
+        .map(|link| link.name.clone())
+        .find(|name| included(name))
+}
+
+/// Tell the block to update whenever a link, address or route changes
+fn watch_routes(id: usize, tx_update_request: Sender<Task>) -> Result<()> {
+    let mut events = netlink::Socket::route_events()?;
+    thread::Builder::new()
+        .name("net".into())
+        .spawn(move || {
+            while events.wait().is_ok() {
+                pause::wait();
+                // Changes come in bursts, like an address followed by its routes
+                thread::sleep(Duration::from_millis(250));
+                if tx_update_request
+                    .send(Task {
+                        id,
+                        update_time: Instant::now(),
+                    })
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        })
+        .block_error("net", "failed to start the route watcher")?;
+    Ok(())
+}
+
+/// An interface the block shows and what was last read of it
+struct Interface {
+    device: NetworkDevice,
     output: TextWidget,
-    ip_addr: Option<String>,
-    ipv6_addr: Option<String>,
+    /// Only queried if a format shows any of it
+    addressing: Option<Addressing>,
     bitrate: Option<String>,
     speed_up: f64,
     speed_down: f64,
     graph_tx: String,
     graph_rx: String,
-    update_interval: Duration,
-    device: NetworkDevice,
-    auto_device: bool,
     tx_buff: Vec<f64>,
     rx_buff: Vec<f64>,
     tx_bytes: u64,
     rx_bytes: u64,
+    /// When `tx_bytes` and `rx_bytes` were read
+    sampled: Instant,
     active: bool,
     exists: bool,
+    last_update: Instant,
+}
+
+impl Interface {
+    fn new(
+        id: usize,
+        instance: usize,
+        device: NetworkDevice,
+        bitrate: bool,
+        addressing: bool,
+        shared_config: SharedConfig,
+    ) -> Result<Self> {
+        let icon = if device.is_wireless() {
+            "net_wireless"
+        } else if device.is_vpn() {
+            "net_vpn"
+        } else if device.device == "lo" {
+            "net_loopback"
+        } else {
+            "net_wired"
+        };
+        Ok(Self {
+            output: TextWidget::new(id, instance, shared_config)
+                .with_icon(icon)?
+                .with_text("")
+                .with_spacing(Spacing::Inline),
+            addressing: addressing.then(Addressing::default),
+            bitrate: bitrate.then(String::new),
+            speed_up: 0.0,
+            speed_down: 0.0,
+            graph_tx: String::new(),
+            graph_rx: String::new(),
+            tx_buff: vec![0.; 10],
+            rx_buff: vec![0.; 10],
+            tx_bytes: device.tx_bytes().unwrap_or(0),
+            rx_bytes: device.rx_bytes().unwrap_or(0),
+            sampled: Instant::now(),
+            active: true,
+            exists: true,
+            last_update: Instant::now() - Duration::from_secs(30),
+            device,
+        })
+    }
+
+    fn update_bitrate(&mut self) -> Result<()> {
+        if let Some(ref mut bitrate_string) = self.bitrate {
+            let bitrate = self.device.bitrate()?;
+            if let Some(b) = bitrate {
+                *bitrate_string = b;
+            }
+        }
+        Ok(())
+    }
+
+    fn update_addressing(&mut self, dns: &mut Vec<String>) -> Result<()> {
+        if let Some(ref mut addressing) = self.addressing {
+            *addressing = self.device.addressing()?;
+            *dns = dns_servers();
+        }
+        Ok(())
+    }
+
+    fn update_tx_rx(&mut self) -> Result<()> {
+        // The block also updates when the routes change, so the time since the last update is
+        // not always the update interval
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.sampled).as_secs_f64().max(0.001);
+        self.sampled = now;
+
+        // Update the throughput/graph widgets if they are enabled
+        let current_tx = self.device.tx_bytes()?;
+        let diff = current_tx.saturating_sub(self.tx_bytes);
+        let tx_bytes = (diff as f64 / elapsed) as u64;
+        self.tx_bytes = current_tx;
+
+        self.speed_up = tx_bytes as f64;
+
+        self.tx_buff.remove(0);
+        self.tx_buff.push(tx_bytes as f64);
+        self.graph_tx = format_vec_to_bar_graph(&self.tx_buff, None, None);
+
+        let current_rx = self.device.rx_bytes()?;
+        let diff = current_rx.saturating_sub(self.rx_bytes);
+        let rx_bytes = (diff as f64 / elapsed) as u64;
+        self.rx_bytes = current_rx;
+
+        self.speed_down = rx_bytes as f64;
+
+        self.rx_buff.remove(0);
+        self.rx_buff.push(rx_bytes as f64);
+        self.graph_rx = format_vec_to_bar_graph(&self.rx_buff, None, None);
+
+        Ok(())
+    }
+
+    fn update(
+        &mut self,
+        format: &FormatTemplate,
+        ip_family: IpFamily,
+        dns: &mut Vec<String>,
+        shared_config: &SharedConfig,
+    ) -> Result<()> {
+        // skip updating if device is not up.
+        self.exists = self.device.exists()?;
+        self.active = self.exists && self.device.is_up()?;
+        if !self.active {
+            self.output.set_text("×".to_string());
+            return Ok(());
+        }
+
+        // Update SSID and IP address every 30s and the bitrate every 10s
+        let now = Instant::now();
+        if now.duration_since(self.last_update).as_secs() % 10 == 0 {
+            self.update_bitrate()?;
+        }
+
+        let waiting_for_ip = match self.addressing {
+            Some(ref addressing) => addressing.main_address(ip_family).is_none(),
+            None => false,
+        };
+
+        if (now.duration_since(self.last_update).as_secs() > 30) || waiting_for_ip {
+            self.update_addressing(dns)?;
+            self.last_update = now;
+        }
+
+        self.update_tx_rx()?;
+
+        let (ssid, freq, signal) = self.device.wifi_info()?;
+
+        let empty_string = "".to_string();
+        let na_string = "N/A".to_string();
+
+        let no_addressing = Addressing::default();
+        let addressing = self.addressing.as_ref().unwrap_or(&no_addressing);
+        let ip = addressing.main_address(ip_family);
+        let ipv6 = addressing.main_address(IpFamily::Ipv6);
+        let ips: Vec<String> = addressing
+            .addresses
+            .iter()
+            .map(|a| a.address.to_string())
+            .collect();
+        // The gateway of the family of `ip`
+        let gateway = addressing.gateways.iter().find(|g| {
+            ip.map(|a| a.address.is_ipv4() == g.is_ipv4())
+                .unwrap_or(true)
+        });
+
+        let values = map!(
+            "ssid" => Value::from_string(ssid.unwrap_or(na_string)),
+            "signal_strength" => Value::from_integer(signal.unwrap_or(0)).percents(),
+            "frequency" => Value::from_float(freq.unwrap_or(0.)).hertz(),
+            "bitrate" => Value::from_string(self.bitrate.clone().unwrap_or_else(|| empty_string.clone())), // TODO: not a String?
+            "ip" => Value::from_string(ip.map(|a| a.address.to_string()).unwrap_or_default()),
+            "ipv6" => Value::from_string(ipv6.map(|a| a.address.to_string()).unwrap_or_default()),
+            "ips" => Value::from_string(ips.join(", ")),
+            "prefix" => Value::from_integer(ip.map(|a| a.prefix as i64).unwrap_or(0)),
+            "scope" => Value::from_string(ip.map(|a| address_scope(&a.address)).unwrap_or_default().to_string()),
+            "gateway" => Value::from_string(gateway.map(|g| g.to_string()).unwrap_or_default()),
+            "dns" => Value::from_string(dns.join(", ")),
+            "mtu" => Value::from_integer(addressing.mtu.unwrap_or(0)),
+            "speed_up" => Value::from_float(self.speed_up).bytes().icon(shared_config.get_icon("net_up")?),
+            "speed_down" => Value::from_float(self.speed_down).bytes().icon(shared_config.get_icon("net_down")?),
+            "graph_up" => Value::from_string(self.graph_tx.clone()),
+            "graph_down" => Value::from_string(self.graph_rx.clone()),
+        );
+
+        self.output.set_texts(format.render(&values)?);
+        Ok(())
+    }
+}
+
+pub struct Net {
+    id: usize,
+    format: FormatTemplate,
+    format_alt: Option<FormatTemplate>,
+    /// The interfaces shown, one widget each
+    interfaces: Vec<Interface>,
+    /// The widget instance of the next interface
+    next_instance: usize,
+    /// The interface to show, if the config names one
+    device: Option<String>,
+    all_interfaces: bool,
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+    dns: Vec<String>,
+    ip_family: IpFamily,
+    show_bitrate: bool,
+    show_addressing: bool,
+    update_interval: Duration,
     hide_inactive: bool,
     hide_missing: bool,
-    last_update: Instant,
     shared_config: SharedConfig,
 }
 
@@ -366,11 +593,23 @@ pub struct NetConfig {
     /// Which interface in /sys/class/net/ to read from.
     pub device: Option<String>,
 
+    /// Show every interface that is up instead of one, each as its own widget.
+    pub all_interfaces: bool,
+
+    /// Interface name regex patterns to include, unless `device` is set.
+    pub interface_name_include: Vec<String>,
+
+    /// Interface name regex patterns to ignore, unless `device` is set.
+    pub interface_name_exclude: Vec<String>,
+
     /// Whether to hide networks that are down/inactive completely.
     pub hide_inactive: bool,
 
     /// Whether to hide networks that are missing.
     pub hide_missing: bool,
+
+    /// The address family of the `ip` placeholder.
+    pub ip_family: IpFamily,
 }
 
 impl Default for NetConfig {
@@ -380,8 +619,12 @@ impl Default for NetConfig {
             format: FormatTemplate::default(),
             format_alt: None,
             device: None,
+            all_interfaces: false,
+            interface_name_include: Vec::new(),
+            interface_name_exclude: Vec::new(),
             hide_inactive: false,
             hide_missing: false,
+            ip_family: IpFamily::default(),
         }
     }
 }
@@ -389,83 +632,81 @@ impl Default for NetConfig {
 impl ConfigBlock for Net {
     type Config = NetConfig;
 
+    const PLACEHOLDERS: Schema = &[
+        Field::text("ssid"),
+        Field::integer("signal_strength", FormatUnit::Percents),
+        Field::float("frequency", FormatUnit::Hertz),
+        Field::text("bitrate"),
+        Field::text("ip"),
+        Field::text("ipv6"),
+        Field::text("ips"),
+        Field::integer("prefix", FormatUnit::None),
+        Field::text("scope"),
+        Field::text("gateway"),
+        Field::text("dns"),
+        Field::integer("mtu", FormatUnit::None),
+        Field::float("speed_up", FormatUnit::Bytes),
+        Field::float("speed_down", FormatUnit::Bytes),
+        Field::text("graph_up"),
+        Field::text("graph_down"),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
         shared_config: SharedConfig,
-        _tx_update_request: Sender<Task>,
+        tx_update_request: Sender<Task>,
     ) -> Result<Self> {
-        let default_device = match NetworkDevice::default_device() {
-            Some(ref s) if !s.is_empty() => s.to_string(),
-            _ => "lo".to_string(),
-        };
-        let device = match block_config.device.clone() {
-            Some(d) => NetworkDevice::from_device(d),
-            _ => NetworkDevice::from_device(default_device),
-        };
-        let init_rx_bytes = device.rx_bytes().unwrap_or(0);
-        let init_tx_bytes = device.tx_bytes().unwrap_or(0);
-        let wireless = device.is_wireless();
-        let vpn = device.is_vpn();
-
         let format = block_config
             .format
             .with_default("{speed_down;K}{speed_up;K}")?;
         let format_alt = block_config.format_alt;
+        let shows = |placeholder: &str| {
+            format.contains(placeholder)
+                || format_alt
+                    .as_ref()
+                    .map(|f| f.contains(placeholder))
+                    .unwrap_or(false)
+        };
+        // TODO: a better way to deal with this?
+        let show_bitrate = shows("bitrate");
+        let show_addressing = [
+            "ip", "ipv6", "ips", "prefix", "scope", "gateway", "dns", "mtu",
+        ]
+        .iter()
+        .any(|placeholder| shows(placeholder));
+
+        fn compile_regexps(patterns: Vec<String>) -> result::Result<Vec<Regex>, regex::Error> {
+            patterns.iter().map(|p| Regex::new(p)).collect()
+        }
 
-        Ok(Net {
+        let mut net = Net {
             id,
             update_interval: block_config.interval,
-            output: TextWidget::new(id, 0, shared_config.clone())
-                .with_icon(if wireless {
-                    "net_wireless"
-                } else if vpn {
-                    "net_vpn"
-                } else if device.device == "lo" {
-                    "net_loopback"
-                } else {
-                    "net_wired"
-                })?
-                .with_text("")
-                .with_spacing(Spacing::Inline),
-            // TODO: a better way to deal with this?
-            bitrate: (format.contains("bitrate")
-                || format_alt
-                    .as_ref()
-                    .map(|f| f.contains("bitrate"))
-                    .unwrap_or(false))
-            .then(String::new),
-            ip_addr: (format.contains("ip")
-                || format_alt
-                    .as_ref()
-                    .map(|f| f.contains("ip"))
-                    .unwrap_or(false))
-            .then(String::new),
-            ipv6_addr: (format.contains("ipv6")
-                || format_alt
-                    .as_ref()
-                    .map(|f| f.contains("ipv6"))
-                    .unwrap_or(false))
-            .then(String::new),
-            speed_up: 0.0,
-            speed_down: 0.0,
-            graph_tx: String::new(),
-            graph_rx: String::new(),
-            device,
-            auto_device: block_config.device.is_none(),
-            rx_buff: vec![0.; 10],
-            tx_buff: vec![0.; 10],
-            rx_bytes: init_rx_bytes,
-            tx_bytes: init_tx_bytes,
-            active: true,
-            exists: true,
+            interfaces: Vec::new(),
+            next_instance: 0,
+            device: block_config.device,
+            all_interfaces: block_config.all_interfaces,
+            include: compile_regexps(block_config.interface_name_include)
+                .block_error("net", "failed to parse include patterns")?,
+            exclude: compile_regexps(block_config.interface_name_exclude)
+                .block_error("net", "failed to parse exclude patterns")?,
+            dns: Vec::new(),
+            ip_family: block_config.ip_family,
+            show_bitrate,
+            show_addressing,
             hide_inactive: block_config.hide_inactive,
             hide_missing: block_config.hide_missing,
-            last_update: Instant::now() - Duration::from_secs(30),
             shared_config,
             format,
             format_alt,
-        })
+        };
+        net.select_interfaces()?;
+        // Without a device the interfaces shown follow the routes
+        if net.device.is_none() && !net.shared_config.dry_run {
+            watch_routes(id, tx_update_request)?;
+        }
+        Ok(net)
     }
 }
 
@@ -483,177 +724,117 @@ fn read_file(path: &Path) -> Result<String> {
 }
 
 impl Net {
-    fn update_bitrate(&mut self) -> Result<()> {
-        if let Some(ref mut bitrate_string) = self.bitrate {
-            let bitrate = self.device.bitrate()?;
-            if let Some(b) = bitrate {
-                *bitrate_string = b;
-            }
+    /// Whether the interface called `name` passes the include and exclude patterns
+    fn is_included(&self, name: &str) -> bool {
+        // If an interface matches an exclude pattern, ignore it
+        if self.exclude.iter().any(|regex| regex.is_match(name)) {
+            return false;
         }
-        Ok(())
+        // If we have at-least one include pattern, make sure
+        // the interface name matches at least one of them
+        self.include.is_empty() || self.include.iter().any(|regex| regex.is_match(name))
     }
 
-    fn update_ip_addr(&mut self) -> Result<()> {
-        if let Some(ref mut ip_addr_string) = self.ip_addr {
-            let ip_addr = self.device.ip_addr()?;
-            if let Some(ip) = ip_addr {
-                *ip_addr_string = ip;
-            }
+    /// The names of the interfaces to show
+    fn devices(&self) -> Result<Vec<String>> {
+        if let Some(ref device) = self.device {
+            return Ok(vec![device.clone()]);
         }
-        if let Some(ref mut ipv6_addr_string) = self.ipv6_addr {
-            let ipv6_addr = self.device.ipv6_addr()?;
-            if let Some(ip) = ipv6_addr {
-                *ipv6_addr_string = ip;
-            }
+        let mut socket = netlink::Socket::route()?;
+        let links = socket.links()?;
+        if self.all_interfaces {
+            return Ok(links
+                .into_iter()
+                .map(|link| link.name)
+                .filter(|name| name != "lo" && self.is_included(name))
+                .filter(|name| {
+                    NetworkDevice::from_device(name.clone())
+                        .is_up()
+                        .unwrap_or(false)
+                })
+                .collect());
         }
-        Ok(())
+        let device = default_interface(&links, &socket.routes()?, |name| self.is_included(name))
+            .unwrap_or_else(|| "lo".to_string());
+        Ok(vec![device])
     }
 
-    fn update_tx_rx(&mut self) -> Result<()> {
-        // TODO: consider using `as_nanos`
-        let update_interval = (self.update_interval.as_secs() as f64)
-            + (self.update_interval.subsec_nanos() as f64 / 1_000_000_000.0);
-
-        // Update the throughput/graph widgets if they are enabled
-        let current_tx = self.device.tx_bytes()?;
-        let diff = current_tx.saturating_sub(self.tx_bytes);
-        let tx_bytes = (diff as f64 / update_interval) as u64;
-        self.tx_bytes = current_tx;
-
-        self.speed_up = tx_bytes as f64;
-
-        self.tx_buff.remove(0);
-        self.tx_buff.push(tx_bytes as f64);
-        self.graph_tx = format_vec_to_bar_graph(&self.tx_buff, None, None);
-
-        let current_rx = self.device.rx_bytes()?;
-        let diff = current_rx.saturating_sub(self.rx_bytes);
-        let rx_bytes = (diff as f64 / update_interval) as u64;
-        self.rx_bytes = current_rx;
-
-        self.speed_down = rx_bytes as f64;
-
-        self.rx_buff.remove(0);
-        self.rx_buff.push(rx_bytes as f64);
-        self.graph_rx = format_vec_to_bar_graph(&self.rx_buff, None, None);
-
+    /// Show the interfaces returned by `devices`, keeping what is known of those already shown
+    fn select_interfaces(&mut self) -> Result<()> {
+        let devices = self.devices()?;
+        let mut shown = std::mem::take(&mut self.interfaces);
+        for device in devices {
+            let interface = match shown.iter().position(|i| i.device.device == device) {
+                Some(i) => shown.remove(i),
+                None => {
+                    self.next_instance += 1;
+                    Interface::new(
+                        self.id,
+                        self.next_instance - 1,
+                        NetworkDevice::from_device(device),
+                        self.show_bitrate,
+                        self.show_addressing,
+                        self.shared_config.clone(),
+                    )?
+                }
+            };
+            self.interfaces.push(interface);
+        }
         Ok(())
     }
 }
 
 impl Block for Net {
     fn update(&mut self) -> Result<Option<Update>> {
-        // Update device
-        if self.auto_device {
-            let dev = match NetworkDevice::default_device() {
-                Some(ref s) if !s.is_empty() => s.to_string(),
-                _ => "lo".to_string(),
-            };
-
-            if self.device.device() != dev {
-                self.device = NetworkDevice::from_device(dev);
-                self.output.set_icon(if self.device.is_wireless() {
-                    "net_wireless"
-                } else if self.device.is_vpn() {
-                    "net_vpn"
-                } else if self.device.device == "lo" {
-                    "net_loopback"
-                } else {
-                    "net_wired"
-                })?;
-            }
-        }
-
-        // skip updating if device is not up.
-        self.exists = self.device.exists()?;
-        self.active = self.exists && self.device.is_up()?;
-        if !self.active {
-            self.output.set_text("×".to_string());
-            return Ok(Some(self.update_interval.into()));
+        if self.device.is_none() {
+            self.select_interfaces()?;
         }
-
-        // Update SSID and IP address every 30s and the bitrate every 10s
-        let now = Instant::now();
-        if now.duration_since(self.last_update).as_secs() % 10 == 0 {
-            self.update_bitrate()?;
+        for interface in &mut self.interfaces {
+            interface.update(
+                &self.format,
+                self.ip_family,
+                &mut self.dns,
+                &self.shared_config,
+            )?;
         }
-
-        let waiting_for_ip = match self.ip_addr.as_deref() {
-            None => false,
-            Some("") => true,
-            Some(_) => false,
-        };
-
-        let waiting_for_ipv6 = match self.ipv6_addr.as_deref() {
-            None => false,
-            Some("") => true,
-            Some(_) => false,
-        };
-
-        if (now.duration_since(self.last_update).as_secs() > 30)
-            || waiting_for_ip
-            || waiting_for_ipv6
-        {
-            self.update_ip_addr()?;
-            self.last_update = now;
-        }
-
-        self.update_tx_rx()?;
-
-        let (ssid, freq, signal) = self.device.wifi_info()?;
-
-        let empty_string = "".to_string();
-        let na_string = "N/A".to_string();
-
-        let values = map!(
-            "ssid" => Value::from_string(ssid.unwrap_or(na_string)),
-            "signal_strength" => Value::from_integer(signal.unwrap_or(0)).percents(),
-            "frequency" => Value::from_float(freq.unwrap_or(0.)).hertz(),
-            "bitrate" => Value::from_string(self.bitrate.clone().unwrap_or_else(|| empty_string.clone())), // TODO: not a String?
-            "ip" => Value::from_string(self.ip_addr.clone().unwrap_or_else(|| empty_string.clone())),
-            "ipv6" => Value::from_string(self.ipv6_addr.clone().unwrap_or(empty_string)),
-            "speed_up" => Value::from_float(self.speed_up).bytes().icon(self.shared_config.get_icon("net_up")?),
-            "speed_down" => Value::from_float(self.speed_down).bytes().icon(self.shared_config.get_icon("net_down")?),
-            "graph_up" => Value::from_string(self.graph_tx.clone()),
-            "graph_down" => Value::from_string(self.graph_rx.clone()),
-        );
-
-        self.output.set_texts(self.format.render(&values)?);
-
         Ok(Some(self.update_interval.into()))
     }
 
     fn view(&self) -> Vec<&dyn I3BarWidget> {
-        if (!self.active && self.hide_inactive) || (!self.exists && self.hide_missing) {
-            vec![]
-        } else {
-            vec![&self.output]
-        }
+        self.interfaces
+            .iter()
+            .filter(|i| (i.active || !self.hide_inactive) && (i.exists || !self.hide_missing))
+            .map(|i| &i.output as &dyn I3BarWidget)
+            .collect()
     }
 
     fn click(&mut self, event: &I3BarEvent) -> Result<()> {
         if event.button == MouseButton::Left {
-            if let Some(ref mut format) = self.format_alt {
-                std::mem::swap(format, &mut self.format);
-            }
-            self.update()?;
+            self.action("toggle_format", event)?;
         }
         Ok(())
     }
 
-    fn id(&self) -> usize {
-        self.id
+    fn action(&mut self, action: &str, _event: &I3BarEvent) -> Result<bool> {
+        if action != "toggle_format" {
+            return Ok(false);
+        }
+        if let Some(ref mut format) = self.format_alt {
+            std::mem::swap(format, &mut self.format);
+        }
+        self.update()?;
+        Ok(true)
     }
-}
 
-#[derive(Deserialize)]
-struct IpDev {
-    addr_info: Option<Vec<IpAddrInfo>>,
-}
+    fn values(&self) -> HashMap<String, Value> {
+        let mut formats = vec![&self.format];
+        formats.extend(&self.format_alt);
+        FormatTemplate::last_values(&formats)
+    }
 
-#[derive(Deserialize)]
-struct IpAddrInfo {
-    local: Option<String>,
+    fn id(&self) -> usize {
+        self.id
+    }
 }
 
 fn decode_escaped_unicode(raw: &[u8]) -> String {
@@ -691,7 +872,8 @@ fn signal_percents(raw: i8) -> i64 {
 
 #[cfg(test)]
 mod tests {
-    use crate::blocks::net::decode_escaped_unicode;
+    use crate::blocks::net::{address_scope, decode_escaped_unicode, default_interface};
+    use crate::netlink::{Link, Route};
 
     #[test]
     fn test_ssid_decode_escaped_unicode() {
@@ -724,4 +906,68 @@ mod tests {
             r" surrounded by spaces ".to_string()
         );
     }
+
+    #[test]
+    fn test_address_scope() {
+        let scope = |address: &str| address_scope(&address.parse().unwrap());
+        assert_eq!(scope("127.0.0.1"), "loopback");
+        assert_eq!(scope("169.254.3.4"), "link_local");
+        assert_eq!(scope("192.168.1.10"), "private");
+        assert_eq!(scope("100.72.0.1"), "private");
+        assert_eq!(scope("93.184.216.34"), "public");
+        assert_eq!(scope("::1"), "loopback");
+        assert_eq!(scope("fe80::1"), "link_local");
+        assert_eq!(scope("fd00::2"), "private");
+        assert_eq!(scope("2001:db8::1"), "public");
+    }
+
+    #[test]
+    fn test_default_interface() {
+        let link = |index, name: &str| Link {
+            index,
+            name: name.to_string(),
+            mtu: Some(1500),
+            up: true,
+        };
+        let default = |ipv6, interface, metric| Route {
+            ipv6,
+            destination: None,
+            prefix: 0,
+            gateway: None,
+            interface: Some(interface),
+            metric,
+        };
+        let links = [
+            link(1, "lo"),
+            link(2, "eth0"),
+            link(3, "wlan0"),
+            link(4, "tun0"),
+        ];
+        let routes = [
+            default(true, 4, 0),
+            default(false, 3, 600),
+            default(false, 2, 100),
+            Route {
+                prefix: 24,
+                ..default(false, 1, 0)
+            },
+        ];
+
+        assert_eq!(
+            default_interface(&links, &routes, |_| true),
+            Some("eth0".to_string())
+        );
+        assert_eq!(
+            default_interface(&links, &routes, |name| name != "eth0"),
+            Some("wlan0".to_string())
+        );
+        assert_eq!(
+            default_interface(&links, &routes, |name| name.starts_with("tun")),
+            Some("tun0".to_string())
+        );
+        assert_eq!(
+            default_interface(&links, &routes, |name| name == "lo"),
+            None
+        );
+    }
 }
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fmt;
 use std::net::Ipv4Addr;
 use std::result;
@@ -17,8 +18,11 @@ use serde_derive::Deserialize;
 use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
+use crate::formatting::unit::Unit;
 use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
+use crate::pause;
 use crate::scheduler::Task;
 use crate::widgets::text::TextWidget;
 use crate::widgets::{I3BarWidget, Spacing, State};
@@ -507,6 +511,19 @@ impl Default for NetworkManagerConfig {
 impl ConfigBlock for NetworkManager {
     type Config = NetworkManagerConfig;
 
+    const PLACEHOLDERS: Schema = &[
+        Field::text("ssid"),
+        Field::integer("strength", Unit::Percents),
+        Field::text("freq"),
+        Field::text("icon"),
+        Field::text("typename"),
+        Field::text("ap"),
+        Field::text("name"),
+        Field::text("ips"),
+        Field::text("devices"),
+        Field::text("id"),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -517,6 +534,33 @@ impl ConfigBlock for NetworkManager {
             .block_error("networkmanager", "failed to establish D-Bus connection")?;
         let manager = ConnectionManager::new();
 
+        fn compile_regexps(patterns: Vec<String>) -> result::Result<Vec<Regex>, regex::Error> {
+            patterns.iter().map(|p| Regex::new(&p)).collect()
+        }
+
+        let networkmanager = NetworkManager {
+            id,
+            indicator: TextWidget::new(id, 0, shared_config.clone()),
+            output: Vec::new(),
+            dbus_conn,
+            manager,
+            primary_only: block_config.primary_only,
+            ap_format: block_config.ap_format.with_default("{ssid}")?,
+            device_format: block_config
+                .device_format
+                .with_default("{icon}{ap} {ips}")?,
+            connection_format: block_config.connection_format.with_default("{devices}")?,
+            interface_name_exclude_regexps: compile_regexps(block_config.interface_name_exclude)
+                .block_error("networkmanager", "failed to parse exclude patterns")?,
+            interface_name_include_regexps: compile_regexps(block_config.interface_name_include)
+                .block_error("networkmanager", "failed to parse include patterns")?,
+            shared_config,
+        };
+
+        if networkmanager.shared_config.dry_run {
+            return Ok(networkmanager);
+        }
+
         thread::Builder::new()
             .name("networkmanager".into())
             .spawn(move || {
@@ -541,46 +585,38 @@ impl ConfigBlock for NetworkManager {
                     let timeout = 300_000;
 
                     for event in c.iter(timeout) {
+                        pause::wait();
                         match event {
                             ConnectionItem::Nothing => (),
-                            _ => send
-                                .send(Task {
+                            _ => {
+                                let task = Task {
                                     id,
                                     update_time: Instant::now(),
-                                })
-                                .unwrap(),
+                                };
+                                if send.send(task).is_err() {
+                                    // The block is gone
+                                    return;
+                                }
+                            }
                         }
                     }
                 }
             })
             .unwrap();
 
-        fn compile_regexps(patterns: Vec<String>) -> result::Result<Vec<Regex>, regex::Error> {
-            patterns.iter().map(|p| Regex::new(&p)).collect()
-        }
-
-        Ok(NetworkManager {
-            id,
-            indicator: TextWidget::new(id, 0, shared_config.clone()),
-            output: Vec::new(),
-            dbus_conn,
-            manager,
-            primary_only: block_config.primary_only,
-            ap_format: block_config.ap_format.with_default("{ssid}")?,
-            device_format: block_config
-                .device_format
-                .with_default("{icon}{ap} {ips}")?,
-            connection_format: block_config.connection_format.with_default("{devices}")?,
-            interface_name_exclude_regexps: compile_regexps(block_config.interface_name_exclude)
-                .block_error("networkmanager", "failed to parse exclude patterns")?,
-            interface_name_include_regexps: compile_regexps(block_config.interface_name_include)
-                .block_error("networkmanager", "failed to parse include patterns")?,
-            shared_config,
-        })
+        Ok(networkmanager)
     }
 }
 
 impl Block for NetworkManager {
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[
+            &self.ap_format,
+            &self.device_format,
+            &self.connection_format,
+        ])
+    }
+
     fn id(&self) -> usize {
         self.id
     }
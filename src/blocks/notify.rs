@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Instant;
@@ -11,8 +12,10 @@ use serde_derive::Deserialize;
 use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
 use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
+use crate::pause;
 use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
 use crate::widgets::text::TextWidget;
@@ -47,6 +50,8 @@ impl Default for NotifyConfig {
 impl ConfigBlock for Notify {
     type Config = NotifyConfig;
 
+    const PLACEHOLDERS: Schema = &[Field::text("state")];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -74,6 +79,18 @@ impl ConfigBlock for Notify {
         #[allow(clippy::mutex_atomic)]
         let state = Arc::new(Mutex::new(initial_state as i64));
         let state_copy = state.clone();
+        let dry_run = shared_config.dry_run;
+
+        let notify = Notify {
+            id,
+            paused: state,
+            format: block_config.format.with_default("")?,
+            output: TextWidget::new(id, 0, shared_config).with_icon(icon)?,
+        };
+
+        if dry_run {
+            return Ok(notify);
+        }
 
         thread::Builder::new()
             .name("notify".into())
@@ -88,6 +105,7 @@ impl ConfigBlock for Notify {
                 c.add_match(&matched_signal).unwrap();
                 loop {
                     for msg in c.incoming(1000) {
+                        pause::wait();
                         if let Some(signal) = PropertiesPropertiesChanged::from_message(&msg) {
                             let value = signal.changed_properties.get("paused").unwrap();
                             let status = &value.0.as_i64().unwrap();
@@ -95,27 +113,29 @@ impl ConfigBlock for Notify {
                             *paused = *status;
 
                             // Tell block to update now.
-                            send.send(Task {
+                            let task = Task {
                                 id,
                                 update_time: Instant::now(),
-                            })
-                            .unwrap();
+                            };
+                            if send.send(task).is_err() {
+                                // The block is gone
+                                return;
+                            }
                         }
                     }
                 }
             })
             .unwrap();
 
-        Ok(Notify {
-            id,
-            paused: state,
-            format: block_config.format.with_default("")?,
-            output: TextWidget::new(id, 0, shared_config).with_icon(icon)?,
-        })
+        Ok(notify)
     }
 }
 
 impl Block for Notify {
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[&self.format])
+    }
+
     fn id(&self) -> usize {
         self.id
     }
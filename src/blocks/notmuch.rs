@@ -8,6 +8,7 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::schema::Schema;
 use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
 use crate::widgets::text::TextWidget;
@@ -74,6 +75,8 @@ fn run_query(db_path: &str, query_string: &str) -> std::result::Result<u32, notm
 impl ConfigBlock for Notmuch {
     type Config = NotmuchConfig;
 
+    const PLACEHOLDERS: Schema = &[];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::process::Command;
 use std::time::Duration;
 
@@ -9,6 +10,10 @@ use crate::config::SharedConfig;
 use crate::config::{LogicalDirection, Scrolling};
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
+use crate::formatting::unit::Unit;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
 use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
 use crate::util::pseudo_uuid;
@@ -32,6 +37,7 @@ pub struct NvidiaGpu {
     memory_widget_mode: MemoryWidgetMode,
 
     show_utilization: Option<TextWidget>,
+    format_utilization: Option<FormatTemplate>,
     show_temperature: Option<TextWidget>,
 
     show_fan: Option<TextWidget>,
@@ -76,6 +82,9 @@ pub struct NvidiaGpuConfig {
     /// GPU utilization. In percent.
     pub show_utilization: bool,
 
+    /// Format override for the utilization widget
+    pub format_utilization: Option<FormatTemplate>,
+
     /// VRAM utilization.
     pub show_memory: bool,
 
@@ -111,6 +120,7 @@ impl Default for NvidiaGpuConfig {
             label: None,
             gpu_id: 0,
             show_utilization: true,
+            format_utilization: None,
             show_memory: true,
             show_temperature: true,
             show_fan_speed: false,
@@ -127,6 +137,8 @@ impl Default for NvidiaGpuConfig {
 impl ConfigBlock for NvidiaGpu {
     type Config = NvidiaGpuConfig;
 
+    const PLACEHOLDERS: Schema = &[Field::integer("utilization", Unit::Percents)];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -169,6 +181,7 @@ impl ConfigBlock for NvidiaGpu {
             } else {
                 None
             },
+            format_utilization: block_config.format_utilization,
 
             show_temperature: if block_config.show_temperature {
                 Some(TextWidget::new(id, id, shared_config.clone()).with_spacing(Spacing::Inline))
@@ -284,7 +297,16 @@ impl Block for NvidiaGpu {
 
             let mut count: usize = 2;
             if let Some(ref mut utilization_widget) = self.show_utilization {
-                utilization_widget.set_text(format!("{:02}%", result[count]));
+                match self.format_utilization {
+                    Some(ref format) => {
+                        let utilization = result[count].parse::<i64>().unwrap_or(0);
+                        let values = map!(
+                            "utilization" => Value::from_integer(utilization).percents(),
+                        );
+                        utilization_widget.set_texts(format.render(&values)?);
+                    }
+                    None => utilization_widget.set_text(format!("{:02}%", result[count])),
+                }
                 count += 1;
             }
             if let Some(ref mut memory_widget) = self.show_memory {
@@ -460,6 +482,11 @@ impl Block for NvidiaGpu {
         Ok(())
     }
 
+    fn values(&self) -> HashMap<String, Value> {
+        let formats: Vec<_> = self.format_utilization.iter().collect();
+        FormatTemplate::last_values(&formats)
+    }
+
     fn id(&self) -> usize {
         self.id
     }
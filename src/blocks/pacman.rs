@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::env;
 use std::ffi::OsString;
 use std::fs;
@@ -14,6 +15,8 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
+use crate::formatting::unit::Unit;
 use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
 use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
@@ -132,6 +135,13 @@ impl PacmanConfig {
 impl ConfigBlock for Pacman {
     type Config = PacmanConfig;
 
+    const PLACEHOLDERS: Schema = &[
+        Field::integer("count", Unit::None),
+        Field::integer("pacman", Unit::None),
+        Field::integer("aur", Unit::None),
+        Field::integer("both", Unit::None),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -141,8 +151,8 @@ impl ConfigBlock for Pacman {
         let output = TextWidget::new(id, 0, shared_config).with_icon("update")?;
 
         let fmt_normal = block_config.format.with_default("{pacman}")?;
-        let fmt_singular = block_config.format_singular.with_default("{pacman}")?;
-        let fmt_up_to_date = block_config.format_up_to_date.with_default("{pacman}")?;
+        let fmt_singular = block_config.format_singular.or(&fmt_normal);
+        let fmt_up_to_date = block_config.format_up_to_date.or(&fmt_normal);
 
         Ok(Pacman {
             id,
@@ -303,6 +313,10 @@ fn has_critical_update(updates: &str, regex: &Regex) -> bool {
 }
 
 impl Block for Pacman {
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[&self.format, &self.format_singular, &self.format_up_to_date])
+    }
+
     fn id(&self) -> usize {
         self.id
     }
@@ -13,7 +13,6 @@ use {
     crossbeam_channel::unbounded,
     lazy_static::lazy_static,
     std::cell::RefCell,
-    std::collections::HashMap,
     std::convert::{TryFrom, TryInto},
     std::ops::Deref,
     std::rc::Rc,
@@ -21,7 +20,7 @@ use {
 };
 
 use std::cmp::{max, min};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::io::Read;
 use std::process::{Command, Stdio};
 use std::thread;
@@ -34,8 +33,11 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::config::{LogicalDirection, Scrolling};
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
+use crate::formatting::unit::Unit;
 use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
+use crate::pause;
 use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
 use crate::subprocess::spawn_child_async;
@@ -63,17 +65,15 @@ struct AlsaSoundDevice {
 }
 
 impl AlsaSoundDevice {
-    fn new(name: String, device: String, natural_mapping: bool) -> Result<Self> {
-        let mut sd = AlsaSoundDevice {
+    /// The device, which is not read until `get_info` is called
+    fn new(name: String, device: String, natural_mapping: bool) -> Self {
+        AlsaSoundDevice {
             name,
             device,
             natural_mapping,
             volume: 0,
             muted: false,
-        };
-        sd.get_info()?;
-
-        Ok(sd)
+        }
     }
 }
 
@@ -174,31 +174,39 @@ impl SoundDevice for AlsaSoundDevice {
             .name("sound_alsa".into())
             .spawn(move || {
                 // Line-buffer to reduce noise.
-                let mut monitor = Command::new("stdbuf")
+                let mut child = Command::new("stdbuf")
                     .args(&["-oL", "alsactl", "monitor"])
                     .stdout(Stdio::piped())
                     .spawn()
-                    .expect("Failed to start alsactl monitor")
+                    .expect("Failed to start alsactl monitor");
+                let mut monitor = child
                     .stdout
+                    .take()
                     .expect("Failed to pipe alsactl monitor output");
 
                 let mut buffer = [0; 1024]; // Should be more than enough.
                 loop {
+                    pause::wait();
                     // Block until we get some output. Doesn't really matter what
                     // the output actually is -- these are events -- we just update
                     // the sound information if *something* happens.
-                    if monitor.read(&mut buffer).is_ok() {
-                        tx_update_request
+                    if monitor.read(&mut buffer).is_ok()
+                        && tx_update_request
                             .send(Task {
                                 id,
                                 update_time: Instant::now(),
                             })
-                            .unwrap();
+                            .is_err()
+                    {
+                        // The block is gone
+                        break;
                     }
                     // Don't update too often. Wait 1/4 second, fast enough for
                     // volume button mashing but slow enough to skip event spam.
                     thread::sleep(Duration::new(0, 250_000_000))
                 }
+                let _ = child.kill();
+                let _ = child.wait();
             })
             .unwrap();
 
@@ -566,14 +574,18 @@ impl PulseAudioClient {
     }
 
     fn send_update_event() {
-        for (id, tx_update_request) in &*PULSEAUDIO_EVENT_LISTENER.lock().unwrap() {
-            tx_update_request
-                .send(Task {
-                    id: *id,
-                    update_time: Instant::now(),
-                })
-                .unwrap();
-        }
+        // Blocks that are gone stop listening
+        PULSEAUDIO_EVENT_LISTENER
+            .lock()
+            .unwrap()
+            .retain(|id, tx_update_request| {
+                tx_update_request
+                    .send(Task {
+                        id: *id,
+                        update_time: Instant::now(),
+                    })
+                    .is_ok()
+            });
     }
 }
 
@@ -820,6 +832,12 @@ impl Sound {
 impl ConfigBlock for Sound {
     type Config = SoundConfig;
 
+    const PLACEHOLDERS: Schema = &[
+        Field::integer("volume", Unit::Percents),
+        Field::text("output_name"),
+        Field::text("output_description"),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -834,10 +852,13 @@ impl ConfigBlock for Sound {
         #[cfg(not(feature = "pulseaudio"))]
         type PulseAudioSoundDevice = AlsaSoundDevice;
 
-        // try to create a pulseaudio device if feature is enabled and `driver != "alsa"`
+        let dry_run = shared_config.dry_run;
+
+        // try to create a pulseaudio device if feature is enabled and `driver != "alsa"`. A dry
+        // run neither connects to PulseAudio nor runs amixer.
         let pulseaudio_device: Result<PulseAudioSoundDevice> = match block_config.driver {
             #[cfg(feature = "pulseaudio")]
-            SoundDriver::Auto | SoundDriver::PulseAudio => {
+            SoundDriver::Auto | SoundDriver::PulseAudio if !dry_run => {
                 let sound_device = PulseAudioSoundDevice::new(block_config.device_kind);
 
                 match block_config.name.as_ref() {
@@ -854,11 +875,17 @@ impl ConfigBlock for Sound {
         // prefer PulseAudio if available and selected, fallback to ALSA
         let device: Box<dyn SoundDevice> = match pulseaudio_device {
             Ok(dev) => Box::new(dev),
-            Err(_) => Box::new(AlsaSoundDevice::new(
-                block_config.name.unwrap_or_else(|| "Master".into()),
-                block_config.device.unwrap_or_else(|| "default".into()),
-                block_config.natural_mapping,
-            )?),
+            Err(_) => {
+                let mut device = AlsaSoundDevice::new(
+                    block_config.name.unwrap_or_else(|| "Master".into()),
+                    block_config.device.unwrap_or_else(|| "default".into()),
+                    block_config.natural_mapping,
+                );
+                if !dry_run {
+                    device.get_info()?;
+                }
+                Box::new(device)
+            }
         };
 
         let mut sound = Self {
@@ -875,7 +902,9 @@ impl ConfigBlock for Sound {
             text: TextWidget::new(id, 0, shared_config).with_icon("volume_empty")?,
         };
 
-        sound.device.monitor(id, tx_update_request)?;
+        if !dry_run {
+            sound.device.monitor(id, tx_update_request)?;
+        }
 
         Ok(sound)
     }
@@ -928,7 +957,7 @@ impl Block for Sound {
             } else {
                 self.text.set_text(String::new());
             }
-            self.text.set_state(State::Warning);
+            self.text.set_state(State::Custom("muted"));
         } else {
             self.text.set_icon(&self.icon(volume))?;
             self.text.set_spacing(Spacing::Normal);
@@ -969,7 +998,116 @@ impl Block for Sound {
         Ok(())
     }
 
+    fn action(&mut self, action: &str, event: &I3BarEvent) -> Result<bool> {
+        match action {
+            "toggle_mute" => self.device.toggle()?,
+            // Clicking at 70% of the width of the block sets the volume to 70% (of `max_vol`)
+            "set_by_position" => {
+                if let Some(position) = event.relative_position() {
+                    let max_vol = self.max_vol.unwrap_or(100);
+                    let volume = (position * max_vol as f64).round() as i32;
+                    self.device
+                        .set_volume(volume - self.device.volume() as i32, self.max_vol)?;
+                }
+            }
+            "volume_up" => self
+                .device
+                .set_volume(self.step_width as i32, self.max_vol)?,
+            "volume_down" => self
+                .device
+                .set_volume(-(self.step_width as i32), self.max_vol)?,
+            _ => return Ok(false),
+        }
+        self.update()?;
+        Ok(true)
+    }
+
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[&self.format])
+    }
+
     fn id(&self) -> usize {
         self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    struct FakeDevice {
+        volume: u32,
+    }
+
+    impl SoundDevice for FakeDevice {
+        fn volume(&self) -> u32 {
+            self.volume
+        }
+        fn muted(&self) -> bool {
+            false
+        }
+        fn output_name(&self) -> String {
+            "fake".to_string()
+        }
+        fn output_description(&self) -> Option<String> {
+            None
+        }
+        fn get_info(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn set_volume(&mut self, step: i32, max_vol: Option<u32>) -> Result<()> {
+            let volume = max(0, self.volume as i32 + step) as u32;
+            self.volume = min(volume, max_vol.unwrap_or(100));
+            Ok(())
+        }
+        fn toggle(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn monitor(&mut self, _: usize, _: Sender<Task>) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn block(max_vol: Option<u32>) -> Sound {
+        let shared_config = SharedConfig::new(&Config::default());
+        Sound {
+            text: TextWidget::new(0, 0, shared_config.clone()),
+            id: 0,
+            device: Box::new(FakeDevice { volume: 50 }),
+            device_kind: DeviceKind::Sink,
+            step_width: 5,
+            format: FormatTemplate::new("{volume}", None).unwrap(),
+            on_click: None,
+            show_volume_when_muted: false,
+            mappings: None,
+            max_vol,
+            scrolling: shared_config.scrolling,
+        }
+    }
+
+    fn click(sound: &mut Sound, relative_x: f64) -> u32 {
+        let event = I3BarEvent {
+            relative_x: Some(relative_x),
+            width: Some(80.),
+            ..I3BarEvent::default()
+        };
+        assert!(sound.action("set_by_position", &event).unwrap());
+        sound.device.volume()
+    }
+
+    #[test]
+    fn set_by_position() {
+        let mut sound = block(None);
+        assert_eq!(click(&mut sound, 0.), 0);
+        assert_eq!(click(&mut sound, 56.), 70);
+        assert_eq!(click(&mut sound, 80.), 100);
+        assert!(sound.text.get_data().full_text.contains("100%"));
+
+        // The whole width stands for `max_vol`
+        let mut sound = block(Some(150));
+        assert_eq!(click(&mut sound, 80.), 150);
+        assert_eq!(click(&mut sound, 40.), 75);
+        assert_eq!(click(&mut sound, 0.), 0);
+    }
+}
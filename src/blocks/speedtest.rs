@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::process::Command;
 use std::sync::{Arc, Mutex};
 use std::thread;
@@ -10,6 +11,8 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
+use crate::formatting::unit::Unit;
 use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
 use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
@@ -85,8 +88,9 @@ fn make_thread(
 ) {
     thread::Builder::new()
         .name("speedtest".into())
-        .spawn(move || loop {
-            if recv.recv().is_ok() {
+        .spawn(move || {
+            // Until the block is gone
+            while recv.recv().is_ok() {
                 if let Ok(output) = get_values() {
                     if let Ok(vals) = parse_values(&output) {
                         if vals.len() == 3 {
@@ -97,11 +101,14 @@ fn make_thread(
 
                             *update = true;
 
-                            done.send(Task {
+                            let task = Task {
                                 id,
                                 update_time: Instant::now(),
-                            })
-                            .unwrap();
+                            };
+                            if done.send(task).is_err() {
+                                // The block is gone
+                                return;
+                            }
                         }
                     }
                 }
@@ -113,6 +120,12 @@ fn make_thread(
 impl ConfigBlock for SpeedTest {
     type Config = SpeedTestConfig;
 
+    const PLACEHOLDERS: Schema = &[
+        Field::float("ping", Unit::Seconds),
+        Field::float("speed_down", Unit::Bits),
+        Field::float("speed_up", Unit::Bits),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -123,8 +136,10 @@ impl ConfigBlock for SpeedTest {
         let (send, recv): (Sender<()>, Receiver<()>) = unbounded();
         let vals = Arc::new(Mutex::new((false, vec![])));
 
-        // Make the update thread
-        make_thread(recv, done, vals.clone(), id);
+        // Make the update thread, unless the block is only being checked
+        if !shared_config.dry_run {
+            make_thread(recv, done, vals.clone(), id);
+        }
 
         Ok(SpeedTest {
             id,
@@ -185,6 +200,10 @@ impl Block for SpeedTest {
         vec![&self.output]
     }
 
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[&self.format])
+    }
+
     fn id(&self) -> usize {
         self.id
     }
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::process::Command;
 use std::time::Duration;
 
@@ -8,6 +9,8 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
+use crate::formatting::unit::Unit;
 use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
 use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
@@ -103,6 +106,11 @@ impl Default for TaskwarriorConfig {
 impl ConfigBlock for Taskwarrior {
     type Config = TaskwarriorConfig;
 
+    const PLACEHOLDERS: Schema = &[
+        Field::integer("count", Unit::None),
+        Field::text("filter_name"),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -123,16 +131,16 @@ impl ConfigBlock for Taskwarrior {
             block_config.filters
         };
 
+        let format = block_config.format.with_default("{count}")?;
+
         Ok(Taskwarrior {
             id,
             update_interval: block_config.interval,
             warning_threshold: block_config.warning_threshold,
             critical_threshold: block_config.critical_threshold,
-            format: block_config.format.with_default("{count}")?,
-            format_singular: block_config.format_singular.with_default("{count}")?,
-            format_everything_done: block_config
-                .format_everything_done
-                .with_default("{count}")?,
+            format_singular: block_config.format_singular.or(&format),
+            format_everything_done: block_config.format_everything_done.or(&format),
+            format,
             filter_index: 0,
             filters,
             output,
@@ -228,6 +236,14 @@ impl Block for Taskwarrior {
         Ok(())
     }
 
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[
+            &self.format,
+            &self.format_singular,
+            &self.format_everything_done,
+        ])
+    }
+
     fn id(&self) -> usize {
         self.id
     }
@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use std::process::Command;
+use std::path::PathBuf;
 use std::time::Duration;
 
 use crossbeam_channel::Sender;
@@ -9,11 +9,13 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
+use crate::formatting::unit::Unit;
 use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
+use crate::hwmon::{self, Kind, Sensor};
 use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
-use crate::util::has_command;
 use crate::widgets::{text::TextWidget, I3BarWidget, Spacing, State};
 
 #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
@@ -29,6 +31,16 @@ impl Default for TemperatureScale {
     }
 }
 
+impl TemperatureScale {
+    /// The temperature `celsius` in this scale
+    fn convert(self, celsius: f64) -> f64 {
+        match self {
+            Self::Celsius => celsius,
+            Self::Fahrenheit => celsius * 9. / 5. + 32.,
+        }
+    }
+}
+
 pub struct Temperature {
     id: usize,
     text: TextWidget,
@@ -40,10 +52,15 @@ pub struct Temperature {
     maximum_idle: i64,
     maximum_info: i64,
     maximum_warning: i64,
+    /// Whether the `max` of an input replaces `maximum_info`, as the config sets none
+    hardware_info: bool,
+    /// Whether the `crit` of an input replaces `maximum_warning`, as the config sets none
+    hardware_warning: bool,
     format: FormatTemplate,
     chip: Option<String>,
     inputs: Option<Vec<String>>,
-    fallback_required: bool,
+    /// Where the chips are listed, `hwmon::HWMON` outside of tests
+    root: PathBuf,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -68,21 +85,22 @@ pub struct TemperatureConfig {
     #[serde(default)]
     pub idle: Option<i64>,
 
-    /// Maximum temperature, below which state is set to info
+    /// Maximum temperature, below which state is set to info. Defaults to the `max` of each input
     #[serde(default)]
     pub info: Option<i64>,
 
-    /// Maximum temperature, below which state is set to warning
+    /// Maximum temperature, below which state is set to warning. Defaults to the `crit` of each
+    /// input
     #[serde(default)]
     pub warning: Option<i64>,
 
     /// Format override
     pub format: FormatTemplate,
 
-    /// Chip override
+    /// Chip override, a name or glob
     pub chip: Option<String>,
 
-    /// Inputs whitelist
+    /// Inputs whitelist, names, labels or globs
     pub inputs: Option<Vec<String>>,
 }
 
@@ -106,6 +124,13 @@ impl Default for TemperatureConfig {
 impl ConfigBlock for Temperature {
     type Config = TemperatureConfig;
 
+    const PLACEHOLDERS: Schema = &[
+        Field::integer("average", Unit::Degrees),
+        Field::integer("min", Unit::Degrees),
+        Field::integer("max", Unit::Degrees),
+        Field::text("max_label"),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -149,118 +174,91 @@ impl ConfigBlock for Temperature {
                     TemperatureScale::Celsius => 80,
                     TemperatureScale::Fahrenheit => 176,
                 }),
+            hardware_info: block_config.info.is_none(),
+            hardware_warning: block_config.warning.is_none(),
             format: block_config
                 .format
                 .with_default("{average} avg, {max} max")?,
             chip: block_config.chip,
             inputs: block_config.inputs,
-            fallback_required: !has_command("temperature", "sensors -j").unwrap_or(false),
+            root: PathBuf::from(hwmon::HWMON),
         })
     }
 }
 
-type SensorsOutput = HashMap<String, HashMap<String, serde_json::Value>>;
-type InputReadings = HashMap<String, f64>;
+impl Temperature {
+    /// The block reading the chips in `root` instead of `hwmon::HWMON`
+    #[cfg(test)]
+    fn with_root(mut self, root: PathBuf) -> Self {
+        self.root = root;
+        self
+    }
 
-impl Block for Temperature {
-    fn update(&mut self) -> Result<Option<Update>> {
-        let mut args = if self.fallback_required {
-            vec!["-u"]
-        } else {
-            vec!["-j"]
+    /// The state of `sensor`, with the limits the hardware sets unless the config sets them
+    fn state(&self, sensor: &Sensor) -> State {
+        let limit = |configured: i64, hardware: Option<f64>, from_hardware: bool| match hardware {
+            Some(limit) if from_hardware => self.scale.convert(limit),
+            _ => configured as f64,
         };
-
-        if let TemperatureScale::Fahrenheit = self.scale {
-            args.push("-f");
-        }
-        if let Some(ref chip) = &self.chip {
-            args.push(chip);
-        }
-        let output = Command::new("sensors")
-            .args(&args)
-            .output()
-            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_owned())
-            .unwrap_or_else(|e| e.to_string());
-
-        let mut temperatures: Vec<i64> = Vec::new();
-
-        if self.fallback_required {
-            for line in output.lines() {
-                if let Some(rest) = line.strip_prefix("  temp") {
-                    let rest = rest
-                        .split('_')
-                        .flat_map(|x| x.split(' '))
-                        .flat_map(|x| x.split('.'))
-                        .collect::<Vec<_>>();
-
-                    if rest[1].starts_with("input") {
-                        match rest[2].parse::<i64>() {
-                            Ok(t) if t == 0 => Ok(()),
-                            Ok(t) if t > -101 && t < 151 => {
-                                temperatures.push(t);
-                                Ok(())
-                            }
-                            Ok(t) => {
-                                // This error is recoverable and therefore should not stop the program
-                                eprintln!("Temperature ({}) outside of range ([-100, 150])", t);
-                                Ok(())
-                            }
-                            Err(_) => Err(BlockError(
-                                "temperature".to_owned(),
-                                "failed to parse temperature as an integer".to_owned(),
-                            )),
-                        }?
-                    }
-                }
-            }
-        } else {
-            let parsed: SensorsOutput = serde_json::from_str(&output)
-                .block_error("temperature", "sensors output is invalid")?;
-            for (_chip, inputs) in parsed {
-                for (input_name, input_values) in inputs {
-                    if let Some(ref whitelist) = self.inputs {
-                        if !whitelist.contains(&input_name) {
-                            continue;
-                        }
-                    }
-
-                    let values_parsed: InputReadings = match serde_json::from_value(input_values) {
-                        Ok(values) => values,
-                        Err(_) => continue, // probably the "Adapter" key, just ignore.
-                    };
-
-                    for (value_name, value) in values_parsed {
-                        if !value_name.starts_with("temp") || !value_name.ends_with("input") {
-                            continue;
-                        }
-
-                        if value > -101f64 && value < 151f64 {
-                            temperatures.push(value as i64);
-                        } else {
-                            // This error is recoverable and therefore should not stop the program
-                            eprintln!("Temperature ({}) outside of range ([-100, 150])", value);
-                        }
-                    }
-                }
-            }
+        let maximum_info = limit(self.maximum_info, sensor.max, self.hardware_info);
+        let maximum_warning = limit(self.maximum_warning, sensor.crit, self.hardware_warning);
+
+        match self.scale.convert(sensor.input) {
+            t if t <= self.maximum_good as f64 => State::Good,
+            t if t <= self.maximum_idle as f64 => State::Idle,
+            t if t <= maximum_info => State::Info,
+            t if t <= maximum_warning => State::Warning,
+            _ => State::Critical,
         }
+    }
+}
 
-        if !temperatures.is_empty() {
-            let max: i64 = *temperatures
+/// How much attention a state asks for
+fn severity(state: &State) -> u8 {
+    match state {
+        State::Good => 0,
+        State::Idle => 1,
+        State::Info => 2,
+        State::Warning => 3,
+        _ => 4,
+    }
+}
+
+impl Block for Temperature {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let chips = hwmon::chips(&self.root)?;
+        let sensors: Vec<&Sensor> = hwmon::select(
+            &chips,
+            &[Kind::Temperature],
+            self.chip.as_deref(),
+            self.inputs.as_deref(),
+        )
+        .map(|(_, sensor)| sensor)
+        // Readings outside of [-100, 150] come from inputs nothing is connected to
+        .filter(|sensor| sensor.input > -101. && sensor.input < 151.)
+        .collect();
+
+        if let Some(hottest) = sensors
+            .iter()
+            .max_by(|a, b| a.input.partial_cmp(&b.input).unwrap())
+        {
+            let temperatures: Vec<f64> = sensors
                 .iter()
-                .max()
-                .block_error("temperature", "failed to get max temperature")?;
-            let min: i64 = *temperatures
+                .map(|sensor| self.scale.convert(sensor.input))
+                .collect();
+            let max = self.scale.convert(hottest.input).round() as i64;
+            let min = temperatures
                 .iter()
-                .min()
-                .block_error("temperature", "failed to get min temperature")?;
-            let avg: i64 = (temperatures.iter().sum::<i64>() as f64 / temperatures.len() as f64)
+                .cloned()
+                .fold(f64::INFINITY, f64::min)
                 .round() as i64;
+            let avg = (temperatures.iter().sum::<f64>() / temperatures.len() as f64).round() as i64;
 
             let values = map!(
                 "average" => Value::from_integer(avg).degrees(),
                 "min" => Value::from_integer(min).degrees(),
-                "max" => Value::from_integer(max).degrees()
+                "max" => Value::from_integer(max).degrees(),
+                "max_label" => Value::from_string(hottest.label().to_string())
             );
 
             self.output = self.format.render(&values)?;
@@ -268,14 +266,11 @@ impl Block for Temperature {
                 self.text.set_texts(self.output.clone());
             }
 
-            let state = match max {
-                m if m <= self.maximum_good => State::Good,
-                m if m <= self.maximum_idle => State::Idle,
-                m if m <= self.maximum_info => State::Info,
-                m if m <= self.maximum_warning => State::Warning,
-                _ => State::Critical,
-            };
-
+            let state = sensors
+                .iter()
+                .map(|sensor| self.state(sensor))
+                .max_by_key(severity)
+                .unwrap_or(State::Idle);
             self.text.set_state(state);
         }
 
@@ -288,20 +283,106 @@ impl Block for Temperature {
 
     fn click(&mut self, e: &I3BarEvent) -> Result<()> {
         if e.button == MouseButton::Left {
-            self.collapsed = !self.collapsed;
-            if self.collapsed {
-                self.text.set_text(String::new());
-                self.text.set_spacing(Spacing::Hidden);
-            } else {
-                self.text.set_texts(self.output.clone());
-                self.text.set_spacing(Spacing::Normal);
-            }
+            self.action("toggle_collapsed", e)?;
         }
 
         Ok(())
     }
 
+    fn action(&mut self, action: &str, _event: &I3BarEvent) -> Result<bool> {
+        if action != "toggle_collapsed" {
+            return Ok(false);
+        }
+        self.collapsed = !self.collapsed;
+        if self.collapsed {
+            self.text.set_text(String::new());
+            self.text.set_spacing(Spacing::Hidden);
+        } else {
+            self.text.set_texts(self.output.clone());
+            self.text.set_spacing(Spacing::Normal);
+        }
+        Ok(true)
+    }
+
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[&self.format])
+    }
+
     fn id(&self) -> usize {
         self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    use crate::hwmon::tests::{block, hwmon_tree};
+
+    #[test]
+    fn hardware_limits() {
+        let sys = hwmon_tree();
+        let chips = hwmon::chips(&sys.path().join("hwmon")).unwrap();
+        let input = |label: &str, input: f64| Sensor {
+            input,
+            ..hwmon::select(&chips, &[Kind::Temperature], None, None)
+                .map(|(_, sensor)| sensor)
+                .find(|sensor| sensor.label() == label)
+                .unwrap()
+                .clone()
+        };
+
+        // The `max` and `crit` of the input replace `info` and `warning`
+        let temperature: Temperature = block("");
+        assert_eq!(temperature.state(&input("Package id 0", 54.)), State::Info);
+        assert_eq!(
+            temperature.state(&input("Package id 0", 101.)),
+            State::Warning
+        );
+        assert_eq!(
+            temperature.state(&input("Package id 0", 106.)),
+            State::Critical
+        );
+        assert_eq!(temperature.state(&input("Composite", 83.)), State::Warning);
+        // Inputs without limits use the defaults
+        assert_eq!(temperature.state(&input("Core 0", 70.)), State::Warning);
+
+        // Limits set in the config win
+        let temperature: Temperature = block("info = 50");
+        assert_eq!(
+            temperature.state(&input("Package id 0", 54.)),
+            State::Warning
+        );
+
+        let temperature: Temperature = block("scale = \"fahrenheit\"");
+        assert_eq!(temperature.state(&input("Package id 0", 54.)), State::Info);
+        assert_eq!(temperature.state(&input("Composite", 86.)), State::Critical);
+    }
+
+    #[test]
+    fn update() {
+        let sys = hwmon_tree();
+        // An input nothing is connected to
+        fs::create_dir_all(sys.path().join("hwmon/hwmon3")).unwrap();
+        fs::write(sys.path().join("hwmon/hwmon3/name"), "it87\n").unwrap();
+        fs::write(sys.path().join("hwmon/hwmon3/temp1_input"), "-128000\n").unwrap();
+        let update = |config: &str| {
+            let mut temperature: Temperature = block(config);
+            temperature = temperature.with_root(sys.path().join("hwmon"));
+            temperature.update().unwrap();
+            temperature.output.0
+        };
+
+        let format = "format = \"{min} {average} {max} {max_label}\"";
+        assert_eq!(update(format), "28° 44° 54° Package id 0");
+        assert_eq!(
+            update(&format!("{}\nchip = \"nvme\"", format)),
+            "39° 39° 39° Composite"
+        );
+        assert_eq!(
+            update(&format!("{}\ninputs = [\"Core *\"]", format)),
+            "50° 50° 51° Core 0"
+        );
+    }
+}
@@ -13,6 +13,7 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::schema::Schema;
 use crate::formatting::FormatTemplate;
 use crate::scheduler::Task;
 use crate::widgets::text::TextWidget;
@@ -58,6 +59,8 @@ impl Default for TimeConfig {
 impl ConfigBlock for Time {
     type Config = TimeConfig;
 
+    const PLACEHOLDERS: Schema = &[];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -9,6 +9,7 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_opt_duration;
 use crate::errors::*;
+use crate::formatting::schema::Schema;
 use crate::protocol::i3bar_event::I3BarEvent;
 use crate::scheduler::Task;
 use crate::widgets::text::TextWidget;
@@ -67,12 +68,19 @@ impl ToggleConfig {
 impl ConfigBlock for Toggle {
     type Config = ToggleConfig;
 
+    const PLACEHOLDERS: Schema = &[];
+
     fn new(
         id: usize,
         block_config: Self::Config,
         shared_config: SharedConfig,
         _tx_update_request: Sender<Task>,
     ) -> Result<Self> {
+        // The icons are only shown once the state is known, look them up for `check` now
+        if shared_config.dry_run {
+            shared_config.get_icon(&block_config.icon_on)?;
+            shared_config.get_icon(&block_config.icon_off)?;
+        }
         Ok(Toggle {
             id,
             text: TextWidget::new(id, 0, shared_config)
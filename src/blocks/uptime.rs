@@ -8,6 +8,7 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::schema::Schema;
 use crate::scheduler::Task;
 use crate::util::read_file;
 use crate::widgets::text::TextWidget;
@@ -38,6 +39,8 @@ impl Default for UptimeConfig {
 impl ConfigBlock for Uptime {
     type Config = UptimeConfig;
 
+    const PLACEHOLDERS: Schema = &[];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -9,6 +9,7 @@ use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::de::deserialize_local_timestamp;
 use crate::errors::*;
+use crate::formatting::schema::Schema;
 use crate::protocol::i3bar_event::I3BarEvent;
 use crate::scheduler::Task;
 use crate::util::xdg_config_home;
@@ -58,12 +59,15 @@ impl Default for WatsonConfig {
 impl ConfigBlock for Watson {
     type Config = WatsonConfig;
 
+    const PLACEHOLDERS: Schema = &[];
+
     fn new(
         id: usize,
         block_config: Self::Config,
         shared_config: SharedConfig,
         tx_update_request: Sender<Task>,
     ) -> Result<Self> {
+        let dry_run = shared_config.dry_run;
         let watson = Watson {
             id,
             text: TextWidget::new(id, 0, shared_config),
@@ -73,6 +77,10 @@ impl ConfigBlock for Watson {
             prev_state: None,
         };
 
+        if dry_run {
+            return Ok(watson);
+        }
+
         // Spin up a thread to watch for changes to the brightness file for the
         // device, and schedule an update if needed.
         thread::spawn(move || {
@@ -107,12 +115,14 @@ impl ConfigBlock for Watson {
                 for event in events {
                     match event.mask {
                         EventMask::CREATE if event.name == Some(&file_name) => {
-                            tx_update_request
-                                .send(Task {
-                                    id,
-                                    update_time: Instant::now(),
-                                })
-                                .expect("unable to send task from watson watcher");
+                            let task = Task {
+                                id,
+                                update_time: Instant::now(),
+                            };
+                            if tx_update_request.send(task).is_err() {
+                                // The block is gone
+                                return;
+                            }
                         }
                         _ => {}
                     }
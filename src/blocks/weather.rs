@@ -9,6 +9,8 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
+use crate::formatting::unit::Unit;
 use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
 use crate::http;
@@ -327,6 +329,18 @@ impl WeatherConfig {
 impl ConfigBlock for Weather {
     type Config = WeatherConfig;
 
+    const PLACEHOLDERS: Schema = &[
+        Field::text("weather"),
+        Field::text("weather_verbose"),
+        Field::integer("temp", Unit::Degrees),
+        Field::integer("humidity", Unit::None),
+        Field::integer("apparent", Unit::Degrees),
+        Field::float("wind", Unit::None),
+        Field::float("wind_kmh", Unit::None),
+        Field::text("direction"),
+        Field::text("location"),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -381,6 +395,10 @@ impl Block for Weather {
         Ok(())
     }
 
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[&self.format])
+    }
+
     fn id(&self) -> usize {
         self.id
     }
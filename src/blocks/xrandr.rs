@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::process::Command;
 use std::str::FromStr;
 use std::time::Duration;
@@ -10,6 +11,8 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::{LogicalDirection, SharedConfig};
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::schema::{Field, Schema};
+use crate::formatting::unit::Unit;
 use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
 use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
@@ -57,6 +60,8 @@ pub struct Xrandr {
     resolution: bool,
     step_width: u32,
     current_idx: usize,
+    /// The template the current monitor was last rendered with
+    format: FormatTemplate,
     shared_config: SharedConfig,
 }
 
@@ -206,8 +211,9 @@ impl Xrandr {
                 "{display}: {brightness}"
             };
 
-            if let Ok(fmt_template) = FormatTemplate::new(format_str, None) {
-                self.text.set_texts(fmt_template.render(&values)?);
+            if let Ok(format) = FormatTemplate::new(format_str, None) {
+                self.text.set_texts(format.render(&values)?);
+                self.format = format;
             }
         }
 
@@ -218,6 +224,14 @@ impl Xrandr {
 impl ConfigBlock for Xrandr {
     type Config = XrandrConfig;
 
+    const PLACEHOLDERS: Schema = &[
+        Field::text("display"),
+        Field::integer("brightness", Unit::Percents),
+        Field::text("brightness_icon"),
+        Field::text("resolution"),
+        Field::text("res_icon"),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -237,6 +251,7 @@ impl ConfigBlock for Xrandr {
             resolution: block_config.resolution,
             step_width,
             monitors: Vec::new(),
+            format: FormatTemplate::default(),
             shared_config,
         })
     }
@@ -293,6 +308,10 @@ impl Block for Xrandr {
         Ok(())
     }
 
+    fn values(&self) -> HashMap<String, Value> {
+        FormatTemplate::last_values(&[&self.format])
+    }
+
     fn id(&self) -> usize {
         self.id
     }
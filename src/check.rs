@@ -0,0 +1,214 @@
+//! Validation of a config file without running the bar.
+//!
+//! The bar stops at the first error of its config. `i3status-rs check` reads every top level key
+//! and every block on its own instead, so that all problems of the file are reported at once.
+//! Blocks are created in a dry run (see `SharedConfig::dry_run`), which catches the errors of
+//! their constructors without starting their threads, subprocesses or connections.
+//! Blocks are checked whatever their `when`. The icons they ask for while they are created are
+//! looked up in the icon set and the sets it extends.
+
+use std::path::{Path, PathBuf};
+
+use serde::de::Deserialize;
+use toml::value::{Table, Value};
+
+use crate::blocks::check_block;
+use crate::config::{Condition, Config, ConfigFile, SharedConfig};
+
+/// A problem of a config file
+pub struct Problem {
+    /// The config file or included file the problem is in
+    pub file: PathBuf,
+    /// The line of the file the problem is on, if it is known
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Problem {
+    fn new(file: &Path, line: Option<usize>, message: String) -> Self {
+        Self {
+            file: file.to_path_buf(),
+            line,
+            message,
+        }
+    }
+}
+
+/// The line of the first `key = ...` or `[key]` at the start of a line
+fn key_line(source: &str, key: &str) -> Option<usize> {
+    source
+        .lines()
+        .position(|line| {
+            let line = line.trim_start();
+            let line = line.strip_prefix('[').unwrap_or(line);
+            line.strip_prefix(key)
+                .map(|rest| matches!(rest.chars().next(), Some(' ' | '=' | ']' | '.')))
+                .unwrap_or_default()
+        })
+        .map(|line| line + 1)
+}
+
+/// Read the config file at `path` and return all of its problems
+pub fn check_config(path: &Path) -> Vec<Problem> {
+    let file = match ConfigFile::read(path) {
+        Ok(file) => file,
+        Err(error) => return vec![Problem::new(path, None, format!("{:?}", error))],
+    };
+    // The lines of the top level keys are looked up in the file itself, even if they come from
+    // an included file
+    let source = std::fs::read_to_string(path).unwrap_or_default();
+    let mut table = file.table;
+    let mut problems = Vec::new();
+    let blocks = table.remove("block");
+
+    // Every key on its own, so that one broken key does not hide the problems of the others
+    let mut valid = Table::new();
+    for (key, value) in table {
+        let line = key_line(&source, &key);
+        if !Config::fields().contains(&key.as_str()) {
+            problems.push(Problem::new(path, line, format!("unknown key '{}'", key)));
+            continue;
+        }
+        let mut single = Table::new();
+        single.insert(key.clone(), value.clone());
+        single.insert("block".to_string(), Value::Array(Vec::new()));
+        match Config::deserialize(Value::Table(single)) {
+            Ok(_) => {
+                valid.insert(key, value);
+            }
+            Err(error) => problems.push(Problem::new(path, line, error.to_string())),
+        }
+    }
+
+    let blocks = match blocks {
+        Some(Value::Array(blocks)) => blocks,
+        Some(_) => {
+            let line = key_line(&source, "block");
+            problems.push(Problem::new(
+                path,
+                line,
+                "'block' must be a list".to_string(),
+            ));
+            Vec::new()
+        }
+        None => Vec::new(),
+    };
+
+    // The blocks are checked against the parts of the config that are fine, the ids of all
+    // blocks together
+    valid.insert("block".to_string(), Value::Array(blocks.clone()));
+    let config = match Config::deserialize(Value::Table(valid)) {
+        Ok(config) => config,
+        Err(error) => {
+            problems.push(Problem::new(path, None, error.to_string()));
+            Config::default()
+        }
+    };
+    let shared_config = SharedConfig::new(&config);
+
+    for (i, (block, origin)) in blocks.into_iter().zip(file.origins).enumerate() {
+        let (path, line) = (origin.file.as_path(), origin.line);
+        let mut block = match block {
+            Value::Table(block) => block,
+            _ => {
+                problems.push(Problem::new(
+                    path,
+                    line,
+                    format!("block #{} is not a table", i),
+                ));
+                continue;
+            }
+        };
+        if let Err(error) = Condition::take(&mut block) {
+            problems.push(Problem::new(
+                path,
+                line,
+                format!("block #{}: {:?}", i, error),
+            ));
+        }
+        let name = match block.remove("block") {
+            Some(Value::String(name)) => name,
+            _ => {
+                problems.push(Problem::new(
+                    path,
+                    line,
+                    format!("block #{} has no `block = \"<name>\"`", i),
+                ));
+                continue;
+            }
+        };
+        if let Err(error) = check_block(&name, Value::Table(block), shared_config.clone()) {
+            problems.push(Problem::new(
+                path,
+                line,
+                format!("block #{} ({}): {:?}", i, name, error),
+            ));
+        }
+        for icon in shared_config.take_missing_icons() {
+            problems.push(Problem::new(
+                path,
+                line,
+                format!(
+                    "block #{} ({}): no icon set has the icon '{}'",
+                    i, name, icon
+                ),
+            ));
+        }
+    }
+
+    // The problems of the file itself first, then those of the files it includes
+    problems
+        .sort_by(|a, b| (a.file != path, &a.file, a.line).cmp(&(b.file != path, &b.file, b.line)));
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+
+    #[test]
+    fn problems() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let file = dir.child("config.toml");
+        file.write_str(
+            r#"
+icons_fromat = " {icon} "
+scrolling = "sideways"
+
+[[block]]
+block = "time"
+
+[[block]]
+block = "cpu"
+interval = "often"
+
+[[block]]
+block = "no_such_block"
+
+[[block]]
+block = "memory"
+state_rules = [{ if = "mem_used > 1", state = "gone" }]
+
+[[block]]
+block = "toggle"
+command_on = "true"
+command_off = "true"
+command_state = "true"
+icon_on = "toggle_onn"
+"#,
+        )
+        .unwrap();
+
+        let problems = check_config(file.path());
+        let lines: Vec<Option<usize>> = problems.iter().map(|problem| problem.line).collect();
+        assert_eq!(
+            lines,
+            [Some(2), Some(3), Some(8), Some(12), Some(15), Some(19)]
+        );
+        assert!(problems[0].message.contains("icons_fromat"));
+        assert!(problems[3].message.contains("no_such_block"));
+        assert!(problems[4].message.contains("gone"));
+        assert!(problems[5].message.contains("'toggle_onn'"));
+    }
+}
@@ -1,30 +1,45 @@
-use std::collections::HashMap;
-use std::rc::Rc;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
 
-use serde::de::{Deserialize, Deserializer};
+use serde::de::{self, Deserialize, Deserializer};
 use serde_derive::Deserialize;
 use toml::value;
 
 use crate::errors;
+use crate::errors::ResultExtInternal;
 use crate::icons::Icons;
 use crate::protocol::i3bar_event::MouseButton;
+use crate::protocol::output::Output;
 use crate::themes::Theme;
+use crate::widgets::State;
+
+mod file;
+
+pub use file::{Condition, ConfigFile};
 
 #[derive(Debug)]
 pub struct SharedConfig {
-    pub theme: Rc<Theme>,
-    icons: Rc<Icons>,
+    pub theme: Arc<Theme>,
+    icons: Arc<Icons>,
     icons_format: String,
     pub scrolling: Scrolling,
+    /// Blocks are only created to check their config. They must not start threads or
+    /// subprocesses, connect to services or touch devices then.
+    pub dry_run: bool,
+    /// The icons blocks asked for during a dry run that no icon set has
+    missing_icons: Arc<Mutex<BTreeSet<String>>>,
 }
 
 impl SharedConfig {
     pub fn new(config: &Config) -> Self {
         Self {
-            theme: Rc::new(config.theme.clone()),
-            icons: Rc::new(config.icons.clone()),
+            theme: Arc::new(config.theme.clone()),
+            icons: Arc::new(config.icons.clone()),
             icons_format: config.icons_format.clone(),
             scrolling: config.scrolling,
+            dry_run: false,
+            missing_icons: Arc::default(),
         }
     }
 
@@ -34,49 +49,51 @@ impl SharedConfig {
 
     pub fn theme_override(&mut self, overrides: &HashMap<String, String>) -> errors::Result<()> {
         let mut theme = self.theme.as_ref().clone();
-        for entry in overrides {
-            match entry.0.as_str() {
-                "idle_fg" => theme.idle_fg = Some(entry.1.to_string()),
-                "idle_bg" => theme.idle_bg = Some(entry.1.to_string()),
-                "info_fg" => theme.info_fg = Some(entry.1.to_string()),
-                "info_bg" => theme.info_bg = Some(entry.1.to_string()),
-                "good_fg" => theme.good_fg = Some(entry.1.to_string()),
-                "good_bg" => theme.good_bg = Some(entry.1.to_string()),
-                "warning_fg" => theme.warning_fg = Some(entry.1.to_string()),
-                "warning_bg" => theme.warning_bg = Some(entry.1.to_string()),
-                "critical_fg" => theme.critical_fg = Some(entry.1.to_string()),
-                "critical_bg" => theme.critical_bg = Some(entry.1.to_string()),
-                x => {
-                    return Err(errors::ConfigurationError(
-                        format!("Theme element \"{}\" cannot be overriden", x),
-                        String::new(),
-                    ))
-                }
-            }
+        for (key, value) in overrides {
+            theme.set(key, value)?;
         }
-        self.theme = Rc::new(theme);
+        self.theme = Arc::new(theme);
         Ok(())
     }
 
+    /// The icon called `icon`, wrapped in `icons_format`. Icons that are missing from the icon
+    /// set and from every set it falls back to are empty.
     pub fn get_icon(&self, icon: &str) -> crate::errors::Result<String> {
-        use crate::errors::OptionExt;
-        Ok(self.icons_format.clone().replace(
-            "{icon}",
-            self.icons
-                .0
-                .get(icon)
-                .internal_error("get_icon()", &format!("icon '{}' not found in your icons file. If you recently upgraded to v0.2 please check NEWS.md.", icon))?,
-        ))
+        self.note_missing_icon(icon);
+        Ok(self.format_icon(self.icons.get(icon, None)))
+    }
+
+    /// Like `get_icon`, but prefers the variant of the icon for `state`, like `bat_full.critical`
+    pub fn get_state_icon(&self, icon: &str, state: State) -> String {
+        self.note_missing_icon(icon);
+        self.format_icon(self.icons.get(icon, Some(state)))
+    }
+
+    fn note_missing_icon(&self, icon: &str) {
+        if self.dry_run && !self.icons.contains(icon) {
+            self.missing_icons.lock().unwrap().insert(icon.to_string());
+        }
+    }
+
+    /// The icons asked for since the last call that no icon set has, for `check`
+    pub fn take_missing_icons(&self) -> BTreeSet<String> {
+        std::mem::take(&mut *self.missing_icons.lock().unwrap())
+    }
+
+    fn format_icon(&self, icon: &str) -> String {
+        self.icons_format.replace("{icon}", icon)
     }
 }
 
 impl Default for SharedConfig {
     fn default() -> Self {
         Self {
-            theme: Rc::new(Theme::default()),
-            icons: Rc::new(Icons::default()),
+            theme: Arc::new(Theme::default()),
+            icons: Arc::new(Icons::default()),
             icons_format: " {icon} ".to_string(),
             scrolling: Scrolling::default(),
+            dry_run: false,
+            missing_icons: Arc::default(),
         }
     }
 }
@@ -84,15 +101,17 @@ impl Default for SharedConfig {
 impl Clone for SharedConfig {
     fn clone(&self) -> Self {
         Self {
-            theme: Rc::clone(&self.theme),
-            icons: Rc::clone(&self.icons),
+            theme: Arc::clone(&self.theme),
+            icons: Arc::clone(&self.icons),
             icons_format: self.icons_format.clone(),
             scrolling: self.scrolling,
+            dry_run: self.dry_run,
+            missing_icons: Arc::clone(&self.missing_icons),
         }
     }
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct Config {
     #[serde(default)]
     pub icons: Icons,
@@ -111,14 +130,42 @@ pub struct Config {
     #[serde(default)]
     pub scrolling: Scrolling,
 
+    /// The bar to print for. It can be overridden on the command line and only takes effect on
+    /// a restart.
+    #[serde(default)]
+    pub output: Output,
+
     #[serde(rename = "block", deserialize_with = "deserialize_blocks")]
     pub blocks: Vec<(String, value::Value)>,
 }
 
 impl Config {
+    /// The keys of a config file
+    pub fn fields() -> &'static [&'static str] {
+        crate::de::struct_fields::<Config>()
+    }
+
+    /// Read the config file at `path` with its includes, its host section and its variables, and
+    /// with the blocks that are meant for this machine
+    pub fn load(path: &Path) -> errors::Result<Config> {
+        let mut file = ConfigFile::read(path)?;
+        file.select_blocks()?;
+        Config::deserialize(value::Value::Table(file.table))
+            .configuration_error("failed to parse config")
+    }
+
     fn default_icons_format() -> String {
         " {icon} ".to_string()
     }
+
+    /// Whether the settings that end up in `SharedConfig` are the same in both configs.
+    /// If they are not, every block has to be rebuilt on reload.
+    pub fn shared_eq(&self, other: &Config) -> bool {
+        self.icons == other.icons
+            && self.theme == other.theme
+            && self.icons_format == other.icons_format
+            && self.scrolling == other.scrolling
+    }
 }
 
 impl Default for Config {
@@ -128,12 +175,13 @@ impl Default for Config {
             theme: Theme::default(),
             icons_format: Config::default_icons_format(),
             scrolling: Scrolling::default(),
+            output: Output::default(),
             blocks: Vec::new(),
         }
     }
 }
 
-#[derive(Deserialize, Copy, Clone, Debug)]
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum Scrolling {
     Reverse,
@@ -170,7 +218,26 @@ where
     D: Deserializer<'de>,
 {
     let mut blocks: Vec<(String, value::Value)> = Vec::new();
+    let mut ids: Vec<&str> = Vec::new();
     let raw_blocks: Vec<value::Table> = Deserialize::deserialize(deserializer)?;
+    for entry in &raw_blocks {
+        if let Some(id) = entry.get("id") {
+            let id = id
+                .as_str()
+                .ok_or_else(|| de::Error::custom("block id must be a string"))?;
+            // Numeric names are the ids of the blocks without a name
+            if id.is_empty() || id.parse::<usize>().is_ok() {
+                return Err(de::Error::custom(format!(
+                    "invalid block id \"{}\", it must not be empty or a number",
+                    id
+                )));
+            }
+            if ids.contains(&id) {
+                return Err(de::Error::custom(format!("duplicate block id \"{}\"", id)));
+            }
+            ids.push(id);
+        }
+    }
     for mut entry in raw_blocks {
         if let Some(name) = entry.remove("block") {
             if let Some(name) = name.as_str() {
@@ -181,3 +248,49 @@ where
 
     Ok(blocks)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocks(config: &str) -> Result<Vec<(String, value::Value)>, String> {
+        toml::from_str::<Config>(config)
+            .map(|config| config.blocks)
+            .map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn block_ids() {
+        let config = blocks(
+            r#"
+            [[block]]
+            block = "sound"
+            id = "vol"
+            [[block]]
+            block = "sound"
+            id = "mic"
+            [[block]]
+            block = "sound"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.len(), 3);
+        assert_eq!(
+            config[0].1.get("id").and_then(|id| id.as_str()),
+            Some("vol")
+        );
+
+        let error = blocks(
+            "[[block]]\nblock = \"sound\"\nid = \"vol\"\n[[block]]\nblock = \"cpu\"\nid = \"vol\"",
+        )
+        .unwrap_err();
+        assert!(error.contains("duplicate block id \"vol\""), "{}", error);
+        // Numbers would be mistaken for the ids of blocks without a name
+        let error = blocks("[[block]]\nblock = \"sound\"\nid = \"2\"").unwrap_err();
+        assert!(error.contains("invalid block id \"2\""), "{}", error);
+        let error = blocks("[[block]]\nblock = \"sound\"\nid = \"\"").unwrap_err();
+        assert!(error.contains("invalid block id \"\""), "{}", error);
+        let error = blocks("[[block]]\nblock = \"sound\"\nid = 2").unwrap_err();
+        assert!(error.contains("block id must be a string"), "{}", error);
+    }
+}
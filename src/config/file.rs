@@ -0,0 +1,467 @@
+//! Reading a config file together with everything it pulls in.
+//!
+//! A config file is read as a plain TOML table first, so that the rest of the bar only ever sees
+//! a single config:
+//! - the files listed in `include` are merged in, the file's own keys take precedence and the
+//!   blocks of the included files come before the file's own blocks,
+//! - the `[hosts.<hostname>]` section of the machine the bar runs on is merged in the same way,
+//! - `${name}` in any string is replaced with the variable `name` of `[vars]`, and
+//!   `${env:NAME}` with the environment variable `NAME`,
+//! - blocks with a `when` condition that does not hold on this machine are dropped.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::de::Deserialize;
+use serde_derive::Deserialize;
+use toml::value::{Table, Value};
+
+use crate::errors::*;
+
+/// How deep config files may include each other
+const MAX_DEPTH: usize = 16;
+
+/// Where a block of a config comes from
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockOrigin {
+    pub file: PathBuf,
+    /// The line of the block's `[[block]]` header, if it is known
+    pub line: Option<usize>,
+}
+
+/// A config file with its includes, its host section and its variables resolved
+#[derive(Debug)]
+pub struct ConfigFile {
+    /// All keys of the config, with `block` holding the blocks
+    pub table: Table,
+    /// The origin of every block, in the order of the blocks
+    pub origins: Vec<BlockOrigin>,
+}
+
+impl ConfigFile {
+    /// Read the config file at `path` and the files it includes. The blocks are not filtered by
+    /// their `when` yet, see `select_blocks`.
+    pub fn read(path: &Path) -> Result<Self> {
+        let mut file = Self::load(path, &hostname(), 0, &mut Vec::new())?;
+        let vars = match file.table.remove("vars") {
+            Some(Value::Table(vars)) => vars,
+            Some(_) => {
+                return Err(ConfigurationError(
+                    "'vars' must be a table".to_string(),
+                    String::new(),
+                ))
+            }
+            None => Table::new(),
+        };
+        for (_, value) in file.table.iter_mut() {
+            substitute(value, &vars)?;
+        }
+        Ok(file)
+    }
+
+    /// The files that make up the config at `path`: the file itself and everything it includes,
+    /// as far as they can be read. A file that fails to parse is still part of the list.
+    pub fn files(path: &Path) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        let _ = Self::load(path, &hostname(), 0, &mut files);
+        files
+    }
+
+    /// Read a single file and its includes, adding the paths of all files it reads to `files`
+    fn load(path: &Path, hostname: &str, depth: usize, files: &mut Vec<PathBuf>) -> Result<Self> {
+        if depth > MAX_DEPTH {
+            return Err(ConfigurationError(
+                format!("Config file '{}' includes itself.", path.display()),
+                String::new(),
+            ));
+        }
+        if !files.iter().any(|file| file == path) {
+            files.push(path.to_path_buf());
+        }
+        let source = fs::read_to_string(path)
+            .configuration_error(&format!("failed to read config file '{}'", path.display()))?;
+        let mut table: Table = toml::from_str(&source).configuration_error(&format!(
+            "failed to parse TOML from config file '{}'",
+            path.display()
+        ))?;
+
+        let includes = match table.remove("include") {
+            Some(Value::String(include)) => vec![include],
+            Some(include) => Vec::deserialize(include).configuration_error(&format!(
+                "'include' of '{}' must be a file or a list of files",
+                path.display()
+            ))?,
+            None => Vec::new(),
+        };
+        let host = match table.remove("hosts") {
+            Some(Value::Table(mut hosts)) => match hosts.remove(hostname) {
+                Some(Value::Table(host)) => Some(host),
+                Some(_) => {
+                    return Err(ConfigurationError(
+                        format!(
+                            "'hosts.{}' of '{}' must be a table",
+                            hostname,
+                            path.display()
+                        ),
+                        String::new(),
+                    ))
+                }
+                None => None,
+            },
+            Some(_) => {
+                return Err(ConfigurationError(
+                    format!("'hosts' of '{}' must be a table", path.display()),
+                    String::new(),
+                ))
+            }
+            None => None,
+        };
+
+        // Relative includes are relative to the including file
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut file = Self {
+            table: Table::new(),
+            origins: Vec::new(),
+        };
+        for include in includes {
+            file.merge(Self::load(&dir.join(include), hostname, depth + 1, files)?);
+        }
+        file.merge(Self::own(path, &source, table, "[[block]]")?);
+        if let Some(host) = host {
+            let header = format!("[[hosts.{}.block]]", hostname);
+            file.merge(Self::own(path, &source, host, &header)?);
+        }
+        Ok(file)
+    }
+
+    /// The keys of a single table of a file, its blocks starting at the lines of `header`
+    fn own(path: &Path, source: &str, table: Table, header: &str) -> Result<Self> {
+        let blocks = match table.get("block") {
+            Some(Value::Array(blocks)) => blocks.len(),
+            Some(_) => {
+                return Err(ConfigurationError(
+                    format!("'block' of '{}' must be a list", path.display()),
+                    String::new(),
+                ))
+            }
+            None => 0,
+        };
+        let lines = block_lines(source, header);
+        let origins = (0..blocks)
+            .map(|i| BlockOrigin {
+                file: path.to_path_buf(),
+                // The lines are only known if every block has a header of its own
+                line: lines.get(i).copied(),
+            })
+            .collect();
+        Ok(Self { table, origins })
+    }
+
+    /// Merge `other` on top of this file. Its tables are merged with the tables of the same name,
+    /// its blocks are added after the blocks of this file and its other values replace those of
+    /// this file.
+    fn merge(&mut self, mut other: Self) {
+        if let Some(Value::Array(blocks)) = other.table.remove("block") {
+            match self
+                .table
+                .entry("block")
+                .or_insert_with(|| Value::Array(Vec::new()))
+            {
+                Value::Array(own) => own.extend(blocks),
+                _ => unreachable!("blocks are checked to be a list"),
+            }
+            self.origins.append(&mut other.origins);
+        }
+        merge_tables(&mut self.table, other.table);
+    }
+
+    /// Drop the blocks whose `when` does not hold on this machine, and remove `when` from the
+    /// others
+    pub fn select_blocks(&mut self) -> Result<()> {
+        let blocks = match self.table.get_mut("block") {
+            Some(Value::Array(blocks)) => blocks,
+            _ => return Ok(()),
+        };
+        let hostname = hostname();
+        let mut origins = std::mem::take(&mut self.origins).into_iter();
+        let mut selected = Vec::new();
+        for mut block in blocks.drain(..) {
+            let origin = origins.next();
+            let holds = match &mut block {
+                Value::Table(block) => match Condition::take(block)? {
+                    Some(condition) => condition.holds(&hostname),
+                    None => true,
+                },
+                _ => true,
+            };
+            if holds {
+                selected.push(block);
+                self.origins.extend(origin);
+            }
+        }
+        *blocks = selected;
+        Ok(())
+    }
+}
+
+fn merge_tables(table: &mut Table, other: Table) {
+    for (key, value) in other {
+        match (table.get_mut(&key), value) {
+            (Some(Value::Table(own)), Value::Table(other)) => merge_tables(own, other),
+            (_, value) => {
+                table.insert(key, value);
+            }
+        }
+    }
+}
+
+/// The lines of all `header`s, like `[[block]]`
+fn block_lines(source: &str, header: &str) -> Vec<usize> {
+    source
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.trim_start().starts_with(header))
+        .map(|(line, _)| line + 1)
+        .collect()
+}
+
+/// The name of the machine, as used by `[hosts.<hostname>]` and `when.host`
+fn hostname() -> String {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|hostname| hostname.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Replace the variables in all strings of `value`. A string that is nothing but a variable
+/// becomes the value of the variable, so that variables can hold numbers, lists and tables as
+/// well.
+fn substitute(value: &mut Value, vars: &Table) -> Result<()> {
+    match value {
+        Value::String(s) => {
+            let whole = s
+                .strip_prefix("${")
+                .and_then(|name| name.strip_suffix('}'))
+                .and_then(|name| vars.get(name));
+            match whole {
+                Some(var) => *value = var.clone(),
+                None => *s = substitute_str(s, vars)?,
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                substitute(value, vars)?;
+            }
+        }
+        Value::Table(table) => {
+            for (_, value) in table.iter_mut() {
+                substitute(value, vars)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Replace every `${name}` of a known variable and every `${env:NAME}` in `s`. Other text in
+/// `${}` is kept, since it may well be a `$` in front of a placeholder of a format.
+fn substitute_str(s: &str, vars: &Table) -> Result<String> {
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        let name = &rest[start + 2..end];
+        let replacement = match (name.strip_prefix("env:"), vars.get(name)) {
+            (Some(var), _) => Some(
+                env::var(var)
+                    .configuration_error(&format!("environment variable '{}' is not set", var))?,
+            ),
+            (None, Some(Value::String(var))) => Some(var.clone()),
+            (None, Some(Value::Integer(var))) => Some(var.to_string()),
+            (None, Some(Value::Float(var))) => Some(var.to_string()),
+            (None, Some(Value::Boolean(var))) => Some(var.to_string()),
+            (None, Some(_)) => {
+                return Err(ConfigurationError(
+                    format!("variable '{}' can't be part of a string", name),
+                    String::new(),
+                ))
+            }
+            (None, None) => None,
+        };
+        match replacement {
+            Some(replacement) => {
+                result.push_str(&rest[..start]);
+                result.push_str(&replacement);
+            }
+            None => result.push_str(&rest[..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// The `when` of a block. A block is only created if all of its conditions hold.
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Condition {
+    /// The machine has one of these hostnames
+    #[serde(default, deserialize_with = "deserialize_hosts")]
+    pub host: Vec<String>,
+    /// The file or directory exists, like `/sys/class/power_supply/BAT0`
+    pub exists: Option<PathBuf>,
+    /// The environment variable is set and not empty
+    pub env: Option<String>,
+}
+
+impl Condition {
+    /// Remove the `when` of `block` and return it
+    pub fn take(block: &mut Table) -> Result<Option<Self>> {
+        match block.remove("when") {
+            Some(when) => Self::deserialize(when)
+                .configuration_error("invalid 'when' of a block")
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn holds(&self, hostname: &str) -> bool {
+        (self.host.is_empty() || self.host.iter().any(|host| host == hostname))
+            && self
+                .exists
+                .as_ref()
+                .map(|path| path.exists())
+                .unwrap_or(true)
+            && self
+                .env
+                .as_ref()
+                .map(|var| env::var_os(var).filter(|value| !value.is_empty()).is_some())
+                .unwrap_or(true)
+    }
+}
+
+fn deserialize_hosts<'de, D>(deserializer: D) -> std::result::Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Hosts {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match Hosts::deserialize(deserializer)? {
+        Hosts::One(host) => vec![host],
+        Hosts::Many(hosts) => hosts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+
+    #[test]
+    fn includes_vars_and_conditions() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        env::set_var("I3RS_TEST_INCLUDES", "from env");
+        dir.child("common.toml")
+            .write_str(&format!(
+                r#"
+icons_format = "{{icon}}"
+
+[vars]
+interval = 5
+bat = "{}"
+
+[theme]
+name = "solarized-dark"
+
+[[block]]
+block = "time"
+interval = "${{interval}}"
+
+[[block]]
+block = "battery"
+when = {{ exists = "${{bat}}" }}
+"#,
+                dir.path().join("BAT0").display()
+            ))
+            .unwrap();
+        let config = dir.child("config.toml");
+        config
+            .write_str(&format!(
+                r##"
+include = "common.toml"
+
+[vars]
+interval = 10
+
+[theme.overrides]
+idle_bg = "#000000"
+
+[[block]]
+block = "cpu"
+format = "${{utilization}} ${{env:I3RS_TEST_INCLUDES}} ${{interval}}s"
+when = {{ host = ["{}", "elsewhere"], exists = "{}" }}
+
+[[block]]
+block = "memory"
+when = {{ host = "elsewhere" }}
+
+[hosts.{}]
+icons_format = " {{icon}} "
+[[hosts.{0}.block]]
+block = "load"
+"##,
+                hostname(),
+                dir.path().display(),
+                hostname()
+            ))
+            .unwrap();
+
+        let mut file = ConfigFile::read(config.path()).unwrap();
+        let blocks = |file: &ConfigFile| -> Vec<String> {
+            file.table["block"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|block| block["block"].as_str().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(blocks(&file), ["time", "battery", "cpu", "memory", "load"]);
+        assert_eq!(file.table["icons_format"].as_str(), Some(" {icon} "));
+        assert_eq!(file.table["theme"]["name"].as_str(), Some("solarized-dark"));
+        assert_eq!(
+            file.table["theme"]["overrides"]["idle_bg"].as_str(),
+            Some("#000000")
+        );
+        let cpu = &file.table["block"][2];
+        assert_eq!(
+            cpu["format"].as_str().unwrap(),
+            "${utilization} from env 10s"
+        );
+        assert_eq!(file.table["block"][0]["interval"].as_integer(), Some(10));
+        let lines: Vec<Option<usize>> = file.origins.iter().map(|origin| origin.line).collect();
+        assert_eq!(lines, [Some(11), Some(15), Some(10), Some(15), Some(21)]);
+
+        file.select_blocks().unwrap();
+        assert_eq!(blocks(&file), ["time", "cpu", "load"]);
+        assert_eq!(file.origins.len(), 3);
+        assert!(file.table["block"][0].get("when").is_none());
+
+        assert_eq!(
+            ConfigFile::files(config.path()),
+            [config.path().to_path_buf(), dir.path().join("common.toml")]
+        );
+
+        dir.child("loop.toml")
+            .write_str("include = [\"loop.toml\"]")
+            .unwrap();
+        assert!(ConfigFile::read(&dir.path().join("loop.toml")).is_err());
+    }
+}
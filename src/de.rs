@@ -3,7 +3,8 @@ use std::time::Duration;
 
 use crate::blocks::Update;
 use chrono::{DateTime, Local};
-use serde::de::{self, Deserialize, Deserializer};
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::forward_to_deserialize_any;
 
 pub fn deserialize_update<'de, D>(deserializer: D) -> Result<Update, D::Error>
 where
@@ -100,11 +101,46 @@ where
     i64::deserialize(deserializer).map(|seconds| Local.timestamp(seconds, 0))
 }
 
+/// The names of the fields of the struct `T`, as its `Deserialize` impl expects them (that is,
+/// with renames applied)
+pub fn struct_fields<'de, T: Deserialize<'de>>() -> &'static [&'static str] {
+    /// A deserializer that records the fields it is asked for and fails
+    struct Fields<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de, 'a> Deserializer<'de> for Fields<'a> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(de::Error::custom("fields recorded"))
+        }
+
+        forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+            option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+            ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(Fields(&mut fields));
+    fields
+}
+
 #[cfg(test)]
 mod tests {
     use crate::blocks::Update;
     use crate::blocks::Update::{Every, Once};
-    use crate::de::{deserialize_duration, deserialize_update};
+    use crate::de::{deserialize_duration, deserialize_update, struct_fields};
     use serde_derive::Deserialize;
     use std::time::Duration;
 
@@ -146,4 +182,18 @@ mod tests {
         let deserialized: UpdateConfig = toml::from_str(duration_toml).unwrap();
         assert_eq!(Once, deserialized.interval);
     }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Renamed {
+        kept: u32,
+        #[serde(rename = "block")]
+        blocks: Vec<u32>,
+    }
+
+    #[test]
+    fn test_struct_fields() {
+        assert_eq!(struct_fields::<Renamed>(), ["kept", "block"]);
+        assert_eq!(struct_fields::<u32>(), [] as [&str; 0]);
+    }
 }
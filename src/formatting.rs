@@ -1,15 +1,21 @@
+pub mod condition;
+pub mod modifier;
 pub mod placeholder;
 pub mod prefix;
+pub mod schema;
 pub mod unit;
 pub mod value;
 
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
+use std::time::Instant;
 
 use serde::de::{MapAccess, Visitor};
 use serde::{de, Deserialize, Deserializer};
 
 use crate::errors::*;
+use condition::Condition;
 use placeholder::unexpected_token;
 use placeholder::Placeholder;
 use value::Value;
@@ -18,12 +24,46 @@ use value::Value;
 enum Token {
     Text(String),
     Var(Placeholder),
+    /// `{if <condition>}...{else}...{end}`
+    Cond(Condition, Vec<Token>, Vec<Token>),
+}
+
+thread_local! {
+    /// The number of the current `sample` of this thread, if one is running
+    static SAMPLE: Cell<Option<usize>> = const { Cell::new(None) };
+    /// The number of the last `sample` of this thread
+    static SAMPLES: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Run `f` as a new sample: the first render of every template in it adds the values of the
+/// sparklines to their history. Any other render only replaces the latest values, so that clicks
+/// and templates rendered several times don't make the sparklines move faster.
+pub fn sample<T>(f: impl FnOnce() -> T) -> T {
+    let number = SAMPLES.with(|samples| {
+        samples.set(samples.get() + 1);
+        samples.get()
+    });
+    let outer = SAMPLE.with(|current| current.replace(Some(number)));
+    let result = f();
+    SAMPLE.with(|current| current.set(outer));
+    result
+}
+
+/// The number of the `sample` running in this thread, if any
+fn current_sample() -> Option<usize> {
+    SAMPLE.with(Cell::get)
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct FormatTemplate {
     full: Option<Vec<Token>>,
     short: Option<Vec<Token>>,
+    /// The recent values of the placeholders that are drawn as sparklines, oldest first
+    history: RefCell<HashMap<String, VecDeque<Option<f64>>>>,
+    /// The number of the sample the latest values of `history` were taken in
+    sampled: Cell<Option<usize>>,
+    /// The placeholder values of the last render, and when it happened
+    rendered: RefCell<Option<(Instant, HashMap<String, Value>)>>,
 }
 
 impl FormatTemplate {
@@ -40,7 +80,13 @@ impl FormatTemplate {
             Some(short) => Some(Self::tokens_from_string(short)?),
             None => None,
         };
-        Ok(Self { full, short })
+        Ok(Self {
+            full,
+            short,
+            history: RefCell::default(),
+            sampled: Cell::default(),
+            rendered: RefCell::default(),
+        })
     }
 
     /// Initialize `full` field if it is `None`
@@ -51,26 +97,44 @@ impl FormatTemplate {
         Ok(self)
     }
 
+    /// Use `fallback` if no format has been configured at all
+    pub fn or(self, fallback: &FormatTemplate) -> Self {
+        if self.full.is_none() && self.short.is_none() {
+            fallback.clone()
+        } else {
+            self
+        }
+    }
+
     /// Whether the format string contains a given placeholder
     pub fn contains(&self, var: &str) -> bool {
         Self::format_contains(&self.full, var) || Self::format_contains(&self.short, var)
     }
 
     fn format_contains(format: &Option<Vec<Token>>, var: &str) -> bool {
-        if let Some(tokens) = format {
-            for token in tokens {
-                if let Token::Var(ref placeholder) = token {
-                    if placeholder.name == var {
-                        return true;
-                    }
-                }
-            }
+        match format {
+            Some(tokens) => Self::tokens_contain(tokens, var),
+            None => false,
         }
-        false
+    }
+
+    fn tokens_contain(tokens: &[Token], var: &str) -> bool {
+        tokens.iter().any(|token| match token {
+            Token::Text(_) => false,
+            Token::Var(placeholder) => placeholder.name == var,
+            Token::Cond(condition, then, otherwise) => {
+                condition.name == var
+                    || Self::tokens_contain(then, var)
+                    || Self::tokens_contain(otherwise, var)
+            }
+        })
     }
 
     fn tokens_from_string(mut s: &str) -> Result<Vec<Token>> {
         let mut tokens = vec![];
+        // The conditional sections that are not closed yet: the condition, the tokens before the
+        // section and, once `{else}` was seen, the tokens of the `then` branch
+        let mut sections: Vec<(Condition, Vec<Token>, Option<Vec<Token>>)> = Vec::new();
 
         // Push text into tokens vector. Check the text for correctness and don't push empty strings
         let push_text = |tokens: &mut Vec<Token>, x: &str| {
@@ -110,10 +174,34 @@ impl FormatTemplate {
                         }
                         // Found the entire placeholder
                         Some((placeholder, rest)) => {
-                            // `placeholder.parse()` parses the placeholder's configuration string
-                            // (e.g. something like `"key:1;K"`) into `Placeholder` struct. We don't
-                            // need to think about that in this code.
-                            tokens.push(Token::Var(placeholder.parse()?));
+                            if let Some(condition) = placeholder.strip_prefix("if ") {
+                                let condition: Condition = condition.parse()?;
+                                schema::check_name(&condition.name)?;
+                                let outer = std::mem::take(&mut tokens);
+                                sections.push((condition, outer, None));
+                            } else if placeholder == "else" {
+                                match sections.last_mut() {
+                                    Some((_, _, then @ None)) => {
+                                        *then = Some(std::mem::take(&mut tokens))
+                                    }
+                                    _ => return Err(Self::misplaced("{else}")),
+                                }
+                            } else if placeholder == "end" {
+                                let (condition, outer, then) =
+                                    sections.pop().ok_or_else(|| Self::misplaced("{end}"))?;
+                                let section = std::mem::replace(&mut tokens, outer);
+                                tokens.push(match then {
+                                    Some(then) => Token::Cond(condition, then, section),
+                                    None => Token::Cond(condition, section, Vec::new()),
+                                });
+                            } else {
+                                // `placeholder.parse()` parses the placeholder's configuration
+                                // string (e.g. something like `"key:1;K"`) into `Placeholder`
+                                // struct. We don't need to think about that in this code.
+                                let placeholder = placeholder.parse()?;
+                                schema::check_placeholder(&placeholder)?;
+                                tokens.push(Token::Var(placeholder));
+                            }
                             s = rest;
                         }
                     }
@@ -121,26 +209,120 @@ impl FormatTemplate {
             }
         }
 
+        if !sections.is_empty() {
+            return Err(InternalError(
+                "format parser".to_string(),
+                "missing '{end}'".to_string(),
+                None,
+            ));
+        }
+
         Ok(tokens)
     }
 
+    fn misplaced(token: &str) -> Error {
+        InternalError(
+            "format parser".to_string(),
+            format!("'{}' without a matching '{{if ...}}'", token),
+            None,
+        )
+    }
+
     pub fn render(&self, vars: &HashMap<&str, Value>) -> Result<(String, Option<String>)> {
+        *self.rendered.borrow_mut() = Some((
+            Instant::now(),
+            vars.iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect(),
+        ));
+        self.record_history(vars);
+        let history = self.history.borrow();
+
         let full = match &self.full {
-            Some(tokens) => Self::render_tokens(tokens, vars)?,
+            Some(tokens) => Self::render_tokens(tokens, vars, &history)?,
             None => String::new(), // TODO: throw an error that says that it's a bug?
         };
         let short = match &self.short {
-            Some(short) => Some(Self::render_tokens(short, vars)?),
+            Some(short) => Some(Self::render_tokens(short, vars, &history)?),
             None => None,
         };
         Ok((full, short))
     }
 
-    fn render_tokens(tokens: &[Token], vars: &HashMap<&str, Value>) -> Result<String> {
+    /// The placeholder values of the most recent render of any of `templates`, for blocks that
+    /// pick one of several templates depending on their state
+    pub fn last_values(templates: &[&FormatTemplate]) -> HashMap<String, Value> {
+        templates
+            .iter()
+            .filter_map(|template| template.rendered.borrow().clone())
+            .max_by_key(|(at, _)| *at)
+            .map(|(_, values)| values)
+            .unwrap_or_default()
+    }
+
+    /// Add the current values of the placeholders that are drawn as sparklines to their history.
+    /// Each `sample` adds one value, and as many are kept as the longest sparkline needs.
+    fn record_history(&self, vars: &HashMap<&str, Value>) {
+        let mut lengths = HashMap::new();
+        for tokens in self.full.iter().chain(self.short.iter()) {
+            Self::sparkline_lengths(tokens, &mut lengths);
+        }
+
+        let sample = current_sample();
+        let new_sample = sample.is_some() && sample != self.sampled.get();
+        if new_sample {
+            self.sampled.set(sample);
+        }
+        let mut history = self.history.borrow_mut();
+        for (name, length) in lengths {
+            let samples = history.entry(name.to_string()).or_default();
+            if !new_sample {
+                samples.pop_back();
+            }
+            samples.push_back(vars.get(name).and_then(Value::as_number));
+            while samples.len() > length {
+                samples.pop_front();
+            }
+        }
+    }
+
+    fn sparkline_lengths<'a>(tokens: &'a [Token], lengths: &mut HashMap<&'a str, usize>) {
+        for token in tokens {
+            match token {
+                Token::Text(_) => {}
+                Token::Var(placeholder) => {
+                    if let Some(sparkline) = placeholder.sparkline {
+                        let length = lengths.entry(&placeholder.name).or_default();
+                        *length = (*length).max(sparkline.samples);
+                    }
+                }
+                Token::Cond(_, then, otherwise) => {
+                    Self::sparkline_lengths(then, lengths);
+                    Self::sparkline_lengths(otherwise, lengths);
+                }
+            }
+        }
+    }
+
+    fn render_tokens(
+        tokens: &[Token],
+        vars: &HashMap<&str, Value>,
+        history: &HashMap<String, VecDeque<Option<f64>>>,
+    ) -> Result<String> {
         let mut rendered = String::new();
         for token in tokens {
             match token {
                 Token::Text(text) => rendered.push_str(&text),
+                Token::Var(Placeholder {
+                    name,
+                    sparkline: Some(sparkline),
+                    bar_max_value,
+                    ..
+                }) => rendered.push_str(&value::format_sparkline(
+                    &history[name],
+                    sparkline,
+                    *bar_max_value,
+                )),
                 Token::Var(var) => rendered.push_str(
                     &vars
                         .get(&*var.name)
@@ -150,6 +332,14 @@ impl FormatTemplate {
                         )?
                         .format(&var)?,
                 ),
+                Token::Cond(condition, then, otherwise) => {
+                    let branch = if condition.eval(vars) {
+                        then
+                    } else {
+                        otherwise
+                    };
+                    rendered.push_str(&Self::render_tokens(branch, vars, history)?);
+                }
             }
         }
         Ok(rendered)
@@ -253,6 +443,69 @@ mod tests {
         );
     }
 
+    #[test]
+    fn render_modifiers() {
+        let ft = FormatTemplate::new("[{title^9|replace(':.*')|upper|right(6)}]", None).unwrap();
+        let values = map!("title" => Value::from_string("vim: main.rs".to_string()));
+        assert_eq!(ft.render(&values).unwrap().0, "[   VIM]");
+    }
+
+    #[test]
+    fn render_conditional() {
+        let ft = FormatTemplate::new(
+            "{count} update{if count != 1}s{end}{if count > 9} (many){else}{if !count} (none){end}{end}",
+            None,
+        )
+        .unwrap();
+
+        let render = |count| {
+            ft.render(&map!("count" => Value::from_integer(count)))
+                .unwrap()
+                .0
+        };
+        assert_eq!(render(1), " 1 update");
+        assert_eq!(render(5), " 5 updates");
+        assert_eq!(render(0), " 0 updates (none)");
+        assert_eq!(render(12), "12 updates (many)");
+
+        assert!(FormatTemplate::new("{if count}", None).is_err());
+        assert!(FormatTemplate::new("{else}", None).is_err());
+        assert!(FormatTemplate::new("{if count}{else}{else}{end}", None).is_err());
+        assert!(FormatTemplate::new("{end}", None).is_err());
+    }
+
+    #[test]
+    fn render_sparkline() {
+        let ft = FormatTemplate::new("[{load~4#8}] [{load~b4}]", None).unwrap();
+
+        let render = |load| {
+            ft.render(&map!("load" => Value::from_integer(load)))
+                .unwrap()
+                .0
+        };
+        assert_eq!(sample(|| render(8)), "[   \u{2588}] [\u{2800}\u{28b8}]");
+        assert_eq!(
+            sample(|| render(4)),
+            "[  \u{2588}\u{2584}] [\u{2800}\u{28e7}]"
+        );
+        assert_eq!(
+            sample(|| render(0)),
+            "[ \u{2588}\u{2584} ] [\u{28b8}\u{2844}]"
+        );
+        // Renders outside of a sample, and further renders in one, only replace the latest value
+        assert_eq!(render(8), "[ \u{2588}\u{2584}\u{2588}] [\u{28b8}\u{28fc}]");
+        assert_eq!(
+            sample(|| {
+                render(6);
+                render(2)
+            }),
+            "[\u{2588}\u{2584}\u{2588}\u{2582}] [\u{28e7}\u{28c7}]"
+        );
+
+        assert!(FormatTemplate::new("{load~0}", None).is_err());
+        assert!(FormatTemplate::new("{load~x}", None).is_err());
+    }
+
     #[test]
     fn contains() {
         let format = FormatTemplate::new("some text {foo} {bar:1} foobar", None);
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use serde::{de, Deserialize, Deserializer};
+
+use super::value::Value;
+use crate::errors::*;
+
+/// Longer operators come first, so that `<=` is not taken for `<`
+const OPERATORS: &[(&str, Operator)] = &[
+    ("==", Operator::Eq),
+    ("!=", Operator::Ne),
+    ("<=", Operator::Le),
+    (">=", Operator::Ge),
+    ("<", Operator::Lt),
+    (">", Operator::Gt),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Number(f64),
+    Text(String),
+}
+
+/// A condition over a placeholder, like `count`, `!count` or `count >= 10`.
+///
+/// Without a comparison the condition holds if the placeholder is set and neither zero nor an
+/// empty string. A missing placeholder never satisfies a condition (unless it is negated).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub name: String,
+    negated: bool,
+    comparison: Option<(Operator, Operand)>,
+}
+
+fn condition_error<T>(condition: &str, reason: &str) -> Result<T> {
+    Err(InternalError(
+        "format parser".to_string(),
+        format!("invalid condition '{}': {}", condition, reason),
+        None,
+    ))
+}
+
+impl FromStr for Condition {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (negated, rest) = match s.trim().strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, s.trim()),
+        };
+
+        let operator = OPERATORS
+            .iter()
+            .filter_map(|&(token, operator)| rest.find(token).map(|pos| (pos, token, operator)))
+            .min_by_key(|&(pos, token, _)| (pos, usize::MAX - token.len()));
+        let (name, comparison) = match operator {
+            None => (rest.trim(), None),
+            Some((pos, token, operator)) => {
+                let operand = rest[pos + token.len()..].trim();
+                if operand.is_empty() {
+                    return condition_error(s, "missing value to compare with");
+                }
+                let operand = match operand.parse() {
+                    Ok(number) => Operand::Number(number),
+                    Err(_) if operator == Operator::Eq || operator == Operator::Ne => {
+                        Operand::Text(operand.trim_matches('\'').to_string())
+                    }
+                    Err(_) => {
+                        return condition_error(s, "text can only be compared with == and !=")
+                    }
+                };
+                (rest[..pos].trim(), Some((operator, operand)))
+            }
+        };
+
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return condition_error(s, "expected a placeholder name");
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            negated,
+            comparison,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Condition {
+    fn deserialize<D>(deserializer: D) -> StdResult<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let condition = String::deserialize(deserializer)?;
+        condition.parse().map_err(de::Error::custom)
+    }
+}
+
+impl Condition {
+    pub fn eval(&self, vars: &HashMap<&str, Value>) -> bool {
+        self.eval_value(vars.get(&*self.name))
+    }
+
+    /// Evaluate the condition for `value`, the value of the placeholder `name`
+    pub fn eval_value(&self, value: Option<&Value>) -> bool {
+        let holds = match value {
+            None => false,
+            Some(value) => match &self.comparison {
+                None => match value.as_number() {
+                    Some(number) => number != 0.,
+                    None => !value.as_text().unwrap_or_default().is_empty(),
+                },
+                Some((operator, Operand::Number(operand))) => match value.as_number() {
+                    Some(number) => match operator {
+                        Operator::Eq => (number - operand).abs() < f64::EPSILON,
+                        Operator::Ne => (number - operand).abs() >= f64::EPSILON,
+                        Operator::Lt => number < *operand,
+                        Operator::Le => number <= *operand,
+                        Operator::Gt => number > *operand,
+                        Operator::Ge => number >= *operand,
+                    },
+                    None => false,
+                },
+                Some((operator, Operand::Text(operand))) => {
+                    let equal = value.as_text() == Some(operand.as_str());
+                    match operator {
+                        Operator::Ne => !equal,
+                        _ => equal,
+                    }
+                }
+            },
+        };
+        holds != self.negated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eval() {
+        let vars = map!(
+            "count" => Value::from_integer(3),
+            "zero" => Value::from_float(0.),
+            "empty" => Value::from_string(String::new()),
+            "state" => Value::from_string("charging".to_string()),
+        );
+        let eval = |condition: &str| condition.parse::<Condition>().unwrap().eval(&vars);
+
+        assert!(eval("count"));
+        assert!(!eval("zero"));
+        assert!(!eval("empty"));
+        assert!(!eval("missing"));
+        assert!(eval("!missing"));
+        assert!(eval("count > 2"));
+        assert!(eval("count<=3"));
+        assert!(!eval("count != 3"));
+        assert!(eval("state == charging"));
+        assert!(eval("state != 'full'"));
+        assert!(!eval("missing == 1"));
+
+        assert!("count > full".parse::<Condition>().is_err());
+        assert!("count >".parse::<Condition>().is_err());
+        assert!("".parse::<Condition>().is_err());
+    }
+}
@@ -0,0 +1,357 @@
+use std::cell::Cell;
+use std::str::FromStr;
+
+use regex::Regex;
+
+use crate::errors::*;
+use crate::util::{escape_pango_text, unescape_pango_text};
+
+/// A transformation of the formatted text of a placeholder, like `trunc(20)` in
+/// `{title|trunc(20)|upper}`
+#[derive(Debug, Clone)]
+pub enum Modifier {
+    Upper,
+    Lower,
+    /// Capitalize every word
+    Title,
+    /// Cut the text to `width` characters, ending with `ellipsis` if anything was cut
+    Trunc {
+        width: usize,
+        ellipsis: String,
+    },
+    /// Pad the text with spaces to `width` characters
+    Align {
+        width: usize,
+        alignment: Alignment,
+    },
+    Replace {
+        regex: Regex,
+        replacement: String,
+    },
+    /// Escape the text for pango markup
+    Escape,
+    /// Turn the escaped text of a block back into plain text
+    Unescape,
+    /// Show a `width` characters long window of the text that moves by one character with every
+    /// `sample`, i.e. every update of the block
+    Scroll {
+        width: usize,
+        separator: String,
+        position: Cell<usize>,
+        /// The number of the sample `position` was moved in
+        sampled: Cell<Option<usize>>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Alignment {
+    Left,
+    Right,
+    Center,
+}
+
+fn modifier_error<T>(modifier: &str, reason: &str) -> Result<T> {
+    Err(InternalError(
+        "format parser".to_string(),
+        format!("invalid modifier '{}': {}", modifier, reason),
+        None,
+    ))
+}
+
+/// Split `s` at every `delimiter` that is neither quoted nor in parentheses
+fn split_outside_quotes(s: &str, delimiter: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut depth = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '\'' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            c if c == delimiter && !quoted && depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// An argument is either a bare word or a single-quoted string with `\'` and `\\` escapes. Other
+/// backslashes are kept, so that regexes can be written as usual.
+fn parse_argument(arg: &str) -> String {
+    let arg = arg.trim();
+    match arg
+        .strip_prefix('\'')
+        .and_then(|arg| arg.strip_suffix('\''))
+    {
+        Some(quoted) => {
+            let mut unquoted = String::with_capacity(quoted.len());
+            let mut chars = quoted.chars();
+            while let Some(c) = chars.next() {
+                match (c, chars.clone().next()) {
+                    ('\\', Some(next @ '\'')) | ('\\', Some(next @ '\\')) => {
+                        unquoted.push(next);
+                        chars.next();
+                    }
+                    (c, _) => unquoted.push(c),
+                }
+            }
+            unquoted
+        }
+        None => arg.to_string(),
+    }
+}
+
+impl Modifier {
+    /// Parse a list of modifiers separated by `|`
+    pub fn parse_list(s: &str) -> Result<Vec<Self>> {
+        split_outside_quotes(s, '|')
+            .into_iter()
+            .map(str::parse)
+            .collect()
+    }
+
+    pub fn apply(&self, text: String) -> String {
+        match self {
+            Self::Upper => text.to_uppercase(),
+            Self::Lower => text.to_lowercase(),
+            Self::Title => {
+                let mut capitalize = true;
+                text.chars()
+                    .flat_map(|c| {
+                        let title: Vec<char> = if capitalize {
+                            c.to_uppercase().collect()
+                        } else {
+                            c.to_lowercase().collect()
+                        };
+                        capitalize = c.is_whitespace();
+                        title
+                    })
+                    .collect()
+            }
+            Self::Trunc { width, ellipsis } => {
+                if text.chars().count() <= *width {
+                    text
+                } else {
+                    let kept = width.saturating_sub(ellipsis.chars().count());
+                    let mut text: String = text.chars().take(kept).collect();
+                    text.push_str(ellipsis);
+                    text
+                }
+            }
+            Self::Align { width, alignment } => {
+                let padding = width.saturating_sub(text.chars().count());
+                let (left, right) = match alignment {
+                    Alignment::Left => (0, padding),
+                    Alignment::Right => (padding, 0),
+                    Alignment::Center => (padding / 2, padding - padding / 2),
+                };
+                format!("{}{}{}", " ".repeat(left), text, " ".repeat(right))
+            }
+            Self::Replace { regex, replacement } => {
+                regex.replace_all(&text, replacement.as_str()).into_owned()
+            }
+            Self::Escape => escape_pango_text(text),
+            Self::Unescape => unescape_pango_text(&text),
+            Self::Scroll {
+                width,
+                separator,
+                position,
+                sampled,
+            } => {
+                // Clicks and templates rendered several times don't make the text move faster
+                let sample = super::current_sample();
+                if sample.is_some() && sample != sampled.get() && sampled.replace(sample).is_some()
+                {
+                    position.set(position.get() + 1);
+                }
+                if text.chars().count() <= *width {
+                    return text;
+                }
+                let cycle: Vec<char> = text.chars().chain(separator.chars()).collect();
+                let start = position.get() % cycle.len();
+                cycle.iter().cycle().skip(start).take(*width).collect()
+            }
+        }
+    }
+}
+
+impl FromStr for Modifier {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (name, args) = match s.split_once('(') {
+            Some((name, args)) => match args.strip_suffix(')') {
+                Some(args) => (
+                    name.trim(),
+                    split_outside_quotes(args, ',')
+                        .into_iter()
+                        .map(parse_argument)
+                        .collect(),
+                ),
+                None => return modifier_error(s, "missing ')'"),
+            },
+            None => (s, Vec::new()),
+        };
+
+        let width = |i: usize| -> Result<usize> {
+            match args.get(i).map(|width| width.parse()) {
+                Some(Ok(width)) => Ok(width),
+                _ => modifier_error(s, "expected a width"),
+            }
+        };
+        let text = |i: usize, default: &str| args.get(i).cloned().unwrap_or_else(|| default.into());
+        let align = |alignment| {
+            Ok(Self::Align {
+                width: width(0)?,
+                alignment,
+            })
+        };
+
+        match name {
+            "upper" => Ok(Self::Upper),
+            "lower" => Ok(Self::Lower),
+            "title" => Ok(Self::Title),
+            "trunc" => Ok(Self::Trunc {
+                width: width(0)?,
+                ellipsis: text(1, "…"),
+            }),
+            "left" => align(Alignment::Left),
+            "right" => align(Alignment::Right),
+            "center" => align(Alignment::Center),
+            "replace" => match args.first().map(|regex| Regex::new(regex)) {
+                Some(Ok(regex)) => Ok(Self::Replace {
+                    regex,
+                    replacement: text(1, ""),
+                }),
+                Some(Err(error)) => modifier_error(s, &error.to_string()),
+                None => modifier_error(s, "expected a regex"),
+            },
+            "escape" => Ok(Self::Escape),
+            "unescape" => Ok(Self::Unescape),
+            "scroll" => Ok(Self::Scroll {
+                width: width(0)?,
+                separator: text(1, " "),
+                position: Cell::new(0),
+                sampled: Cell::new(None),
+            }),
+            _ => modifier_error(s, "unknown modifier"),
+        }
+    }
+}
+
+impl PartialEq for Modifier {
+    fn eq(&self, other: &Self) -> bool {
+        use Modifier::*;
+        match (self, other) {
+            (Upper, Upper) | (Lower, Lower) | (Title, Title) => true,
+            (Escape, Escape) | (Unescape, Unescape) => true,
+            (
+                Trunc {
+                    width: a,
+                    ellipsis: b,
+                },
+                Trunc {
+                    width: c,
+                    ellipsis: d,
+                },
+            ) => a == c && b == d,
+            (
+                Align {
+                    width: a,
+                    alignment: b,
+                },
+                Align {
+                    width: c,
+                    alignment: d,
+                },
+            ) => a == c && b == d,
+            (
+                Replace {
+                    regex: a,
+                    replacement: b,
+                },
+                Replace {
+                    regex: c,
+                    replacement: d,
+                },
+            ) => a.as_str() == c.as_str() && b == d,
+            (
+                Scroll {
+                    width: a,
+                    separator: b,
+                    ..
+                },
+                Scroll {
+                    width: c,
+                    separator: d,
+                    ..
+                },
+            ) => a == c && b == d,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formatting::sample;
+
+    #[test]
+    fn apply() {
+        let apply = |modifiers: &str, text: &str| {
+            Modifier::parse_list(modifiers)
+                .unwrap()
+                .iter()
+                .fold(text.to_string(), |text, modifier| modifier.apply(text))
+        };
+
+        assert_eq!(apply("upper", "abc"), "ABC");
+        assert_eq!(apply("title", "the QUICK fox"), "The Quick Fox");
+        assert_eq!(apply("trunc(5)", "abcdefgh"), "abcd…");
+        assert_eq!(apply("trunc(5, '...')", "abcdefgh"), "ab...");
+        assert_eq!(apply("trunc(5)", "abc"), "abc");
+        assert_eq!(apply("center(7)|trunc(6)", "abc"), "  abc…");
+        assert_eq!(apply("right(4)", "ab"), "  ab");
+        assert_eq!(
+            apply(
+                "replace('^(.*) - (Mozilla Firefox|Chromium)$', '$2: $1')",
+                "Docs - Chromium"
+            ),
+            "Chromium: Docs"
+        );
+        assert_eq!(apply("replace('\\'')", "it's"), "its");
+        assert_eq!(apply("replace('\\(.*\\)')", "a (b) c"), "a  c");
+        assert_eq!(
+            apply("unescape|trunc(3, '')|escape", "a&amp;b&lt;c"),
+            "a&amp;b"
+        );
+
+        let scroll = Modifier::parse_list("scroll(3, '|')").unwrap();
+        let frames: Vec<String> = (0..6)
+            .map(|_| {
+                sample(|| {
+                    // Only the first render of a sample moves the text
+                    scroll[0].apply("abcd".to_string());
+                    scroll[0].apply("abcd".to_string())
+                })
+            })
+            .collect();
+        assert_eq!(frames, ["abc", "bcd", "cd|", "d|a", "|ab", "abc"]);
+        // Renders outside of updates don't move it either
+        assert_eq!(scroll[0].apply("abcd".to_string()), "abc");
+
+        assert!(Modifier::parse_list("trunc").is_err());
+        assert!(Modifier::parse_list("replace('(')").is_err());
+        assert!(Modifier::parse_list("sideways").is_err());
+    }
+}
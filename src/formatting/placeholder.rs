@@ -1,15 +1,18 @@
 use std::str::FromStr;
 
+use super::modifier::Modifier;
 use super::prefix::Prefix;
 use super::unit::Unit;
 use crate::errors::*;
 
-const DELIMETERS: &[char] = &[':', '^', ';', '*', '#'];
+const DELIMETERS: &[char] = &[':', '^', ';', '*', '#', '~'];
 const MIN_WIDTH_TOKEN: char = DELIMETERS[0];
 const MAX_WIDTH_TOKEN: char = DELIMETERS[1];
 const MIN_PREFIX_TOKEN: char = DELIMETERS[2];
 const UNIT_TOKEN: char = DELIMETERS[3];
 const BAR_MAX_VAL_TOKEN: char = DELIMETERS[4];
+const SPARKLINE_TOKEN: char = DELIMETERS[5];
+const MODIFIER_TOKEN: char = '|';
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Placeholder {
@@ -19,6 +22,8 @@ pub struct Placeholder {
     pub min_prefix: MinPrefixConfig,
     pub max_width: Option<usize>,
     pub bar_max_value: Option<f64>,
+    pub sparkline: Option<SparklineConfig>,
+    pub modifiers: Vec<Modifier>,
 }
 
 pub(super) fn unexpected_token<T>(token: char) -> Result<T> {
@@ -33,6 +38,12 @@ impl FromStr for Placeholder {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self> {
+        // Modifiers come last and may contain any of the delimiters in their arguments
+        let (s, modifiers) = match s.split_once(MODIFIER_TOKEN) {
+            Some((s, modifiers)) => (s, Modifier::parse_list(modifiers)?),
+            None => (s, Vec::new()),
+        };
+
         // A handy macro for parsing placeholders configuration
         macro_rules! parse {
             ($delim:expr) => {
@@ -52,6 +63,7 @@ impl FromStr for Placeholder {
         let min_prefix = parse!(MIN_PREFIX_TOKEN);
         let unit = parse!(UNIT_TOKEN);
         let bar_max_value = parse!(BAR_MAX_VAL_TOKEN);
+        let sparkline = parse!(SPARKLINE_TOKEN);
 
         // Parse max_width
         let max_width = if max_width.is_empty() {
@@ -79,6 +91,12 @@ impl FromStr for Placeholder {
             min_prefix: min_prefix.parse()?,
             max_width,
             bar_max_value,
+            sparkline: if sparkline.is_empty() {
+                None
+            } else {
+                Some(sparkline.parse()?)
+            },
+            modifiers,
         })
     }
 }
@@ -160,3 +178,38 @@ impl FromStr for MinPrefixConfig {
         })
     }
 }
+
+/// Draw the history of a value as a sparkline: `[b][l]<samples>`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SparklineConfig {
+    /// How many of the most recent values are drawn
+    pub samples: usize,
+    /// Draw two samples per character with braille dots instead of one with block elements
+    pub braille: bool,
+    /// Scale the heights logarithmically, so that small values remain visible next to large ones
+    pub log: bool,
+}
+
+impl FromStr for SparklineConfig {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let flags = s.len() - s.trim_start_matches(&['b', 'l'][..]).len();
+        let (flags, samples) = s.split_at(flags);
+
+        Ok(Self {
+            samples: match samples.parse() {
+                Ok(samples) if samples > 0 => samples,
+                _ => {
+                    return Err(InternalError(
+                        "format parser".to_string(),
+                        format!("failed to parse sparkline samples '{}'", samples),
+                        None,
+                    ))
+                }
+            },
+            braille: flags.contains('b'),
+            log: flags.contains('l'),
+        })
+    }
+}
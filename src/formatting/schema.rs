@@ -0,0 +1,178 @@
+//! The placeholders a block provides to its formats.
+//!
+//! Every block declares the placeholders it renders its formats with. The formats of a block's
+//! config are checked against them while the config is read, so that a typo in a format string
+//! is a configuration error instead of a block that fails once it renders that format.
+
+use std::cell::Cell;
+use std::fmt;
+
+use super::placeholder::Placeholder;
+use super::unit::Unit;
+use crate::errors::*;
+
+/// The kind of value of a placeholder
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Text,
+    Integer,
+    Float,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            Self::Text => "text",
+            Self::Integer => "integer",
+            Self::Float => "float",
+        })
+    }
+}
+
+/// A placeholder of a schema. A name ending in `<n>`, like `utilization<n>`, stands for a
+/// placeholder per number, like `utilization1` and `utilization2`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Field {
+    pub name: &'static str,
+    pub kind: Kind,
+    pub unit: Unit,
+}
+
+impl Field {
+    pub const fn text(name: &'static str) -> Self {
+        Self {
+            name,
+            kind: Kind::Text,
+            unit: Unit::None,
+        }
+    }
+
+    pub const fn integer(name: &'static str, unit: Unit) -> Self {
+        Self {
+            name,
+            kind: Kind::Integer,
+            unit,
+        }
+    }
+
+    pub const fn float(name: &'static str, unit: Unit) -> Self {
+        Self {
+            name,
+            kind: Kind::Float,
+            unit,
+        }
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        match self.name.strip_suffix("<n>") {
+            Some(prefix) => matches!(
+                name.strip_prefix(prefix),
+                Some(number) if !number.is_empty() && number.bytes().all(|c| c.is_ascii_digit())
+            ),
+            None => self.name == name,
+        }
+    }
+}
+
+/// All placeholders a block provides
+pub type Schema = &'static [Field];
+
+thread_local! {
+    /// The schema the formats parsed by this thread are checked against, while there is one
+    static SCHEMA: Cell<Option<Schema>> = const { Cell::new(None) };
+}
+
+/// Run `f` and check every format it parses against `schema`
+pub fn with_schema<T>(schema: Schema, f: impl FnOnce() -> T) -> T {
+    let outer = SCHEMA.with(|current| current.replace(Some(schema)));
+    let result = f();
+    SCHEMA.with(|current| current.set(outer));
+    result
+}
+
+fn schema_error<T>(message: String) -> Result<T> {
+    Err(InternalError("format parser".to_string(), message, None))
+}
+
+/// The field called `name` of `schema`
+pub fn field(schema: Schema, name: &str) -> Result<Field> {
+    match schema.iter().find(|field| field.matches(name)) {
+        Some(field) => Ok(*field),
+        None if schema.is_empty() => schema_error(format!(
+            "unknown placeholder '{}', the block has no placeholders",
+            name
+        )),
+        None => schema_error(format!(
+            "unknown placeholder '{}', the block has {}",
+            name,
+            schema
+                .iter()
+                .map(|field| field.name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Check that the current schema, if any, has a placeholder called `name`
+pub(super) fn check_name(name: &str) -> Result<()> {
+    match SCHEMA.with(Cell::get) {
+        Some(schema) => field(schema, name).map(|_| ()),
+        None => Ok(()),
+    }
+}
+
+/// Check that the current schema, if any, has `placeholder` and that its options suit the value
+pub(super) fn check_placeholder(placeholder: &Placeholder) -> Result<()> {
+    let schema = match SCHEMA.with(Cell::get) {
+        Some(schema) => schema,
+        None => return Ok(()),
+    };
+    let field = field(schema, &placeholder.name)?;
+    if field.kind == Kind::Text {
+        if placeholder.unit.unit.is_some()
+            || placeholder.bar_max_value.is_some()
+            || placeholder.sparkline.is_some()
+        {
+            return schema_error(format!(
+                "'{}' is text, it has no unit and can't be drawn as a bar or sparkline",
+                placeholder.name
+            ));
+        }
+    } else if let Some(unit) = placeholder.unit.unit {
+        if field.unit.convert(unit).is_err() {
+            return schema_error(format!(
+                "'{}' can't be shown in '{}'",
+                placeholder.name, unit
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formatting::FormatTemplate;
+
+    #[test]
+    fn check_formats() {
+        const SCHEMA: Schema = &[
+            Field::integer("utilization", Unit::Percents),
+            Field::integer("utilization<n>", Unit::Percents),
+            Field::float("speed", Unit::Bytes),
+            Field::text("name"),
+        ];
+        let check = |format: &str| with_schema(SCHEMA, || FormatTemplate::new(format, None));
+
+        assert!(check("{utilization} {utilization12:3} {speed*b} {name|upper}").is_ok());
+        assert!(check("{if utilization > 50}{name}{end}").is_ok());
+        assert!(check("{utilisation}").is_err());
+        assert!(check("{utilization}{utilization1x}").is_err());
+        assert!(check("{if speeed}{end}").is_err());
+        assert!(check("{speed*%}").is_err());
+        assert!(check("{name~8}").is_err());
+        // Formats are only checked while there is a schema
+        assert!(FormatTemplate::new("{utilisation}", None).is_ok());
+    }
+}
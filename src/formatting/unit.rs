@@ -11,7 +11,11 @@ pub enum Unit {
     Degrees,
     Seconds,
     Watts,
+    Volts,
+    Amperes,
     Hertz,
+    /// Revolutions per minute
+    Rpm,
     None,
 }
 
@@ -27,7 +31,10 @@ impl fmt::Display for Unit {
                 Self::Degrees => "°",
                 Self::Seconds => "s",
                 Self::Watts => "W",
+                Self::Volts => "V",
+                Self::Amperes => "A",
                 Self::Hertz => "Hz",
+                Self::Rpm => "RPM",
                 Self::None => "",
             }
         )
@@ -45,7 +52,10 @@ impl FromStr for Unit {
             "deg" => Ok(Unit::Degrees),
             "s" => Ok(Unit::Seconds),
             "W" => Ok(Unit::Watts),
+            "V" => Ok(Unit::Volts),
+            "A" => Ok(Unit::Amperes),
             "Hz" => Ok(Unit::Hertz),
+            "RPM" => Ok(Unit::Rpm),
             "" => Ok(Unit::None),
             x => Err(InternalError(
                 "format parser".to_string(),
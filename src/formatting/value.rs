@@ -1,6 +1,8 @@
 use crate::errors::*;
 
-use super::placeholder::{MinPrefixConfig, Placeholder};
+use std::collections::VecDeque;
+
+use super::placeholder::{MinPrefixConfig, Placeholder, SparklineConfig};
 use super::prefix::Prefix;
 use super::unit::Unit;
 
@@ -124,6 +126,68 @@ fn format_bar(value: f64, length: usize) -> String {
         .collect()
 }
 
+/// Draw `samples` (oldest first) as a sparkline that is `config.samples` long. The top of the
+/// sparkline is `max`, or the largest sample if it is not set. Missing samples are left blank.
+pub(super) fn format_sparkline(
+    samples: &VecDeque<Option<f64>>,
+    config: &SparklineConfig,
+    max: Option<f64>,
+) -> String {
+    let max = max.unwrap_or_else(|| samples.iter().flatten().fold(0., |a, &b| f64::max(a, b)));
+    let scale = |value: f64| {
+        if config.log {
+            value.max(0.).ln_1p() / max.ln_1p()
+        } else {
+            value / max
+        }
+    };
+    // The height of each sample in steps of `levels`, the newest sample is the rightmost one
+    let heights = |levels: usize| -> Vec<usize> {
+        let missing = config.samples.saturating_sub(samples.len());
+        (0..missing)
+            .map(|_| 0)
+            .chain(
+                samples
+                    .iter()
+                    .skip(samples.len().saturating_sub(config.samples))
+                    .map(|sample| match sample {
+                        Some(value) if max > 0. => {
+                            (scale(*value).clamp(0., 1.) * levels as f64).round() as usize
+                        }
+                        _ => 0,
+                    }),
+            )
+            .collect()
+    };
+
+    if config.braille {
+        // The dots of the left and the right column of a braille character, from bottom to top
+        const LEFT: [u32; 4] = [0x40, 0x04, 0x02, 0x01];
+        const RIGHT: [u32; 4] = [0x80, 0x20, 0x10, 0x08];
+        let dots = |column: &[u32; 4], height: usize| column[..height].iter().sum::<u32>();
+
+        let mut heights = heights(4);
+        if heights.len() % 2 == 1 {
+            heights.insert(0, 0);
+        }
+        heights
+            .chunks(2)
+            .map(|pair| {
+                char::from_u32(0x2800 + dots(&LEFT, pair[0]) + dots(&RIGHT, pair[1])).unwrap_or(' ')
+            })
+            .collect()
+    } else {
+        heights(8)
+            .into_iter()
+            .map(|height| match height {
+                0 => ' ',
+                // Lower one eighth block to full block
+                x => char::from_u32(0x2580 + x as u32).unwrap_or(' '),
+            })
+            .collect()
+    }
+}
+
 impl Value {
     // Constuctors
     pub fn from_string(text: String) -> Self {
@@ -186,10 +250,38 @@ impl Value {
         self.unit = Unit::Watts;
         self
     }
+    pub fn volts(mut self) -> Self {
+        self.unit = Unit::Volts;
+        self
+    }
+    pub fn amperes(mut self) -> Self {
+        self.unit = Unit::Amperes;
+        self
+    }
     pub fn hertz(mut self) -> Self {
         self.unit = Unit::Hertz;
         self
     }
+    pub fn rpm(mut self) -> Self {
+        self.unit = Unit::Rpm;
+        self
+    }
+
+    /// The raw number, or the number a text parses to
+    pub fn as_number(&self) -> Option<f64> {
+        match &self.value {
+            InternalValue::Text(text) => text.trim().parse().ok(),
+            InternalValue::Integer(value) => Some(*value as f64),
+            InternalValue::Float(value) => Some(*value),
+        }
+    }
+
+    pub fn as_text(&self) -> Option<&str> {
+        match &self.value {
+            InternalValue::Text(text) => Some(text),
+            _ => None,
+        }
+    }
 
     pub fn format(&self, var: &Placeholder) -> Result<String> {
         // Get user-specified min_width and pad_with values. Use defaults instead
@@ -210,7 +302,7 @@ impl Value {
             }
         }
 
-        let value = match self.value {
+        let mut value = match self.value {
             InternalValue::Text(ref text) => {
                 // Format text value. First pad it to the left with `pad_with` symbol. Then apply
                 // `max_width` option.
@@ -250,6 +342,10 @@ impl Value {
             }
         };
 
+        for modifier in &var.modifiers {
+            value = modifier.apply(value);
+        }
+
         // We prepend the resulting string with the icon if it is set
         let icon_str = self.icon.as_deref().unwrap_or("");
 
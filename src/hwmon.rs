@@ -0,0 +1,550 @@
+//! Reading the sensors of the kernel's hwmon interface.
+//!
+//! Every chip is a directory of `/sys/class/hwmon` with a `name` file and files per input, like
+//! `temp1_input`, `temp1_label` and `temp1_crit`, in the units of the kernel's
+//! `Documentation/hwmon/sysfs-interface.rst`. Chips are named like lm-sensors names them, so
+//! configs written for `sensors` keep working, and both chips and inputs can be selected by glob.
+//! The energy counters of the power capping zones, like those of RAPL, are read as one more chip.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_derive::Deserialize;
+
+use crate::errors::*;
+
+/// Where the kernel lists the chips
+pub const HWMON: &str = "/sys/class/hwmon";
+
+/// Where the kernel lists the power capping zones, like the RAPL domains of Intel and AMD CPUs
+pub const POWERCAP: &str = "/sys/class/powercap";
+
+/// What an input measures
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    /// Degrees Celsius
+    Temperature,
+    /// Revolutions per minute
+    Fan,
+    /// Volts
+    Voltage,
+    /// Amperes
+    Current,
+    /// Watts
+    Power,
+    /// Joules
+    Energy,
+    /// Percents of relative humidity
+    Humidity,
+}
+
+impl Kind {
+    fn from_prefix(prefix: &str) -> Option<Self> {
+        match prefix {
+            "temp" => Some(Self::Temperature),
+            "fan" => Some(Self::Fan),
+            "in" => Some(Self::Voltage),
+            "curr" => Some(Self::Current),
+            "power" => Some(Self::Power),
+            "energy" => Some(Self::Energy),
+            "humidity" => Some(Self::Humidity),
+            _ => None,
+        }
+    }
+
+    /// How many of the unit the kernel reports make one of the unit of the kind
+    fn divisor(self) -> f64 {
+        match self {
+            Self::Fan => 1.,
+            Self::Power | Self::Energy => 1e6,
+            Self::Temperature | Self::Voltage | Self::Current | Self::Humidity => 1e3,
+        }
+    }
+}
+
+/// An input of a chip
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sensor {
+    pub kind: Kind,
+    /// The name of the input, like `temp1`
+    pub name: String,
+    /// The label the driver gives the input, like `Package id 0`
+    pub label: Option<String>,
+    pub input: f64,
+    /// The limit above which the hardware considers the input too high
+    pub max: Option<f64>,
+    /// The limit above which the hardware considers the input critical
+    pub crit: Option<f64>,
+}
+
+impl Sensor {
+    /// The label of the input or, if it has none, its name, as `sensors` shows it
+    pub fn label(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Whether `pattern` matches the name or the label of the input
+    pub fn matches(&self, pattern: &str) -> bool {
+        glob_match(pattern, &self.name)
+            || matches!(self.label, Some(ref label) if glob_match(pattern, label))
+    }
+}
+
+/// A hwmon chip and its inputs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chip {
+    /// The name of the driver, like `coretemp`
+    pub name: String,
+    /// The name lm-sensors gives the chip, like `coretemp-isa-0000`
+    pub id: String,
+    pub sensors: Vec<Sensor>,
+}
+
+impl Chip {
+    /// Whether `pattern` matches the name or the lm-sensors name of the chip
+    pub fn matches(&self, pattern: &str) -> bool {
+        glob_match(pattern, &self.name) || glob_match(pattern, &self.id)
+    }
+}
+
+/// All chips in `root`, which is [`HWMON`] outside of tests, in the order of their numbers
+pub fn chips(root: &Path) -> Result<Vec<Chip>> {
+    let mut paths: Vec<(u32, PathBuf)> = fs::read_dir(root)
+        .internal_error("hwmon", &format!("failed to read {}", root.display()))?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let number = entry
+                .file_name()
+                .to_str()?
+                .strip_prefix("hwmon")?
+                .parse()
+                .ok()?;
+            Some((number, entry.path()))
+        })
+        .collect();
+    paths.sort();
+    Ok(paths
+        .into_iter()
+        .filter_map(|(_, path)| read_chip(&path))
+        .collect())
+}
+
+/// The energy counters of the power capping zones in `root`, which is [`POWERCAP`] outside of
+/// tests, as a chip called `powercap` with an input per zone, named like `intel-rapl:0` and
+/// labeled like `package-0`. Most kernels only let root read the counters.
+pub fn powercap(root: &Path) -> Chip {
+    let mut zones: Vec<PathBuf> = fs::read_dir(root)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .collect()
+        })
+        .unwrap_or_default();
+    zones.sort();
+    let sensors = zones
+        .into_iter()
+        .filter_map(|zone| {
+            Some(Sensor {
+                kind: Kind::Energy,
+                input: read_number(&zone.join("energy_uj"), Kind::Energy)?,
+                label: read_value(&zone.join("name")),
+                name: file_name(&zone)?,
+                max: None,
+                crit: None,
+            })
+        })
+        .collect();
+    Chip {
+        name: "powercap".to_string(),
+        id: "powercap".to_string(),
+        sensors,
+    }
+}
+
+/// The inputs of one of `kinds` of the chips `chip` matches, or of all chips, that one of `inputs`
+/// matches, or all of them, with the lm-sensors names of their chips
+pub fn select<'a, 'b>(
+    chips: &'a [Chip],
+    kinds: &'b [Kind],
+    chip: Option<&'b str>,
+    inputs: Option<&'b [String]>,
+) -> impl Iterator<Item = (&'a str, &'a Sensor)> + 'b
+where
+    'a: 'b,
+{
+    chips
+        .iter()
+        .filter(move |c| chip.map(|pattern| c.matches(pattern)).unwrap_or(true))
+        .flat_map(|c| c.sensors.iter().map(move |sensor| (c.id.as_str(), sensor)))
+        .filter(move |(_, sensor)| {
+            kinds.contains(&sensor.kind)
+                && inputs
+                    .map(|patterns| patterns.iter().any(|pattern| sensor.matches(pattern)))
+                    .unwrap_or(true)
+        })
+}
+
+fn read_value(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|value| value.trim_end().to_string())
+}
+
+fn read_number(path: &Path, kind: Kind) -> Option<f64> {
+    read_value(path)?
+        .parse::<f64>()
+        .ok()
+        .map(|value| value / kind.divisor())
+}
+
+fn read_chip(path: &Path) -> Option<Chip> {
+    // Drivers of old kernels keep their files in the directory of the device
+    let dir = if path.join("name").exists() {
+        path.to_path_buf()
+    } else {
+        path.join("device")
+    };
+    let name = read_value(&dir.join("name"))?;
+
+    let mut inputs: Vec<(Kind, u32, String)> = fs::read_dir(&dir)
+        .ok()?
+        .filter_map(|entry| {
+            let file_name = entry.ok()?.file_name().into_string().ok()?;
+            let input = file_name
+                .strip_suffix("_input")
+                .or_else(|| file_name.strip_suffix("_average"))?;
+            let prefix = input.trim_end_matches(|c: char| c.is_ascii_digit());
+            let number = input[prefix.len()..].parse().ok()?;
+            Some((Kind::from_prefix(prefix)?, number, input.to_string()))
+        })
+        .collect();
+    inputs.sort_by_key(|(kind, number, _)| (*kind as u8, *number));
+    // Power meters may have both `power1_input` and `power1_average`
+    inputs.dedup();
+
+    let sensors = inputs
+        .into_iter()
+        .filter_map(|(kind, _, input)| {
+            let file = |suffix: &str| dir.join(format!("{}_{}", input, suffix));
+            // Inputs the hardware can't read right now fail with EIO or ENODATA
+            let value = read_number(&file("input"), kind)
+                .or_else(|| read_number(&file("average"), kind))?;
+            Some(Sensor {
+                kind,
+                label: read_value(&file("label")),
+                input: value,
+                max: read_number(&file("max"), kind),
+                crit: read_number(&file("crit"), kind),
+                name: input,
+            })
+        })
+        .collect();
+
+    Some(Chip {
+        id: chip_id(&name, path),
+        name,
+        sensors,
+    })
+}
+
+fn file_name(path: &Path) -> Option<String> {
+    path.file_name()?.to_str().map(String::from)
+}
+
+/// The name lm-sensors gives the chip called `name` at `path`, which is built from the bus and
+/// address of its device, like `coretemp-isa-0000`, `nvme-pci-0100` or `lm75-i2c-1-48`
+fn chip_id(name: &str, path: &Path) -> String {
+    let device = match fs::canonicalize(path.join("device")) {
+        Ok(device) => device,
+        Err(_) => return format!("{}-virtual-0", name),
+    };
+    let bus = fs::canonicalize(device.join("subsystem"))
+        .ok()
+        .and_then(|subsystem| file_name(&subsystem));
+    let address = file_name(&device).unwrap_or_default();
+    let number = |s: &str, radix| u32::from_str_radix(s, radix).ok();
+
+    match bus.as_deref() {
+        // `0000:01:00.0` is domain, bus, slot and function
+        Some("pci") => {
+            let parts: Vec<&str> = address.split([':', '.']).collect();
+            match parts[..] {
+                [domain, bus, slot, function] => {
+                    let address = (number(domain, 16).unwrap_or(0) << 16)
+                        + (number(bus, 16).unwrap_or(0) << 8)
+                        + (number(slot, 16).unwrap_or(0) << 3)
+                        + number(function, 16).unwrap_or(0);
+                    format!("{}-pci-{:04x}", name, address)
+                }
+                _ => format!("{}-pci-0000", name),
+            }
+        }
+        // `1-0048` is the adapter and the address
+        Some("i2c") => match address.split_once('-') {
+            Some((adapter, address)) => format!(
+                "{}-i2c-{}-{:02x}",
+                name,
+                adapter,
+                number(address, 16).unwrap_or(0)
+            ),
+            None => format!("{}-i2c-0-00", name),
+        },
+        // `coretemp.0` and `nct6775.656` are the driver and the id, which is the port of ISA chips
+        Some("platform") | Some("isa") => {
+            let id = address
+                .rsplit_once('.')
+                .and_then(|(_, id)| number(id, 10))
+                .unwrap_or(0);
+            format!("{}-isa-{:04x}", name, id)
+        }
+        // `LNXTHERM:00`
+        Some("acpi") => {
+            let id = address
+                .rsplit_once(':')
+                .and_then(|(_, id)| number(id, 10))
+                .unwrap_or(0);
+            format!("{}-acpi-{}", name, id)
+        }
+        Some(bus) if bus != "virtual" => format!("{}-{}-0", name, bus),
+        _ => format!("{}-virtual-0", name),
+    }
+}
+
+/// Whether `pattern`, in which `*` stands for any text and `?` for any character, matches all of
+/// `text`
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was and the text it was tried at
+    let mut star = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            // Let the last `*` take one more character
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    star = Some((star_p, star_t + 1));
+                    p = star_p + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    use crossbeam_channel::unbounded;
+    use serde::de::DeserializeOwned;
+
+    use crate::blocks::ConfigBlock;
+    use crate::config::{Config, SharedConfig};
+
+    /// A block of the blocks that read hwmon, created from `config`
+    pub(crate) fn block<B>(config: &str) -> B
+    where
+        B: ConfigBlock,
+        B::Config: DeserializeOwned,
+    {
+        let config = toml::from_str(config).unwrap();
+        B::new(
+            0,
+            config,
+            SharedConfig::new(&Config::default()),
+            unbounded().0,
+        )
+        .unwrap()
+    }
+
+    /// A sysfs with a CPU, an NVMe drive, an i2c sensor and a thermal zone, like
+    /// `/sys/class/hwmon` and the devices its links point to
+    pub(crate) fn hwmon_tree() -> assert_fs::TempDir {
+        let sys = assert_fs::TempDir::new().unwrap();
+        let write = |path: &str, content: &str| {
+            let path = sys.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, format!("{}\n", content)).unwrap();
+        };
+        let link = |target: &str, path: &str| {
+            let path = sys.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            symlink(target, path).unwrap();
+        };
+        for bus in ["platform", "pci", "i2c"] {
+            fs::create_dir_all(sys.path().join("bus").join(bus)).unwrap();
+        }
+
+        link("../../bus/platform", "devices/coretemp.0/subsystem");
+        link("../../devices/coretemp.0", "hwmon/hwmon0/device");
+        write("hwmon/hwmon0/name", "coretemp");
+        write("hwmon/hwmon0/temp1_input", "54000");
+        write("hwmon/hwmon0/temp1_label", "Package id 0");
+        write("hwmon/hwmon0/temp1_max", "100000");
+        write("hwmon/hwmon0/temp1_crit", "105000");
+        write("hwmon/hwmon0/temp2_input", "51000");
+        write("hwmon/hwmon0/temp2_label", "Core 0");
+        write("hwmon/hwmon0/temp3_input", "49500");
+        write("hwmon/hwmon0/temp3_label", "Core 1");
+
+        link("../../bus/pci", "devices/0000:01:00.0/subsystem");
+        link("../../devices/0000:01:00.0", "hwmon/hwmon2/device");
+        write("hwmon/hwmon2/name", "nvme");
+        write("hwmon/hwmon2/temp1_input", "38850");
+        write("hwmon/hwmon2/temp1_label", "Composite");
+        write("hwmon/hwmon2/temp1_max", "81850");
+        write("hwmon/hwmon2/temp1_crit", "84850");
+
+        link("../../bus/i2c", "devices/1-0048/subsystem");
+        link("../../devices/1-0048", "hwmon/hwmon10/device");
+        write("hwmon/hwmon10/name", "ina219");
+        write("hwmon/hwmon10/in0_input", "12");
+        write("hwmon/hwmon10/in1_input", "12048");
+        write("hwmon/hwmon10/curr1_input", "1500");
+        write("hwmon/hwmon10/power1_input", "18072000");
+        write("hwmon/hwmon10/power1_average", "18000000");
+        write("hwmon/hwmon10/fan1_input", "1250");
+        // An input the hardware can't read
+        fs::create_dir_all(sys.path().join("hwmon/hwmon10/temp1_input")).unwrap();
+
+        write("hwmon/hwmon1/name", "acpitz");
+        write("hwmon/hwmon1/temp1_input", "27800");
+        write("hwmon/hwmon1/temp1_crit", "119000");
+
+        write("powercap/intel-rapl/enabled", "1");
+        write("powercap/intel-rapl:0/name", "package-0");
+        write("powercap/intel-rapl:0/energy_uj", "72120533411");
+        write("powercap/intel-rapl:0:0/name", "core");
+        write("powercap/intel-rapl:0:0/energy_uj", "25006412088");
+        // A counter only root can read
+        write("powercap/intel-rapl:0:1/name", "uncore");
+        fs::create_dir_all(sys.path().join("powercap/intel-rapl:0:1/energy_uj")).unwrap();
+        sys
+    }
+
+    #[test]
+    fn read_chips() {
+        let sys = hwmon_tree();
+        let chips = chips(&sys.path().join("hwmon")).unwrap();
+
+        let ids: Vec<&str> = chips.iter().map(|chip| chip.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            [
+                "coretemp-isa-0000",
+                "acpitz-virtual-0",
+                "nvme-pci-0100",
+                "ina219-i2c-1-48"
+            ]
+        );
+
+        assert_eq!(
+            chips[0].sensors[0],
+            Sensor {
+                kind: Kind::Temperature,
+                name: "temp1".to_string(),
+                label: Some("Package id 0".to_string()),
+                input: 54.,
+                max: Some(100.),
+                crit: Some(105.),
+            }
+        );
+        assert_eq!(chips[1].sensors[0].label(), "temp1");
+
+        let ina219: Vec<(Kind, &str, f64)> = chips[3]
+            .sensors
+            .iter()
+            .map(|sensor| (sensor.kind, sensor.label(), sensor.input))
+            .collect();
+        assert_eq!(
+            ina219,
+            [
+                (Kind::Fan, "fan1", 1250.),
+                (Kind::Voltage, "in0", 0.012),
+                (Kind::Voltage, "in1", 12.048),
+                (Kind::Current, "curr1", 1.5),
+                (Kind::Power, "power1", 18.072),
+            ]
+        );
+    }
+
+    #[test]
+    fn read_powercap() {
+        let sys = hwmon_tree();
+        let chip = powercap(&sys.path().join("powercap"));
+        let zones: Vec<(&str, &str, f64)> = chip
+            .sensors
+            .iter()
+            .map(|sensor| (sensor.name.as_str(), sensor.label(), sensor.input))
+            .collect();
+        assert_eq!(
+            zones,
+            [
+                ("intel-rapl:0", "package-0", 72120.533411),
+                ("intel-rapl:0:0", "core", 25006.412088),
+            ]
+        );
+        assert!(powercap(&sys.path().join("missing")).sensors.is_empty());
+    }
+
+    #[test]
+    fn select_inputs() {
+        let sys = hwmon_tree();
+        let chips = chips(&sys.path().join("hwmon")).unwrap();
+        let select = |chip: Option<&str>, inputs: Option<&[String]>| -> Vec<String> {
+            select(&chips, &[Kind::Temperature], chip, inputs)
+                .map(|(_, sensor)| sensor.label().to_string())
+                .collect()
+        };
+        let cores = ["Core *".to_string()];
+        let first = ["temp1".to_string()];
+
+        assert_eq!(select(None, None).len(), 5);
+        assert_eq!(select(Some("coretemp"), Some(&cores)), ["Core 0", "Core 1"]);
+        assert_eq!(select(Some("*-pci-*"), None), ["Composite"]);
+        assert_eq!(
+            select(Some("coretemp-isa-0000"), Some(&first)),
+            ["Package id 0"]
+        );
+        assert!(select(Some("k10temp"), None).is_empty());
+
+        let kinds = [Kind::Voltage, Kind::Current];
+        let inputs: Vec<(&str, &str)> = super::select(&chips, &kinds, None, None)
+            .map(|(chip, sensor)| (chip, sensor.label()))
+            .collect();
+        assert_eq!(
+            inputs,
+            [
+                ("ina219-i2c-1-48", "in0"),
+                ("ina219-i2c-1-48", "in1"),
+                ("ina219-i2c-1-48", "curr1")
+            ]
+        );
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob_match("coretemp", "coretemp"));
+        assert!(glob_match("core*", "coretemp"));
+        assert!(glob_match("*-isa-*", "coretemp-isa-0000"));
+        assert!(glob_match("Core ?", "Core 1"));
+        assert!(glob_match("*a*b", "xaxxab"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("Core ?", "Core 10"));
+        assert!(!glob_match("core", "coretemp"));
+        assert!(!glob_match("*-pci-*", "coretemp-isa-0000"));
+    }
+}
@@ -1,12 +1,15 @@
 use std::collections::HashMap;
 use std::fmt;
+use std::path::Path;
 
 use serde::de::{self, Deserialize, Deserializer, MapAccess, Visitor};
 use serde_derive::Deserialize;
 
+use crate::errors::*;
 use crate::util;
+use crate::widgets::State;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Icons(pub HashMap<String, String>);
 
 impl Default for Icons {
@@ -103,19 +106,61 @@ impl Default for Icons {
     }
 }
 
+/// How deep icon sets may extend each other
+const MAX_DEPTH: usize = 16;
+
 impl Icons {
-    pub fn from_file(file: &str) -> Option<Self> {
+    /// Read an icon set and the sets it extends. Every chain of sets ends with the "none" set,
+    /// so that an icon a set lacks is still shown as text.
+    pub fn from_file(file: &str) -> Result<Self> {
+        Self::load(file, None, 0).map(Self)
+    }
+
+    /// Sets are looked up next to the set that extends them first, so that the bundled sets
+    /// find each other wherever they are installed
+    fn load(file: &str, dir: Option<&Path>, depth: usize) -> Result<HashMap<String, String>> {
         if file == "none" {
-            Some(Icons::default())
-        } else {
-            let file = util::find_file(file, Some("icons"), Some("toml"))?;
-            Some(Icons(util::deserialize_file(&file).ok()?))
+            return Ok(Icons::default().0);
         }
+        if depth > MAX_DEPTH {
+            return Err(ConfigurationError(
+                format!("Icon set '{}' extends itself.", file),
+                String::new(),
+            ));
+        }
+        let path = dir
+            .map(|dir| dir.join(file).with_extension("toml"))
+            .filter(|path| path.exists())
+            .or_else(|| util::find_file(file, Some("icons"), Some("toml")))
+            .ok_or_else(|| {
+                ConfigurationError(format!("Icon set '{}' not found.", file), String::new())
+            })?;
+        let mut own: HashMap<String, String> = util::deserialize_file(&path)?;
+        let mut icons = match own.remove("extend") {
+            Some(extend) => Self::load(&extend, path.parent(), depth + 1)?,
+            None => Icons::default().0,
+        };
+        icons.extend(own);
+        Ok(icons)
+    }
+
+    /// Whether any set has the icon called `name`
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    /// The icon called `name`, or its variant `<name>.<state>` if there is one. Icons that no set
+    /// has are empty.
+    pub fn get(&self, name: &str, state: Option<State>) -> &str {
+        state
+            .and_then(|state| self.0.get(&format!("{}.{}", name, state.name())))
+            .or_else(|| self.0.get(name))
+            .map_or("", String::as_str)
     }
 }
 
 impl<'de> Deserialize<'de> for Icons {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    fn deserialize<D>(deserializer: D) -> StdResult<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
@@ -141,12 +186,11 @@ impl<'de> Deserialize<'de> for Icons {
             /// ```toml
             /// icons = "awesome"
             /// ```
-            fn visit_str<E>(self, file: &str) -> Result<Icons, E>
+            fn visit_str<E>(self, file: &str) -> StdResult<Icons, E>
             where
                 E: de::Error,
             {
-                Icons::from_file(file)
-                    .ok_or_else(|| de::Error::custom(format!("Icon set '{}' not found.", file)))
+                Icons::from_file(file).map_err(de::Error::custom)
             }
 
             /// Handle configs like:
@@ -155,11 +199,11 @@ impl<'de> Deserialize<'de> for Icons {
             /// [icons]
             /// name = "awesome"
             /// ```
-            fn visit_map<V>(self, mut map: V) -> Result<Icons, V::Error>
+            fn visit_map<V>(self, mut map: V) -> StdResult<Icons, V::Error>
             where
                 V: MapAccess<'de>,
             {
-                let mut icons: Option<&str> = None;
+                let mut icons: Option<String> = None;
                 let mut overrides: Option<HashMap<String, String>> = None;
                 while let Some(key) = map.next_key()? {
                     match key {
@@ -186,9 +230,7 @@ impl<'de> Deserialize<'de> for Icons {
                 }
 
                 let mut icons = match icons {
-                    Some(icons) => Icons::from_file(icons).ok_or_else(|| {
-                        de::Error::custom(format!("Icon set '{}' not found", icons))
-                    })?,
+                    Some(icons) => Icons::from_file(&icons).map_err(de::Error::custom)?,
                     None => Icons::default(),
                 };
 
@@ -204,3 +246,54 @@ impl<'de> Deserialize<'de> for Icons {
         deserializer.deserialize_any(IconsVisitor)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+
+    #[test]
+    fn extend_and_variants() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let base = dir.child("base.toml");
+        base.write_str("cpu = \"C\"\n\"bat_full.critical\" = \"!\"\n")
+            .unwrap();
+        let child = dir.child("child.toml");
+        child
+            .write_str(&format!(
+                "extend = \"{}\"\nbat_full = \"B\"\n",
+                base.path().display()
+            ))
+            .unwrap();
+
+        let icons = Icons::from_file(child.path().to_str().unwrap()).unwrap();
+        assert_eq!(icons.get("cpu", None), "C");
+        assert_eq!(icons.get("bat_full", Some(State::Good)), "B");
+        assert_eq!(icons.get("bat_full", Some(State::Critical)), "!");
+        // The "none" set is the last fallback
+        assert_eq!(icons.get("time", None), "TIME");
+        assert_eq!(icons.get("no_such_icon", None), "");
+        assert!(!icons.0.contains_key("extend"));
+    }
+
+    #[test]
+    fn bundled_sets() {
+        let set = |name: &str| {
+            Icons::from_file(&format!(
+                "{}/files/icons/{}",
+                env!("CARGO_MANIFEST_DIR"),
+                name
+            ))
+            .unwrap()
+        };
+        for name in ["awesome", "material"] {
+            assert!(!set(name).contains("extend"));
+        }
+
+        // awesome5 falls back to material-nf, and both to the text of the "none" set
+        let (awesome5, material_nf) = (set("awesome5"), set("material-nf"));
+        assert!(material_nf.0.keys().all(|icon| awesome5.contains(icon)));
+        assert_ne!(awesome5.get("cpu", None), material_nf.get("cpu", None));
+        assert_eq!(awesome5.get("bat", None), "BAT");
+    }
+}
@@ -0,0 +1,215 @@
+//! A control interface for the running bar.
+//!
+//! The bar listens on a Unix socket for requests. A request is a single line of whitespace
+//! separated words (e.g. `update weather`), the answer is a status line (`ok` or `error`)
+//! followed by the actual output. `i3status-rs ctl` is a small client for it.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+
+use crate::errors::*;
+use crate::protocol::i3bar_event::MouseButton;
+
+/// What a client asked the bar to do
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// List the ids and names of all blocks
+    List,
+    /// Print the rendered widgets of all blocks as JSON
+    Dump,
+    /// Update the blocks with this id or name
+    Update(String),
+    /// Send a click event to the blocks with this id or name
+    Click {
+        block: String,
+        button: MouseButton,
+        instance: Option<usize>,
+    },
+    /// Send a realtime signal (`SIGRTMIN+signal`) to the blocks with this id or name only
+    Signal { block: String, signal: i32 },
+    /// Reload the config file
+    Reload,
+}
+
+impl Command {
+    fn parse(line: &str) -> StdResult<Self, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["list"] => Ok(Self::List),
+            ["dump"] => Ok(Self::Dump),
+            ["reload"] => Ok(Self::Reload),
+            ["update", block] => Ok(Self::Update(block.to_string())),
+            ["signal", block, signal] => Ok(Self::Signal {
+                block: block.to_string(),
+                signal: signal
+                    .parse()
+                    .map_err(|_| format!("invalid signal '{}'", signal))?,
+            }),
+            ["click", block, rest @ ..] if rest.len() <= 2 => Ok(Self::Click {
+                block: block.to_string(),
+                button: match rest.first() {
+                    Some(button) => button.parse()?,
+                    None => MouseButton::Left,
+                },
+                instance: match rest.get(1) {
+                    Some(instance) => Some(
+                        instance
+                            .parse()
+                            .map_err(|_| format!("invalid instance '{}'", instance))?,
+                    ),
+                    None => None,
+                },
+            }),
+            _ => Err(format!("invalid request '{}'", line.trim())),
+        }
+    }
+}
+
+/// A request from a client, waiting to be answered by the main loop
+pub struct Request {
+    pub command: Command,
+    reply: Sender<StdResult<String, String>>,
+}
+
+impl Request {
+    pub fn respond(&self, result: StdResult<String, String>) {
+        let _ = self.reply.send(result);
+    }
+}
+
+/// The socket to use if none has been given on the command line
+pub fn default_socket_path() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("i3status-rs.sock"),
+        None => PathBuf::from(format!("/tmp/i3status-rs-{}.sock", nix::unistd::getuid())),
+    }
+}
+
+/// Starts a thread that listens on the socket and sends the requests of clients on the
+/// provided channel
+pub fn listen(path: &Path, sender: Sender<Request>) -> Result<()> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(InternalError(
+                "ipc".to_string(),
+                format!(
+                    "{} is used by another instance, use --socket to pick a different one",
+                    path.display()
+                ),
+                None,
+            ));
+        }
+        // Left over by an instance that is gone
+        std::fs::remove_file(path).internal_error("ipc", "failed to remove stale socket")?;
+    }
+    let listener = UnixListener::bind(path).internal_error("ipc", "failed to bind socket")?;
+
+    thread::Builder::new()
+        .name("ipc".into())
+        .spawn(move || {
+            for stream in listener.incoming().flatten() {
+                // A misbehaving client must not take the listener down
+                let _ = handle_client(stream, &sender);
+            }
+        })
+        .internal_error("ipc", "failed to spawn thread")?;
+
+    Ok(())
+}
+
+fn handle_client(stream: UnixStream, sender: &Sender<Request>) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+
+    let result = match Command::parse(&line) {
+        Ok(command) => {
+            let (reply, rx_reply) = crossbeam_channel::bounded(1);
+            if sender.send(Request { command, reply }).is_err() {
+                return Ok(());
+            }
+            rx_reply
+                .recv()
+                .unwrap_or_else(|_| Err("the bar did not answer".to_string()))
+        }
+        Err(error) => Err(error),
+    };
+
+    let mut stream = stream;
+    match result {
+        Ok(output) => write!(stream, "ok\n{}", output),
+        Err(error) => write!(stream, "error\n{}", error),
+    }
+}
+
+/// Send a request to a running bar, print its answer and return whether it succeeded
+pub fn send_request(path: &Path, request: &str) -> Result<bool> {
+    let mut stream = UnixStream::connect(path).internal_error(
+        "ipc",
+        &format!(
+            "failed to connect to {}, is i3status-rs running?",
+            path.display()
+        ),
+    )?;
+    writeln!(stream, "{}", request).internal_error("ipc", "failed to send request")?;
+
+    let mut answer = String::new();
+    stream
+        .read_to_string(&mut answer)
+        .internal_error("ipc", "failed to read answer")?;
+    let (status, output) = answer.split_once('\n').unwrap_or((&answer, ""));
+    if status == "ok" {
+        if !output.is_empty() {
+            println!("{}", output.trim_end());
+        }
+        Ok(true)
+    } else {
+        eprintln!("{}", output.trim_end());
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(Command::parse("list\n"), Ok(Command::List));
+        assert_eq!(
+            Command::parse("update weather"),
+            Ok(Command::Update("weather".to_string()))
+        );
+        assert_eq!(
+            Command::parse("click 3"),
+            Ok(Command::Click {
+                block: "3".to_string(),
+                button: MouseButton::Left,
+                instance: None,
+            })
+        );
+        assert_eq!(
+            Command::parse("click sound wheel_up 1"),
+            Ok(Command::Click {
+                block: "sound".to_string(),
+                button: MouseButton::WheelUp,
+                instance: Some(1),
+            })
+        );
+        assert_eq!(
+            Command::parse("signal vol 4"),
+            Ok(Command::Signal {
+                block: "vol".to_string(),
+                signal: 4,
+            })
+        );
+        assert!(Command::parse("click sound sideways").is_err());
+        assert!(Command::parse("update").is_err());
+        assert!(Command::parse("").is_err());
+    }
+}
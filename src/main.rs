@@ -5,16 +5,23 @@ mod util;
 #[macro_use]
 mod formatting;
 pub mod blocks;
+mod check;
 mod config;
 mod errors;
 mod http;
+mod hwmon;
 mod icons;
+mod ipc;
+mod netlink;
+mod pause;
 mod protocol;
+mod reload;
 mod scheduler;
 mod signals;
 mod subprocess;
 mod themes;
 mod widgets;
+mod worker;
 
 #[cfg(feature = "profiling")]
 use cpuprofiler::PROFILER;
@@ -24,22 +31,29 @@ use std::ops::DerefMut;
 #[cfg(feature = "pulseaudio")]
 use libpulse_binding as pulse;
 
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-use clap::{crate_authors, crate_description, App, Arg, ArgMatches};
+use clap::{crate_authors, crate_description, App, AppSettings, Arg, ArgMatches, SubCommand};
 use crossbeam_channel::{select, Receiver, Sender};
 
+#[cfg(feature = "profiling")]
 use crate::blocks::create_block;
+#[cfg(feature = "profiling")]
 use crate::blocks::Block;
 use crate::config::Config;
 use crate::config::SharedConfig;
 use crate::errors::*;
+use crate::protocol::i3bar_block::I3BarBlock;
 use crate::protocol::i3bar_event::{process_events, I3BarEvent};
+use crate::protocol::output::{Output, Printer};
+use crate::reload::{apply_config, watch_config};
 use crate::scheduler::{Task, UpdateScheduler};
-use crate::signals::process_signals;
-use crate::util::deserialize_file;
+use crate::signals::{convert_to_valid_signal, process_signals};
+use crate::themes::Theme;
 use crate::widgets::text::TextWidget;
 use crate::widgets::{I3BarWidget, State};
+use crate::worker::{BlockWorker, Command, Response};
 
 fn main() {
     let ver = if env!("GIT_COMMIT_HASH").is_empty() || env!("GIT_COMMIT_DATE").is_empty() {
@@ -77,6 +91,21 @@ fn main() {
                 .long("never-pause")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("output")
+                .help("The bar to print for, overrides `output` of the config file [default: i3bar]")
+                .long("output")
+                .value_name("OUTPUT")
+                .possible_values(&["i3bar", "swaybar", "plain", "tmux", "lemonbar", "waybar"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("list-placeholders")
+                .help("Print the placeholders the formats of a block can use, with their kind and unit, and exit")
+                .long("list-placeholders")
+                .value_name("BLOCK")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("one-shot")
                 .help("Print blocks once and exit")
@@ -90,6 +119,50 @@ fn main() {
                 .long("no-init")
                 .takes_value(false)
                 .hidden(true),
+        )
+        .arg(
+            Arg::with_name("socket")
+                .help("Listen for control requests on this socket [default: $XDG_RUNTIME_DIR/i3status-rs.sock]")
+                .long("socket")
+                .value_name("PATH")
+                .takes_value(true),
+        )
+        .subcommand(
+            SubCommand::with_name("ctl")
+                .about("Control a running i3status-rs")
+                .setting(AppSettings::ArgRequiredElseHelp)
+                .arg(
+                    Arg::with_name("socket")
+                        .help("The socket of the running instance [default: $XDG_RUNTIME_DIR/i3status-rs.sock]")
+                        .long("socket")
+                        .value_name("PATH")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("request")
+                        .value_name("REQUEST")
+                        .help("One of:\n\
+                               list                                 list the ids and names of all blocks\n\
+                               dump                                 print the widgets of all blocks as JSON\n\
+                               update <block>                       update a block\n\
+                               click <block> [button] [instance]    send a click to a block\n\
+                               signal <block> <signal>              send SIGRTMIN+<signal> to a block\n\
+                               reload                               reload the config file\n\
+                               <block> is the position, the configured id or the name of a block.")
+                        .required(true)
+                        .multiple(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("check")
+                .about("Report all problems of a config file without running the bar")
+                .arg(
+                    Arg::with_name("config")
+                        .value_name("CONFIG_FILE")
+                        .help("The config file to check")
+                        .required(false)
+                        .index(1),
+                ),
         );
 
     #[cfg(feature = "profiling")]
@@ -113,8 +186,60 @@ fn main() {
     let matches = builder.get_matches();
     let exit_on_error = matches.is_present("exit-on-error");
 
+    // Act as a client of a running instance
+    if let Some(matches) = matches.subcommand_matches("ctl") {
+        let socket_path = socket_path(matches);
+        let request: Vec<&str> = matches.values_of("request").unwrap().collect();
+        match ipc::send_request(&socket_path, &request.join(" ")) {
+            Ok(true) => return,
+            Ok(false) => ::std::process::exit(1),
+            Err(error) => {
+                eprintln!("{}", error);
+                ::std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(name) = matches.value_of("list-placeholders") {
+        match blocks::block_placeholders(name) {
+            Ok([]) => println!("{} has no placeholders", name),
+            Ok(placeholders) => {
+                for field in placeholders {
+                    let line = format!("{:<28}{:<9}{}", field.name, field.kind, field.unit);
+                    println!("{}", line.trim_end());
+                }
+            }
+            Err(error) => {
+                eprintln!("{}", error);
+                ::std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // Check a config file instead of running the bar
+    if let Some(matches) = matches.subcommand_matches("check") {
+        let config_path = config_path(matches);
+        let problems = check::check_config(&config_path);
+        for problem in &problems {
+            match problem.line {
+                Some(line) => eprintln!("{}:{}: {}", problem.file.display(), line, problem.message),
+                None => eprintln!("{}: {}", problem.file.display(), problem.message),
+            }
+        }
+        if !problems.is_empty() {
+            eprintln!("{} problem(s) found", problems.len());
+            ::std::process::exit(1);
+        }
+        println!("{}: ok", config_path.display());
+        return;
+    }
+
+    let config_path = config_path(&matches);
+
     // Run and match for potential error
-    if let Err(error) = run(&matches) {
+    let mut printer = None;
+    if let Err(error) = run(&matches, &config_path, &mut printer) {
         if exit_on_error {
             eprintln!("{:?}", error);
             ::std::process::exit(1);
@@ -125,32 +250,75 @@ fn main() {
             .with_state(State::Critical)
             .with_text(&format!("{:?}", error));
 
-        // Print errors
-        println!("[{}],", error_widget.get_data().render());
+        // Print errors. If the config could not be read, the bar has not been set up yet.
+        let printer = printer.unwrap_or_else(|| {
+            let printer = Printer::new(
+                cli_output(&matches).unwrap_or_default(),
+                &socket_path(&matches),
+            );
+            if !matches.is_present("no-init") {
+                printer.init(matches.is_present("never-pause"));
+            }
+            printer
+        });
+        printer.print(&[error_widget.get_data()]);
         eprintln!("\n\n{:?}", error);
 
-        // Wait for USR2 signal to restart
-        signal_hook::iterator::Signals::new(&[signal_hook::consts::SIGUSR2])
-            .unwrap()
-            .forever()
-            .next()
-            .unwrap();
+        // Wait for USR2 signal or a change of the config file to restart
+        let (tx_reload, mut rx_reload) = crossbeam_channel::unbounded();
+        let _ = watch_config(&config_path, tx_reload);
+        let (tx_signals, rx_signals) = crossbeam_channel::unbounded();
+        process_signals(tx_signals, false);
+        loop {
+            select! {
+                recv(rx_reload) -> res => match res {
+                    Ok(Ok(())) => break,
+                    // The file can't be watched any longer, only USR2 is left
+                    _ => rx_reload = crossbeam_channel::never(),
+                },
+                recv(rx_signals) -> sig => if sig == Ok(signal_hook::consts::SIGUSR2) {
+                    break;
+                },
+            }
+        }
         restart();
     }
 }
 
-fn run(matches: &ArgMatches) -> Result<()> {
-    if !matches.is_present("no-init") {
-        // Now we can start to run the i3bar protocol
-        protocol::init(matches.is_present("never-pause"));
+/// The config file given on the command line, or the default one
+fn config_path(matches: &ArgMatches) -> PathBuf {
+    match matches.value_of("config") {
+        Some(config_path) => PathBuf::from(config_path),
+        None => util::xdg_config_home().join("i3status-rust/config.toml"),
     }
+}
 
-    // Read & parse the config file
-    let config_path = match matches.value_of("config") {
-        Some(config_path) => std::path::PathBuf::from(config_path),
-        None => util::xdg_config_home().join("i3status-rust/config.toml"),
-    };
-    let config: Config = deserialize_file(&config_path)?;
+/// The output given on the command line
+fn cli_output(matches: &ArgMatches) -> Option<Output> {
+    matches
+        .value_of("output")
+        .and_then(|output| output.parse().ok())
+}
+
+fn socket_path(matches: &ArgMatches) -> PathBuf {
+    matches
+        .value_of("socket")
+        .map_or_else(ipc::default_socket_path, PathBuf::from)
+}
+
+/// Run the bar. `printer` is set as soon as the header of the bar has been printed.
+fn run(matches: &ArgMatches, config_path: &Path, printer: &mut Option<Printer>) -> Result<()> {
+    let mut config = Config::load(config_path)?;
+
+    let socket_path = socket_path(matches);
+    let printer: &Printer = printer.insert(Printer::new(
+        cli_output(matches).unwrap_or(config.output),
+        &socket_path,
+    ));
+    if !matches.is_present("no-init") {
+        // Now we can start to run the protocol
+        printer.init(matches.is_present("never-pause"));
+    }
 
     // Update request channel
     let (tx_update_requests, rx_update_requests): (Sender<Task>, Receiver<Task>) =
@@ -169,62 +337,250 @@ fn run(matches: &ArgMatches) -> Result<()> {
         }
     }
 
-    let shared_config = SharedConfig::new(&config);
+    let mut shared_config = SharedConfig::new(&config);
+
+    // Blocks answer commands sent to their threads through this channel
+    let (tx_responses, rx_responses): (Sender<Response>, Receiver<Response>) =
+        crossbeam_channel::unbounded();
+
+    // Every block runs in a thread of its own
+    let mut spawn_block = |id, block_name: &str, block_config, shared_config| {
+        BlockWorker::spawn(
+            id,
+            block_name,
+            block_config,
+            shared_config,
+            tx_update_requests.clone(),
+            tx_responses.clone(),
+        )
+    };
 
     // Initialize the blocks
-    let mut blocks: Vec<Box<dyn Block>> = Vec::new();
+    let mut blocks: Vec<BlockWorker> = Vec::new();
     for &(ref block_name, ref block_config) in &config.blocks {
-        blocks.push(create_block(
+        blocks.push(spawn_block(
             blocks.len(),
             block_name,
             block_config.clone(),
             shared_config.clone(),
-            tx_update_requests.clone(),
         )?);
     }
 
     let mut scheduler = UpdateScheduler::new(&blocks);
+    // Blocks created by a config reload get ids that were never used before
+    let mut next_id = blocks.len();
 
-    // We wait for click events in a separate thread, to avoid blocking to wait for stdin
-    let (tx_clicks, rx_clicks): (Sender<I3BarEvent>, Receiver<I3BarEvent>) =
+    // We wait for click events in a separate thread, to avoid blocking to wait for stdin.
+    // Only i3bar sends them, the clicks of other bars arrive through the control socket.
+    let (tx_clicks, mut rx_clicks): (Sender<I3BarEvent>, Receiver<I3BarEvent>) =
         crossbeam_channel::unbounded();
-    process_events(tx_clicks);
+    if printer.output() == Output::I3bar {
+        process_events(tx_clicks);
+    } else {
+        rx_clicks = crossbeam_channel::never();
+    }
 
     // We wait for signals in a separate thread
     let (tx_signals, rx_signals): (Sender<i32>, Receiver<i32>) = crossbeam_channel::unbounded();
-    process_signals(tx_signals);
+    // i3bar asks us to pause unless `--never-pause` is given
+    let pausable = printer.output() == Output::I3bar && !matches.is_present("never-pause");
+    process_signals(tx_signals, pausable);
+
+    // We watch the config file for changes in a separate thread
+    let (tx_reload, mut rx_reload): (Sender<Result<()>>, Receiver<Result<()>>) =
+        crossbeam_channel::unbounded();
+    watch_config(config_path, tx_reload)?;
+
+    // We listen for control requests in a separate thread. The bar is perfectly usable without
+    // them, so failing to set up the socket is not fatal.
+    let (tx_ipc, rx_ipc): (Sender<ipc::Request>, Receiver<ipc::Request>) =
+        crossbeam_channel::unbounded();
+    if let Err(error) = ipc::listen(&socket_path, tx_ipc) {
+        eprintln!("{}", error);
+    }
 
     // Time to next update channel.
     // Fires immediately for first updates
     let mut ttnu = crossbeam_channel::after(Duration::from_millis(0));
 
     let one_shot = matches.is_present("one-shot");
+    let exit_on_error = matches.is_present("exit-on-error");
+    let mut first_updates_sent = false;
+    // Whether the blocks changed since they were printed last
+    let mut redraw = false;
+    // While i3bar hides the bar, nothing is updated or printed. Update requests are collected
+    // and sent once the bar is visible again, see `request_update`.
+    let mut deferred_updates: Vec<usize> = Vec::new();
+
+    // A config that fails to reload leaves the running blocks alone, the error is shown in front
+    // of them until a reload succeeds
+    let mut reload_error: Option<I3BarBlock> = None;
+    macro_rules! reload {
+        () => {{
+            let result = Config::load(config_path).and_then(|new_config| {
+                apply_config(
+                    new_config,
+                    &mut config,
+                    &mut shared_config,
+                    &mut blocks,
+                    &mut scheduler,
+                    &mut next_id,
+                    &mut spawn_block,
+                )
+            });
+            reload_error = result
+                .as_ref()
+                .err()
+                .map(|error| error_widget("config reload failed", error, &shared_config));
+            redraw = true;
+            result
+        }};
+    }
     loop {
         // We use the message passing concept of channel selection
         // to avoid busy wait
         select! {
             // Receive click events
-            recv(rx_clicks) -> res => if let Ok(event) = res {
-                if let Some(id) = event.id {
-                        blocks.get_mut(id)
-                    .internal_error("click handler", "could not get required block")?
-                            .click(&event)?;
-                    protocol::print_blocks(&blocks, &shared_config)?;
+            recv(rx_clicks) -> res => match res {
+                Ok(mut event) => {
+                    if let (None, Some(name)) = (event.id, &event.name) {
+                        event.id = named_block(&blocks, name);
+                    }
+                    // Events for names we do not know are ignored
+                    if let Some(id) = event.id {
+                        if let Some(block) = blocks.iter_mut().find(|block| block.id() == id) {
+                            block.click(event);
+                            redraw = true;
+                        }
+                    }
                 }
+                // stdin was closed, there will be no more click events
+                Err(_) => rx_clicks = crossbeam_channel::never(),
             },
             // Receive async update requests
             recv(rx_update_requests) -> request => if let Ok(req) = request {
-                // Process immediately and forget
-                blocks.get_mut(req.id)
-                    .internal_error("scheduler", "could not get required block")?
-                    .update()?;
-                protocol::print_blocks(&blocks, &shared_config)?;
+                // Process immediately and forget. Requests from blocks that were removed by a
+                // config reload are ignored.
+                if let Some(block) = blocks.iter_mut().find(|block| block.id() == req.id) {
+                    request_update(block, &mut deferred_updates);
+                }
+            },
+            // Receive the results of commands processed by the blocks
+            recv(rx_responses) -> res => if let Ok(response) = res {
+                if exit_on_error && response.result.is_err() {
+                    return response.result.map(|_| ());
+                }
+                if let Some(block) = blocks.iter_mut().find(|block| block.id() == response.id) {
+                    if block.accept(response, &mut scheduler) {
+                        // state changed
+                        redraw = true;
+                    }
+                }
+            },
+            // Receive config file changes
+            recv(rx_reload) -> res => match res {
+                Ok(Ok(())) => {
+                    let _ = reload!();
+                }
+                // The watcher has stopped, reloads are only possible through USR2 and `ctl`
+                Ok(Err(error)) => {
+                    reload_error = Some(error_widget("config watcher", &error, &shared_config));
+                    redraw = true;
+                    rx_reload = crossbeam_channel::never();
+                }
+                Err(_) => rx_reload = crossbeam_channel::never(),
+            },
+            // Receive control requests
+            recv(rx_ipc) -> res => if let Ok(request) = res {
+                match request.command {
+                    ipc::Command::List => {
+                        let list: Vec<String> = blocks
+                            .iter()
+                            .map(|block| match block.named_id() {
+                                Some(named_id) => {
+                                    format!("{}\t{}\t{}", block.id(), block.name(), named_id)
+                                }
+                                None => format!("{}\t{}", block.id(), block.name()),
+                            })
+                            .collect();
+                        request.respond(Ok(list.join("\n")));
+                    }
+                    ipc::Command::Dump => {
+                        let dump: Vec<String> = blocks
+                            .iter()
+                            .map(|block| {
+                                let widgets: Vec<String> =
+                                    block.widgets().iter().map(I3BarBlock::render).collect();
+                                format!(
+                                    "{{\"id\":{},\"name\":{},\"widgets\":[{}]}}",
+                                    block.id(),
+                                    serde_json::to_string(block.name()).unwrap(),
+                                    widgets.join(",")
+                                )
+                            })
+                            .collect();
+                        request.respond(Ok(format!("[{}]", dump.join(","))));
+                    }
+                    ipc::Command::Update(ref target) => {
+                        let mut found = false;
+                        for block in blocks.iter_mut().filter(|block| is_target(block, target)) {
+                            request_update(block, &mut deferred_updates);
+                            found = true;
+                        }
+                        request.respond(found_or_error(found, target));
+                    }
+                    ipc::Command::Click { block: ref target, button, instance } => {
+                        let mut found = false;
+                        for block in blocks.iter_mut().filter(|block| is_target(block, target)) {
+                            block.click(I3BarEvent {
+                                id: Some(block.id()),
+                                instance,
+                                button,
+                                ..I3BarEvent::default()
+                            });
+                            found = true;
+                        }
+                        if found {
+                            redraw = true;
+                        }
+                        request.respond(found_or_error(found, target));
+                    }
+                    ipc::Command::Signal { block: ref target, signal } => {
+                        match convert_to_valid_signal(signal) {
+                            Ok(signal) => {
+                                let mut found = false;
+                                let targets = blocks
+                                    .iter_mut()
+                                    .filter(|block| is_target(block, target));
+                                for block in targets {
+                                    block.send(Command::Signal(signal));
+                                    found = true;
+                                }
+                                request.respond(found_or_error(found, target));
+                            }
+                            Err(error) => request.respond(Err(error.to_string())),
+                        }
+                    }
+                    ipc::Command::Reload => {
+                        let result = reload!();
+                        request.respond(result.map(|_| String::new()).map_err(|e| e.to_string()));
+                    }
+                }
             },
             // Receive update timer events
             recv(ttnu) -> _ => {
-                scheduler.do_scheduled_updates(&mut blocks)?;
-                // redraw the blocks, state changed
-                protocol::print_blocks(&blocks, &shared_config)?;
+                // Restart the blocks that got stuck
+                let now = Instant::now();
+                for block in blocks.iter_mut() {
+                    if matches!(block.deadline(), Some(deadline) if deadline <= now) {
+                        block.restart();
+                        scheduler.schedule(block.id());
+                    }
+                }
+                if !pause::is_paused() {
+                    scheduler.do_scheduled_updates(&mut blocks)?;
+                    first_updates_sent = true;
+                }
             },
             // Receive signal events
             recv(rx_signals) -> res => if let Ok(sig) = res {
@@ -232,35 +588,135 @@ fn run(matches: &ArgMatches) -> Result<()> {
                     signal_hook::consts::SIGUSR1 => {
                         //USR1 signal that updates every block in the bar
                         for block in blocks.iter_mut() {
-                            block.update()?;
+                            request_update(block, &mut deferred_updates);
                         }
                     },
                     signal_hook::consts::SIGUSR2 => {
                         //USR2 signal that should reload the config
-                        restart();
+                        let _ = reload!();
+                    },
+                    signal_hook::consts::SIGTSTP => {
+                        //TSTP signal from i3bar, the bar is hidden
+                        pause::pause();
+                    },
+                    signal_hook::consts::SIGCONT => {
+                        //CONT signal from i3bar, the bar is visible again. Blocks that
+                        //asked to be updated in the meantime are updated now, and the
+                        //timer below fires right away for the scheduled updates we missed.
+                        if pause::is_paused() {
+                            pause::resume();
+                            for id in deferred_updates.drain(..) {
+                                if let Some(block) = blocks.iter_mut().find(|block| block.id() == id) {
+                                    block.send(Command::Update { scheduled: false });
+                                }
+                            }
+                            ttnu = crossbeam_channel::after(Duration::from_millis(0));
+                        }
                     },
                     _ => {
                         //Real time signal that updates only the blocks listening
                         //for that signal
                         for block in blocks.iter_mut() {
-                            block.signal(sig)?;
+                            block.send(Command::Signal(sig));
                         }
                     },
                 };
-                protocol::print_blocks(&blocks, &shared_config)?;
             }
         }
 
-        // Set the time-to-next-update timer
-        if let Some(time) = scheduler.time_to_next_update() {
+        if redraw && !pause::is_paused() {
+            print_blocks(reload_error.as_ref(), &shared_config, &blocks, printer)?;
+            redraw = false;
+        }
+
+        // Set the time-to-next-update timer. It also has to fire when the command a block is
+        // busy with times out. While paused, only the latter is of interest.
+        let time_to_timeout = blocks
+            .iter()
+            .filter_map(BlockWorker::deadline)
+            .min()
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        let time_to_next_update = if pause::is_paused() {
+            None
+        } else {
+            scheduler.time_to_next_update()
+        };
+        let time = match (time_to_next_update, time_to_timeout) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        if let Some(time) = time {
             ttnu = crossbeam_channel::after(time)
+        } else if pause::is_paused() {
+            ttnu = crossbeam_channel::never();
         }
-        if one_shot {
+        if one_shot && first_updates_sent && blocks.iter().all(BlockWorker::is_idle) {
             break Ok(());
         }
     }
 }
 
+/// Update `block` right away or, while the bar is paused, once it is visible again
+fn request_update(block: &mut BlockWorker, deferred_updates: &mut Vec<usize>) {
+    if !pause::is_paused() {
+        block.send(Command::Update { scheduled: false });
+    } else if !deferred_updates.contains(&block.id()) {
+        deferred_updates.push(block.id());
+    }
+}
+
+/// The id of the block whose widgets are named `name`, that is, whose `id` in the config is
+/// `name`
+fn named_block(blocks: &[BlockWorker], name: &str) -> Option<usize> {
+    blocks
+        .iter()
+        .find(|block| block.named_id() == Some(name))
+        .map(BlockWorker::id)
+}
+
+/// Whether a control request for `target` is meant for `block`. The target is either a numeric
+/// id, the `id` of a block from the config or a block name like "cpu", which addresses all blocks
+/// of that kind.
+fn is_target(block: &BlockWorker, target: &str) -> bool {
+    match target.parse::<usize>() {
+        Ok(id) => block.id() == id,
+        Err(_) => block.named_id() == Some(target) || block.name() == target,
+    }
+}
+
+fn found_or_error(found: bool, target: &str) -> StdResult<String, String> {
+    if found {
+        Ok(String::new())
+    } else {
+        Err(format!("no block matches '{}'", target))
+    }
+}
+
+/// A widget in the critical state that shows `error`
+fn error_widget(context: &str, error: &Error, shared_config: &SharedConfig) -> I3BarBlock {
+    let mut widget =
+        TextWidget::new(usize::MAX, 0, shared_config.clone()).with_state(State::Critical);
+    widget.set_texts((
+        util::escape_pango_text(format!("{}: {}", context, error)),
+        Some(format!("{}: error", context)),
+    ));
+    widget.get_data()
+}
+
+fn print_blocks(
+    error: Option<&I3BarBlock>,
+    shared_config: &SharedConfig,
+    blocks: &[BlockWorker],
+    printer: &Printer,
+) -> Result<()> {
+    let widgets: Vec<(&[I3BarBlock], &Theme)> = error
+        .map(|error| (std::slice::from_ref(error), &*shared_config.theme))
+        .into_iter()
+        .chain(blocks.iter().map(|block| (block.widgets(), block.theme())))
+        .collect();
+    protocol::print_blocks(&widgets, printer)
+}
+
 /// Restart `i3status-rs` in-place
 fn restart() -> ! {
     use std::env;
@@ -327,3 +783,66 @@ fn profile_config(name: &str, runs: &str, config: &Config, update: Sender<Task>)
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(id: usize, name: &str, config: &str, tx_response: &Sender<Response>) -> BlockWorker {
+        let (tx, _rx) = crossbeam_channel::unbounded();
+        BlockWorker::spawn(
+            id,
+            name,
+            toml::from_str(config).unwrap(),
+            SharedConfig::new(&Config::default()),
+            tx,
+            tx_response.clone(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn route_by_name() {
+        let (tx_response, rx_response) = crossbeam_channel::unbounded();
+        let mut blocks = vec![
+            block(
+                0,
+                "custom",
+                "id = \"vol\"\ncommand = \"echo hi\"",
+                &tx_response,
+            ),
+            block(1, "custom", "command = \"echo ho\"", &tx_response),
+            block(2, "cpu", "", &tx_response),
+        ];
+
+        // Clicks on widgets named after a block
+        assert_eq!(named_block(&blocks, "vol"), Some(0));
+        assert_eq!(named_block(&blocks, "custom"), None);
+        assert_eq!(named_block(&blocks, "nope"), None);
+
+        // Control requests by id, by `id` from the config and by kind of block
+        let targets = |target: &str| -> Vec<usize> {
+            blocks
+                .iter()
+                .filter(|block| is_target(block, target))
+                .map(BlockWorker::id)
+                .collect()
+        };
+        assert_eq!(targets("vol"), [0]);
+        assert_eq!(targets("custom"), [0, 1]);
+        assert_eq!(targets("2"), [2]);
+        assert_eq!(targets("cpu"), [2]);
+        assert!(targets("3").is_empty());
+        assert_eq!(
+            found_or_error(false, "3").unwrap_err(),
+            "no block matches '3'"
+        );
+
+        // The widgets of a named block carry its name instead of its numeric id
+        let mut scheduler = UpdateScheduler::new(&[]);
+        blocks[0].send(Command::Update { scheduled: false });
+        let response = rx_response.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(blocks[0].accept(response, &mut scheduler));
+        assert_eq!(blocks[0].widgets()[0].name.as_deref(), Some("vol"));
+    }
+}
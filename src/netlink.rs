@@ -0,0 +1,536 @@
+//! A small client for the netlink protocols the net block needs.
+//!
+//! rtnetlink describes the links, addresses and routes of the machine and nl80211, a generic
+//! netlink family, the network a wireless interface is connected to. rtnetlink replies are
+//! parsed from plain byte buffers, so the parsers can be tested with captured messages. nl80211
+//! is spoken through the `nl80211` crate.
+
+use std::convert::TryInto;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::os::unix::io::RawFd;
+
+use nix::errno::Errno;
+use nix::libc;
+use nix::sys::socket::{
+    bind, recv, send, socket, AddressFamily, MsgFlags, NetlinkAddr, SockAddr, SockFlag,
+    SockProtocol, SockType,
+};
+
+use crate::errors::*;
+
+// netlink
+const NLMSG_HDRLEN: usize = 16;
+const NLMSG_ERROR: u16 = 2;
+const NLMSG_DONE: u16 = 3;
+const NLM_F_REQUEST: u16 = 0x1;
+const NLM_F_ACK: u16 = 0x4;
+const NLM_F_DUMP: u16 = 0x300;
+const NLA_HDRLEN: usize = 4;
+const NLA_TYPE_MASK: u16 = 0x3fff;
+
+// rtnetlink
+const RTM_NEWLINK: u16 = 16;
+const RTM_GETLINK: u16 = 18;
+const RTM_NEWADDR: u16 = 20;
+const RTM_GETADDR: u16 = 22;
+const RTM_NEWROUTE: u16 = 24;
+const RTM_GETROUTE: u16 = 26;
+const IFINFOMSG_LEN: usize = 16;
+const IFADDRMSG_LEN: usize = 8;
+const RTMSG_LEN: usize = 12;
+const RTMGRP_LINK: u32 = 0x1;
+const RTMGRP_IPV4_IFADDR: u32 = 0x10;
+const RTMGRP_IPV4_ROUTE: u32 = 0x40;
+const RTMGRP_IPV6_IFADDR: u32 = 0x100;
+const RTMGRP_IPV6_ROUTE: u32 = 0x400;
+const IFLA_IFNAME: u16 = 3;
+const IFLA_MTU: u16 = 4;
+const IFLA_OPERSTATE: u16 = 16;
+const IFA_ADDRESS: u16 = 1;
+const IFA_LOCAL: u16 = 2;
+const RTA_DST: u16 = 1;
+const RTA_OIF: u16 = 4;
+const RTA_GATEWAY: u16 = 5;
+const RTA_PRIORITY: u16 = 6;
+const RTA_TABLE: u16 = 15;
+const RT_TABLE_MAIN: u32 = 254;
+const RTN_UNICAST: u8 = 1;
+const IF_OPER_UP: u8 = 6;
+
+fn netlink_error<T>(message: String) -> Result<T> {
+    Err(InternalError("netlink".to_string(), message, None))
+}
+
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+fn u16_at(buf: &[u8], at: usize) -> u16 {
+    u16::from_ne_bytes(buf[at..at + 2].try_into().unwrap())
+}
+
+fn u32_at(buf: &[u8], at: usize) -> u32 {
+    u32::from_ne_bytes(buf[at..at + 4].try_into().unwrap())
+}
+
+/// A netlink message without its header
+#[derive(Debug, Clone, PartialEq)]
+struct Message {
+    kind: u16,
+    seq: u32,
+    payload: Vec<u8>,
+}
+
+/// Split a buffer received from a netlink socket into its messages
+fn parse_messages(mut buf: &[u8]) -> Result<Vec<Message>> {
+    let mut messages = Vec::new();
+    while buf.len() >= NLMSG_HDRLEN {
+        let len = u32_at(buf, 0) as usize;
+        if len < NLMSG_HDRLEN || len > buf.len() {
+            return netlink_error(format!("invalid message length {}", len));
+        }
+        messages.push(Message {
+            kind: u16_at(buf, 4),
+            seq: u32_at(buf, 8),
+            payload: buf[NLMSG_HDRLEN..len].to_vec(),
+        });
+        buf = &buf[align(len).min(buf.len())..];
+    }
+    Ok(messages)
+}
+
+/// The attributes in `buf` as pairs of type and payload
+fn parse_attributes(mut buf: &[u8]) -> Vec<(u16, &[u8])> {
+    let mut attributes = Vec::new();
+    while buf.len() >= NLA_HDRLEN {
+        let len = u16_at(buf, 0) as usize;
+        if len < NLA_HDRLEN || len > buf.len() {
+            break;
+        }
+        attributes.push((u16_at(buf, 2) & NLA_TYPE_MASK, &buf[NLA_HDRLEN..len]));
+        buf = &buf[align(len).min(buf.len())..];
+    }
+    attributes
+}
+
+fn attribute(buf: &[u8], kind: u16) -> Option<&[u8]> {
+    parse_attributes(buf)
+        .into_iter()
+        .find(|(k, _)| *k == kind)
+        .map(|(_, payload)| payload)
+}
+
+fn attribute_u32(buf: &[u8], kind: u16) -> Option<u32> {
+    attribute(buf, kind)
+        .filter(|payload| payload.len() >= 4)
+        .map(|payload| u32_at(payload, 0))
+}
+
+fn attribute_string(buf: &[u8], kind: u16) -> Option<String> {
+    attribute(buf, kind).map(|payload| {
+        let payload = payload.split(|&c| c == 0).next().unwrap_or_default();
+        String::from_utf8_lossy(payload).into_owned()
+    })
+}
+
+fn ip_addr(payload: &[u8]) -> Option<IpAddr> {
+    match payload.len() {
+        4 => {
+            let octets: [u8; 4] = payload.try_into().unwrap();
+            Some(Ipv4Addr::from(octets).into())
+        }
+        16 => {
+            let octets: [u8; 16] = payload.try_into().unwrap();
+            Some(Ipv6Addr::from(octets).into())
+        }
+        _ => None,
+    }
+}
+
+/// A network interface
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    pub index: u32,
+    pub name: String,
+    pub mtu: Option<u32>,
+    /// The operational state is `up`
+    pub up: bool,
+}
+
+impl Link {
+    fn parse(payload: &[u8]) -> Option<Self> {
+        if payload.len() < IFINFOMSG_LEN {
+            return None;
+        }
+        let attributes = &payload[IFINFOMSG_LEN..];
+        Some(Self {
+            index: u32_at(payload, 4),
+            name: attribute_string(attributes, IFLA_IFNAME)?,
+            mtu: attribute_u32(attributes, IFLA_MTU),
+            up: attribute(attributes, IFLA_OPERSTATE) == Some(&[IF_OPER_UP]),
+        })
+    }
+}
+
+/// An address of a network interface
+#[derive(Debug, Clone, PartialEq)]
+pub struct Address {
+    pub index: u32,
+    pub address: IpAddr,
+    pub prefix: u8,
+}
+
+impl Address {
+    fn parse(payload: &[u8]) -> Option<Self> {
+        if payload.len() < IFADDRMSG_LEN {
+            return None;
+        }
+        let attributes = &payload[IFADDRMSG_LEN..];
+        // The local address is the address of the interface, `IFA_ADDRESS` is the address of the
+        // peer on point-to-point links. IPv6 addresses only have the latter.
+        let address =
+            attribute(attributes, IFA_LOCAL).or_else(|| attribute(attributes, IFA_ADDRESS));
+        Some(Self {
+            index: u32_at(payload, 4),
+            address: ip_addr(address?)?,
+            prefix: payload[1],
+        })
+    }
+}
+
+/// A route of the main routing table
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    pub ipv6: bool,
+    pub destination: Option<IpAddr>,
+    pub prefix: u8,
+    pub gateway: Option<IpAddr>,
+    /// The index of the interface the route goes through
+    pub interface: Option<u32>,
+    pub metric: u32,
+}
+
+impl Route {
+    fn parse(payload: &[u8]) -> Option<Self> {
+        if payload.len() < RTMSG_LEN {
+            return None;
+        }
+        let attributes = &payload[RTMSG_LEN..];
+        let table = attribute_u32(attributes, RTA_TABLE).unwrap_or(payload[4] as u32);
+        if table != RT_TABLE_MAIN || payload[7] != RTN_UNICAST {
+            return None;
+        }
+        Some(Self {
+            ipv6: payload[0] == libc::AF_INET6 as u8,
+            destination: attribute(attributes, RTA_DST).and_then(ip_addr),
+            prefix: payload[1],
+            gateway: attribute(attributes, RTA_GATEWAY).and_then(ip_addr),
+            interface: attribute_u32(attributes, RTA_OIF),
+            metric: attribute_u32(attributes, RTA_PRIORITY).unwrap_or(0),
+        })
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.prefix == 0
+    }
+}
+
+/// The network a wireless interface is connected to
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WirelessInfo {
+    pub ssid: Option<Vec<u8>>,
+    /// In MHz
+    pub frequency: Option<u32>,
+    /// In dBm
+    pub signal: Option<i8>,
+    /// In units of 100 kbit/s
+    pub bitrate: Option<u32>,
+}
+
+/// A netlink socket
+pub struct Socket {
+    fd: RawFd,
+    seq: u32,
+}
+
+impl Socket {
+    fn connect(groups: u32) -> Result<Self> {
+        let fd = socket(
+            AddressFamily::Netlink,
+            SockType::Raw,
+            SockFlag::SOCK_CLOEXEC,
+            SockProtocol::NetlinkRoute,
+        )
+        .internal_error("netlink", "failed to open a socket")?;
+        let socket = Self { fd, seq: 0 };
+        bind(fd, &SockAddr::Netlink(NetlinkAddr::new(0, groups)))
+            .internal_error("netlink", "failed to bind the socket")?;
+        Ok(socket)
+    }
+
+    /// A socket for rtnetlink
+    pub fn route() -> Result<Self> {
+        Self::connect(0)
+    }
+
+    /// A socket for rtnetlink that is told about changes of links, addresses and routes
+    pub fn route_events() -> Result<Self> {
+        Self::connect(
+            RTMGRP_LINK
+                | RTMGRP_IPV4_IFADDR
+                | RTMGRP_IPV4_ROUTE
+                | RTMGRP_IPV6_IFADDR
+                | RTMGRP_IPV6_ROUTE,
+        )
+    }
+
+    /// Wait for the next event and discard it, along with any that arrived with it
+    pub fn wait(&mut self) -> Result<()> {
+        let mut buf = vec![0; 32 * 1024];
+        match recv(self.fd, &mut buf, MsgFlags::empty()) {
+            // Events were dropped because too many arrived at once, which is a change as well
+            Ok(_) | Err(nix::Error::Sys(Errno::ENOBUFS)) => (),
+            Err(error) => {
+                return netlink_error(format!("failed to receive an event: {}", error));
+            }
+        }
+        while recv(self.fd, &mut buf, MsgFlags::MSG_DONTWAIT).is_ok() {}
+        Ok(())
+    }
+
+    /// Send a request and return the replies
+    fn request(&mut self, kind: u16, dump: bool, payload: &[u8]) -> Result<Vec<Message>> {
+        self.seq += 1;
+        let flags = NLM_F_REQUEST | if dump { NLM_F_DUMP } else { NLM_F_ACK };
+        let mut request = Vec::with_capacity(NLMSG_HDRLEN + payload.len());
+        request.extend_from_slice(&((NLMSG_HDRLEN + payload.len()) as u32).to_ne_bytes());
+        request.extend_from_slice(&kind.to_ne_bytes());
+        request.extend_from_slice(&flags.to_ne_bytes());
+        request.extend_from_slice(&self.seq.to_ne_bytes());
+        request.extend_from_slice(&0u32.to_ne_bytes());
+        request.extend_from_slice(payload);
+        send(self.fd, &request, MsgFlags::empty())
+            .internal_error("netlink", "failed to send a request")?;
+
+        let mut replies = Vec::new();
+        let mut buf = vec![0; 32 * 1024];
+        loop {
+            let len = recv(self.fd, &mut buf, MsgFlags::empty())
+                .internal_error("netlink", "failed to receive a reply")?;
+            for message in parse_messages(&buf[..len])? {
+                if message.seq != self.seq {
+                    continue;
+                }
+                match message.kind {
+                    NLMSG_DONE | NLMSG_ERROR => {
+                        let error = match message.payload.get(..4) {
+                            Some(error) => i32::from_ne_bytes(error.try_into().unwrap()),
+                            None => 0,
+                        };
+                        if error < 0 {
+                            return netlink_error(format!(
+                                "request failed: {}",
+                                std::io::Error::from_raw_os_error(-error)
+                            ));
+                        }
+                        return Ok(replies);
+                    }
+                    _ => replies.push(message),
+                }
+            }
+        }
+    }
+
+    /// All network interfaces
+    pub fn links(&mut self) -> Result<Vec<Link>> {
+        Ok(self
+            .request(RTM_GETLINK, true, &[0; IFINFOMSG_LEN])?
+            .iter()
+            .filter(|message| message.kind == RTM_NEWLINK)
+            .filter_map(|message| Link::parse(&message.payload))
+            .collect())
+    }
+
+    /// The addresses of all network interfaces
+    pub fn addresses(&mut self) -> Result<Vec<Address>> {
+        Ok(self
+            .request(RTM_GETADDR, true, &[0; IFADDRMSG_LEN])?
+            .iter()
+            .filter(|message| message.kind == RTM_NEWADDR)
+            .filter_map(|message| Address::parse(&message.payload))
+            .collect())
+    }
+
+    /// The routes of the main routing table
+    pub fn routes(&mut self) -> Result<Vec<Route>> {
+        Ok(self
+            .request(RTM_GETROUTE, true, &[0; RTMSG_LEN])?
+            .iter()
+            .filter(|message| message.kind == RTM_NEWROUTE)
+            .filter_map(|message| Route::parse(&message.payload))
+            .collect())
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        let _ = nix::unistd::close(self.fd);
+    }
+}
+
+/// The network the wireless interface with index `index` is connected to
+pub fn wireless_info(index: u32) -> Result<WirelessInfo> {
+    let interface = nl80211::Socket::connect()
+        .internal_error("nl80211", "failed to connect to the socket")?
+        .get_interfaces_info()
+        .internal_error("nl80211", "failed to get interfaces' information")?
+        .into_iter()
+        .find(|interface| interface.index.as_deref() == Some(&index.to_ne_bytes()[..]));
+    let interface = match interface {
+        Some(interface) => interface,
+        None => return Ok(WirelessInfo::default()),
+    };
+    let mut info = WirelessInfo {
+        frequency: interface.frequency.as_ref().map(nl80211::parse_u32),
+        ..WirelessInfo::default()
+    };
+    // SSID is `None` when not connected
+    if interface.ssid.is_some() {
+        let station = interface
+            .get_station_info()
+            .internal_error("nl80211", "failed to get station information")?;
+        info.signal = station.signal.as_ref().map(nl80211::parse_i8);
+        info.bitrate = station
+            .tx_bitrate
+            .as_ref()
+            .map(|bitrate| nl80211::parse_u16(bitrate) as u32);
+    }
+    info.ssid = interface.ssid;
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The reply to `RTM_GETLINK` for eth0, without most of its attributes
+    const LINK: &[u8] = &[
+        0x54, 0x00, 0x00, 0x00, 0x10, 0x00, 0x02, 0x00, 0x01, 0x00, 0x00, 0x00, 0x65, 0x44, 0x00,
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x04, 0x00, 0x00, 0x00, 0x43, 0x10, 0x01, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x09, 0x00, 0x03, 0x00, 0x65, 0x74, 0x68, 0x30, 0x00, 0x00, 0x00, 0x00, 0x08,
+        0x00, 0x0d, 0x00, 0xe8, 0x03, 0x00, 0x00, 0x05, 0x00, 0x10, 0x00, 0x06, 0x00, 0x00, 0x00,
+        0x05, 0x00, 0x11, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0x00, 0x43, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x08, 0x00, 0x04, 0x00, 0x78, 0x05, 0x00, 0x00,
+    ];
+
+    /// The reply to `RTM_GETADDR` for lo (127.0.0.1/8, ::1/128) and eth0 (192.0.2.2/24,
+    /// fd00::2/64, fe80::fc:ff:fe00:1/64)
+    const ADDRESSES: &[u8] = &[
+        0x4c, 0x00, 0x00, 0x00, 0x14, 0x00, 0x02, 0x00, 0x02, 0x00, 0x00, 0x00, 0x65, 0x44, 0x00,
+        0x00, 0x02, 0x08, 0x80, 0xfe, 0x01, 0x00, 0x00, 0x00, 0x08, 0x00, 0x01, 0x00, 0x7f, 0x00,
+        0x00, 0x01, 0x08, 0x00, 0x02, 0x00, 0x7f, 0x00, 0x00, 0x01, 0x07, 0x00, 0x03, 0x00, 0x6c,
+        0x6f, 0x00, 0x00, 0x08, 0x00, 0x08, 0x00, 0x80, 0x00, 0x00, 0x00, 0x14, 0x00, 0x06, 0x00,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x10, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00,
+        0x00, 0x58, 0x00, 0x00, 0x00, 0x14, 0x00, 0x02, 0x00, 0x02, 0x00, 0x00, 0x00, 0x65, 0x44,
+        0x00, 0x00, 0x02, 0x18, 0x80, 0x00, 0x04, 0x00, 0x00, 0x00, 0x08, 0x00, 0x01, 0x00, 0xc0,
+        0x00, 0x02, 0x02, 0x08, 0x00, 0x02, 0x00, 0xc0, 0x00, 0x02, 0x02, 0x08, 0x00, 0x04, 0x00,
+        0xc0, 0x00, 0x02, 0xff, 0x09, 0x00, 0x03, 0x00, 0x65, 0x74, 0x68, 0x30, 0x00, 0x00, 0x00,
+        0x00, 0x08, 0x00, 0x08, 0x00, 0x80, 0x00, 0x00, 0x00, 0x14, 0x00, 0x06, 0x00, 0xff, 0xff,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x10, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x50,
+        0x00, 0x00, 0x00, 0x14, 0x00, 0x02, 0x00, 0x02, 0x00, 0x00, 0x00, 0x65, 0x44, 0x00, 0x00,
+        0x0a, 0x80, 0x80, 0xfe, 0x01, 0x00, 0x00, 0x00, 0x14, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x14, 0x00,
+        0x06, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x10, 0x00, 0x00, 0x00, 0x10,
+        0x00, 0x00, 0x00, 0x08, 0x00, 0x08, 0x00, 0x80, 0x00, 0x00, 0x00, 0x05, 0x00, 0x0b, 0x00,
+        0x01, 0x00, 0x00, 0x00, 0x48, 0x00, 0x00, 0x00, 0x14, 0x00, 0x02, 0x00, 0x02, 0x00, 0x00,
+        0x00, 0x65, 0x44, 0x00, 0x00, 0x0a, 0x40, 0x82, 0x00, 0x04, 0x00, 0x00, 0x00, 0x14, 0x00,
+        0x01, 0x00, 0xfd, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x02, 0x14, 0x00, 0x06, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0x10, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x08, 0x00, 0x08, 0x00, 0x82, 0x00, 0x00,
+        0x00, 0x50, 0x00, 0x00, 0x00, 0x14, 0x00, 0x02, 0x00, 0x02, 0x00, 0x00, 0x00, 0x65, 0x44,
+        0x00, 0x00, 0x0a, 0x40, 0x80, 0xfd, 0x04, 0x00, 0x00, 0x00, 0x14, 0x00, 0x01, 0x00, 0xfe,
+        0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xfc, 0x00, 0xff, 0xfe, 0x00, 0x00, 0x01,
+        0x14, 0x00, 0x06, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x10, 0x00, 0x00,
+        0x00, 0x10, 0x00, 0x00, 0x00, 0x08, 0x00, 0x08, 0x00, 0x80, 0x00, 0x00, 0x00, 0x05, 0x00,
+        0x0b, 0x00, 0x03, 0x00, 0x00, 0x00,
+    ];
+
+    /// Some of the reply to `RTM_GETROUTE`: the IPv4 default route and subnet of eth0, a route of
+    /// the local table and the IPv6 default route
+    const ROUTES: &[u8] = &[
+        0x34, 0x00, 0x00, 0x00, 0x18, 0x00, 0x02, 0x00, 0x03, 0x00, 0x00, 0x00, 0x65, 0x44, 0x00,
+        0x00, 0x02, 0x00, 0x00, 0x00, 0xfe, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00,
+        0x0f, 0x00, 0xfe, 0x00, 0x00, 0x00, 0x08, 0x00, 0x05, 0x00, 0xc0, 0x00, 0x02, 0x01, 0x08,
+        0x00, 0x04, 0x00, 0x04, 0x00, 0x00, 0x00, 0x3c, 0x00, 0x00, 0x00, 0x18, 0x00, 0x02, 0x00,
+        0x03, 0x00, 0x00, 0x00, 0x65, 0x44, 0x00, 0x00, 0x02, 0x18, 0x00, 0x00, 0xfe, 0x02, 0xfd,
+        0x01, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x0f, 0x00, 0xfe, 0x00, 0x00, 0x00, 0x08, 0x00,
+        0x01, 0x00, 0xc0, 0x00, 0x02, 0x00, 0x08, 0x00, 0x07, 0x00, 0xc0, 0x00, 0x02, 0x02, 0x08,
+        0x00, 0x04, 0x00, 0x04, 0x00, 0x00, 0x00, 0x3c, 0x00, 0x00, 0x00, 0x18, 0x00, 0x02, 0x00,
+        0x03, 0x00, 0x00, 0x00, 0x65, 0x44, 0x00, 0x00, 0x02, 0x08, 0x00, 0x00, 0xff, 0x02, 0xfe,
+        0x02, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x0f, 0x00, 0xff, 0x00, 0x00, 0x00, 0x08, 0x00,
+        0x01, 0x00, 0x7f, 0x00, 0x00, 0x00, 0x08, 0x00, 0x07, 0x00, 0x7f, 0x00, 0x00, 0x01, 0x08,
+        0x00, 0x04, 0x00, 0x01, 0x00, 0x00, 0x00, 0x74, 0x00, 0x00, 0x00, 0x18, 0x00, 0x02, 0x00,
+        0x03, 0x00, 0x00, 0x00, 0x65, 0x44, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00, 0xfe, 0x03, 0x00,
+        0x01, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x0f, 0x00, 0xfe, 0x00, 0x00, 0x00, 0x08, 0x00,
+        0x06, 0x00, 0x00, 0x04, 0x00, 0x00, 0x14, 0x00, 0x05, 0x00, 0xfd, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x08, 0x00, 0x04, 0x00,
+        0x04, 0x00, 0x00, 0x00, 0x24, 0x00, 0x0c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0x00, 0x14, 0x00, 0x00,
+        0x00, 0x00, 0x00,
+    ];
+
+    fn payloads(buf: &[u8]) -> Vec<Vec<u8>> {
+        parse_messages(buf)
+            .unwrap()
+            .into_iter()
+            .map(|message| message.payload)
+            .collect()
+    }
+
+    #[test]
+    fn parse_rtnetlink() {
+        let links: Vec<Link> = payloads(LINK)
+            .iter()
+            .filter_map(|p| Link::parse(p))
+            .collect();
+        assert_eq!(
+            links,
+            [Link {
+                index: 4,
+                name: "eth0".to_string(),
+                mtu: Some(1400),
+                up: true
+            }]
+        );
+
+        let addresses: Vec<(u32, String, u8)> = payloads(ADDRESSES)
+            .iter()
+            .filter_map(|p| Address::parse(p))
+            .map(|a| (a.index, a.address.to_string(), a.prefix))
+            .collect();
+        assert_eq!(
+            addresses,
+            [
+                (1, "127.0.0.1".to_string(), 8),
+                (4, "192.0.2.2".to_string(), 24),
+                (1, "::1".to_string(), 128),
+                (4, "fd00::2".to_string(), 64),
+                (4, "fe80::fc:ff:fe00:1".to_string(), 64),
+            ]
+        );
+
+        let routes: Vec<Route> = payloads(ROUTES)
+            .iter()
+            .filter_map(|p| Route::parse(p))
+            .collect();
+        assert_eq!(routes.len(), 3);
+        assert!(routes[0].is_default());
+        assert_eq!(routes[0].gateway, Some("192.0.2.1".parse().unwrap()));
+        assert_eq!(routes[0].interface, Some(4));
+        assert!(!routes[1].is_default());
+        assert_eq!(routes[1].destination, Some("192.0.2.0".parse().unwrap()));
+        assert_eq!(routes[2].gateway, Some("fd00::1".parse().unwrap()));
+        assert_eq!(routes[2].metric, 1024);
+    }
+}
@@ -0,0 +1,96 @@
+//! Pausing the bar while i3bar hides it.
+//!
+//! i3bar sends `SIGTSTP` when it hides the bar and `SIGCONT` once it shows it again. The main
+//! loop then stops sending commands to the blocks, and the threads blocks run in the background,
+//! like D-Bus listeners and the route watcher of `net`, call `wait` before they handle their next
+//! event, so that nothing runs while nobody can see it.
+
+use std::sync::{Condvar, Mutex};
+
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref PAUSED: (Mutex<bool>, Condvar) = (Mutex::new(false), Condvar::new());
+}
+
+/// Pause the background threads of all blocks
+pub fn pause() {
+    *PAUSED.0.lock().unwrap() = true;
+}
+
+/// Let the background threads of all blocks continue
+pub fn resume() {
+    *PAUSED.0.lock().unwrap() = false;
+    PAUSED.1.notify_all();
+}
+
+pub fn is_paused() -> bool {
+    *PAUSED.0.lock().unwrap()
+}
+
+/// Wait until the bar is no longer paused
+pub fn wait() {
+    let mut paused = PAUSED.0.lock().unwrap();
+    while *paused {
+        paused = PAUSED.1.wait(paused).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::config::{Config, SharedConfig};
+    use crate::worker::BlockWorker;
+
+    // There is only one pause for the whole program, so everything that depends on it is tested
+    // here, one after the other
+    #[test]
+    fn pause_threads_and_updates() {
+        let (tx, _rx) = crossbeam_channel::unbounded();
+        let (tx_responses, rx_responses) = crossbeam_channel::unbounded();
+        let mut block = BlockWorker::spawn(
+            0,
+            "template",
+            toml::from_str("").unwrap(),
+            SharedConfig::new(&Config::default()),
+            tx,
+            tx_responses,
+        )
+        .unwrap();
+        let mut deferred = Vec::new();
+
+        // Not paused, so this returns right away
+        wait();
+        crate::request_update(&mut block, &mut deferred);
+        assert!(deferred.is_empty());
+        assert!(rx_responses.recv_timeout(Duration::from_secs(5)).is_ok());
+
+        pause();
+        assert!(is_paused());
+        let done = Arc::new(AtomicBool::new(false));
+        let waiter = {
+            let done = done.clone();
+            thread::spawn(move || {
+                wait();
+                done.store(true, Ordering::SeqCst);
+            })
+        };
+        // Update requests wait for the bar to be visible again, and are only sent once
+        crate::request_update(&mut block, &mut deferred);
+        crate::request_update(&mut block, &mut deferred);
+        assert_eq!(deferred, [0]);
+        thread::sleep(Duration::from_millis(100));
+        assert!(!done.load(Ordering::SeqCst));
+        assert!(rx_responses.try_recv().is_err());
+
+        resume();
+        waiter.join().unwrap();
+        assert!(done.load(Ordering::SeqCst));
+        assert!(!is_paused());
+    }
+}
@@ -1,6 +1,7 @@
 use std::fmt;
-use std::io;
+use std::io::{self, BufRead};
 use std::option::Option;
+use std::str::FromStr;
 use std::string::*;
 use std::thread;
 
@@ -8,7 +9,9 @@ use crossbeam_channel::Sender;
 use serde::{de, Deserializer};
 use serde_derive::Deserialize;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+use crate::errors::{InternalError, ResultExtInternal};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum MouseButton {
     Left,
     Middle,
@@ -20,22 +23,83 @@ pub enum MouseButton {
     Unknown,
 }
 
+impl Default for MouseButton {
+    fn default() -> Self {
+        MouseButton::Unknown
+    }
+}
+
+impl FromStr for MouseButton {
+    type Err = String;
+
+    /// Parse a button name, like "left" or "wheel_up", or an X11 button number
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use MouseButton::*;
+        Ok(match s {
+            "left" | "1" => Left,
+            "middle" | "2" => Middle,
+            "right" | "3" => Right,
+            "wheel_up" | "up" | "4" => WheelUp,
+            "wheel_down" | "down" | "5" => WheelDown,
+            "back" | "8" => Back,
+            "forward" | "9" => Forward,
+            x => return Err(format!("unknown mouse button '{}'", x)),
+        })
+    }
+}
+
+impl<'de> de::Deserialize<'de> for MouseButton {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let button = <String as de::Deserialize>::deserialize(deserializer)?;
+        button.parse().map_err(de::Error::custom)
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 struct I3BarEventInternal {
     pub name: Option<String>,
     pub instance: Option<String>,
-    pub x: u64,
-    pub y: u64,
+    pub x: Option<f64>,
+    pub y: Option<f64>,
 
     #[serde(deserialize_with = "deserialize_mousebutton")]
     pub button: MouseButton,
+
+    // The following fields are only sent by sway and newer versions of i3
+    #[serde(default)]
+    pub modifiers: Vec<String>,
+    pub relative_x: Option<f64>,
+    pub relative_y: Option<f64>,
+    pub width: Option<f64>,
+    pub height: Option<f64>,
+    pub scale: Option<f64>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct I3BarEvent {
     pub id: Option<usize>,
+    /// The `name` of the clicked widget if it is not a numeric id, but the `id` of a block from
+    /// the config. `id` is filled in by the main loop in that case.
+    pub name: Option<String>,
     pub instance: Option<usize>,
     pub button: MouseButton,
+
+    /// The modifier keys held during the click, like "Shift", "Control" or "Mod4"
+    pub modifiers: Vec<String>,
+    /// The position of the click on the screen
+    pub x: Option<f64>,
+    pub y: Option<f64>,
+    /// The position of the click, relative to the top left corner of the clicked widget
+    pub relative_x: Option<f64>,
+    pub relative_y: Option<f64>,
+    /// The size of the clicked widget
+    pub width: Option<f64>,
+    pub height: Option<f64>,
+    /// The scale of the output the bar is on
+    pub scale: Option<f64>,
 }
 
 impl I3BarEvent {
@@ -45,28 +109,151 @@ impl I3BarEvent {
             _ => false,
         }
     }
+
+    /// Whether the modifier key was held during the click (case insensitive)
+    pub fn has_modifier(&self, modifier: &str) -> bool {
+        self.modifiers
+            .iter()
+            .any(|held| held.eq_ignore_ascii_case(modifier))
+    }
+
+    /// Where the click hit the widget horizontally, from 0.0 (left edge) to 1.0 (right edge).
+    /// `None` if the bar does not report it.
+    pub fn relative_position(&self) -> Option<f64> {
+        match (self.relative_x, self.width) {
+            (Some(x), Some(width)) if width > 0. => Some((x / width).clamp(0., 1.)),
+            _ => None,
+        }
+    }
 }
 
+/// Splits the endless JSON array that i3bar writes to stdin into its objects. Everything
+/// outside of objects (the opening `[`, commas, whitespace, garbage) is skipped, and objects may
+/// be split across lines or share one.
+struct ObjectReader<R> {
+    input: R,
+}
+
+impl<R: BufRead> ObjectReader<R> {
+    /// The next complete object, or `None` once the input ends
+    fn next_object(&mut self) -> io::Result<Option<String>> {
+        let mut object = Vec::new();
+        let mut depth = 0;
+        let mut in_string = false;
+        let mut escaped = false;
+        loop {
+            let buffer = self.input.fill_buf()?;
+            if buffer.is_empty() {
+                // A partial object at the end of the input is dropped
+                return Ok(None);
+            }
+
+            let mut consumed = 0;
+            let mut complete = false;
+            for &byte in buffer {
+                consumed += 1;
+                if depth == 0 {
+                    if byte == b'{' {
+                        depth = 1;
+                        object.push(byte);
+                    }
+                    continue;
+                }
+                object.push(byte);
+                if in_string {
+                    if escaped {
+                        escaped = false;
+                    } else if byte == b'\\' {
+                        escaped = true;
+                    } else if byte == b'"' {
+                        in_string = false;
+                    }
+                    continue;
+                }
+                match byte {
+                    b'"' => in_string = true,
+                    b'{' => depth += 1,
+                    b'}' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            complete = true;
+                            break;
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            self.input.consume(consumed);
+
+            if complete {
+                return Ok(Some(String::from_utf8_lossy(&object).into_owned()));
+            }
+        }
+    }
+}
+
+fn parse_event(object: &str) -> crate::errors::Result<I3BarEvent> {
+    let e: I3BarEventInternal = serde_json::from_str(object).internal_error(
+        "i3bar input",
+        &format!("failed to parse click event '{}'", object),
+    )?;
+    // Names that are no numbers are the `id`s of blocks from the config, or do not belong to
+    // us at all. The main loop sorts them out.
+    let (id, name) = match e.name.as_deref().map(str::parse::<usize>) {
+        Some(Ok(id)) => (Some(id), None),
+        _ => (None, e.name),
+    };
+    Ok(I3BarEvent {
+        id,
+        name,
+        instance: e.instance.and_then(|x| x.parse::<usize>().ok()),
+        button: e.button,
+        modifiers: e.modifiers,
+        x: e.x,
+        y: e.y,
+        relative_x: e.relative_x,
+        relative_y: e.relative_y,
+        width: e.width,
+        height: e.height,
+        scale: e.scale,
+    })
+}
+
+/// Starts a thread that reads click events from stdin and sends them on the provided channel.
+/// Malformed events are reported and skipped. The thread (and with it the channel) ends when
+/// stdin is closed.
 pub fn process_events(sender: Sender<I3BarEvent>) {
     thread::Builder::new()
         .name("input".into())
-        .spawn(move || loop {
-            let mut input = String::new();
-            io::stdin().read_line(&mut input).unwrap();
-
-            // Take only the valid JSON object betweem curly braces (cut off leading bracket, commas and whitespace)
-            let slice = input.trim_start_matches(|c| c != '{');
-            let slice = slice.trim_end_matches(|c| c != '}');
-
-            if !slice.is_empty() {
-                let e: I3BarEventInternal = serde_json::from_str(slice).unwrap();
-                sender
-                    .send(I3BarEvent {
-                        id: e.name.map(|x| x.parse::<usize>().unwrap()),
-                        instance: e.instance.map(|x| x.parse::<usize>().unwrap()),
-                        button: e.button,
-                    })
-                    .unwrap();
+        .spawn(move || {
+            let stdin = io::stdin();
+            let mut reader = ObjectReader {
+                input: stdin.lock(),
+            };
+            loop {
+                let object = match reader.next_object() {
+                    Ok(Some(object)) => object,
+                    Ok(None) => break,
+                    Err(error) => {
+                        eprintln!(
+                            "{}",
+                            InternalError(
+                                "i3bar input".to_string(),
+                                "failed to read from stdin".to_string(),
+                                Some((error.to_string(), format!("{:?}", error))),
+                            )
+                        );
+                        break;
+                    }
+                };
+                match parse_event(&object) {
+                    Ok(event) => {
+                        if sender.send(event).is_err() {
+                            break;
+                        }
+                    }
+                    Err(error) => eprintln!("{}", error),
+                }
             }
         })
         .unwrap();
@@ -106,3 +293,62 @@ where
 
     deserializer.deserialize_any(MouseButtonVisitor)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_objects() {
+        let input = "[\n{\"name\":\"1\",\"button\":1}\n,{\"name\":\"a}\\\"{\",\n\"button\":3},{\"name\":\"2\",\"button\":99,\"foo\":{}}\n,{\"name\"";
+        let mut reader = ObjectReader {
+            input: io::Cursor::new(input),
+        };
+        let mut events = Vec::new();
+        while let Some(object) = reader.next_object().unwrap() {
+            events.push(parse_event(&object).unwrap());
+        }
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].id, Some(1));
+        assert_eq!(events[0].button, MouseButton::Left);
+        assert_eq!(events[1].id, None);
+        assert_eq!(events[1].name.as_deref(), Some("a}\"{"));
+        assert_eq!(events[1].button, MouseButton::Right);
+        assert_eq!(events[2].button, MouseButton::Unknown);
+
+        assert!(parse_event("{\"name\":").is_err());
+    }
+
+    #[test]
+    fn parse_click_with_position() {
+        // As sent by sway when shift-clicking a widget that is 84 pixels wide
+        let event = parse_event(
+            r#"{"name":"3","instance":"1","button":1,"event":272,"x":1802,"y":12,"relative_x":42,"relative_y":12,"width":84,"height":22,"scale":1,"modifiers":["Shift"]}"#,
+        )
+        .unwrap();
+        assert_eq!(event.id, Some(3));
+        assert_eq!(event.instance, Some(1));
+        assert_eq!(event.button, MouseButton::Left);
+        assert!(event.has_modifier("shift"));
+        assert!(!event.has_modifier("Mod4"));
+        assert_eq!((event.x, event.y), (Some(1802.), Some(12.)));
+        assert_eq!((event.width, event.height), (Some(84.), Some(22.)));
+        assert_eq!(event.scale, Some(1.));
+        assert_eq!(event.relative_position(), Some(0.5));
+
+        let at = |relative_x: f64| I3BarEvent {
+            relative_x: Some(relative_x),
+            ..event.clone()
+        };
+        assert_eq!(at(0.).relative_position(), Some(0.));
+        assert_eq!(at(84.).relative_position(), Some(1.));
+        assert_eq!(at(90.).relative_position(), Some(1.));
+
+        // Older versions of i3 only send the button and the position on the screen
+        let event =
+            parse_event(r#"{"name":"3","instance":"1","button":3,"x":1802,"y":12}"#).unwrap();
+        assert!(event.modifiers.is_empty());
+        assert_eq!(event.relative_position(), None);
+    }
+}
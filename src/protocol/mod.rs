@@ -1,22 +1,17 @@
 pub mod i3bar_block;
 pub mod i3bar_event;
+pub mod output;
 
-use crate::blocks::Block;
-use crate::config::SharedConfig;
 use crate::errors::*;
+use crate::themes::Theme;
 use crate::util::add_colors;
 
 use i3bar_block::I3BarBlock;
+use output::Printer;
 
-pub fn init(never_pause: bool) {
-    if never_pause {
-        println!("{{\"version\": 1, \"click_events\": true, \"stop_signal\": 0}}\n[");
-    } else {
-        println!("{{\"version\": 1, \"click_events\": true}}\n[");
-    }
-}
-
-pub fn print_blocks(blocks: &[Box<dyn Block>], config: &SharedConfig) -> Result<()> {
+/// Print the widgets of all blocks, `blocks` holds the rendered widgets of each block and the
+/// theme of the block, which decides on its separator and tint
+pub fn print_blocks(blocks: &[(&[I3BarBlock], &Theme)], printer: &Printer) -> Result<()> {
     let mut last_bg: Option<String> = None;
 
     let mut rendered_blocks = vec![];
@@ -28,13 +23,12 @@ pub fn print_blocks(blocks: &[Box<dyn Block>], config: &SharedConfig) -> Result<
      */
     let visible_count = blocks
         .iter()
-        .filter(|block| !block.view().is_empty())
+        .filter(|(widgets, _)| !widgets.is_empty())
         .count();
 
     let mut alternator = visible_count % 2 == 0;
 
-    for block in blocks.iter() {
-        let widgets = block.view();
+    for &(widgets, theme) in blocks {
         if widgets.is_empty() {
             continue;
         }
@@ -42,19 +36,17 @@ pub fn print_blocks(blocks: &[Box<dyn Block>], config: &SharedConfig) -> Result<
         let mut rendered_widgets: Vec<I3BarBlock> = widgets
             .iter()
             .map(|widget| {
-                let mut data = widget.get_data();
+                let mut data = widget.clone();
                 if alternator {
                     // Apply tint for all widgets of every second block
                     data.background = add_colors(
                         data.background.as_deref(),
-                        config.theme.alternating_tint_bg.as_deref(),
-                    )
-                    .unwrap();
-                    data.color = add_colors(
-                        data.color.as_deref(),
-                        config.theme.alternating_tint_bg.as_deref(),
+                        theme.alternating_tint_bg.as_deref(),
                     )
                     .unwrap();
+                    data.color =
+                        add_colors(data.color.as_deref(), theme.alternating_tint_bg.as_deref())
+                            .unwrap();
                 }
                 data
             })
@@ -62,56 +54,48 @@ pub fn print_blocks(blocks: &[Box<dyn Block>], config: &SharedConfig) -> Result<
 
         alternator = !alternator;
 
-        if config.theme.separator.is_none() {
+        if theme.separator.is_none() {
             // Re-add native separator on last widget for native theme
             rendered_widgets.last_mut().unwrap().separator = None;
             rendered_widgets.last_mut().unwrap().separator_block_width = None;
         }
 
-        // Serialize and concatenate widgets
-        let block_str = rendered_widgets
-            .iter()
-            .map(|w| w.render())
-            .collect::<Vec<String>>()
-            .join(",");
-
-        if config.theme.separator.is_none() {
+        if theme.separator.is_none() {
             // Skip separator block for native theme
-            rendered_blocks.push(block_str.to_string());
+            rendered_blocks.append(&mut rendered_widgets);
             continue;
         }
 
         // The first widget's BG is used to get the FG color for the current separator
-        let sep_fg = if config.theme.separator_fg == Some("auto".to_string()) {
+        let sep_fg = if theme.separator_fg == Some("auto".to_string()) {
             rendered_widgets.first().unwrap().background.clone()
         } else {
-            config.theme.separator_fg.clone()
+            theme.separator_fg.clone()
         };
 
         // The separator's BG is the last block's last widget's BG
-        let sep_bg = if config.theme.separator_bg == Some("auto".to_string()) {
+        let sep_bg = if theme.separator_bg == Some("auto".to_string()) {
             last_bg
         } else {
-            config.theme.separator_bg.clone()
+            theme.separator_bg.clone()
         };
 
-        if let Some(ref separator) = config.theme.separator {
+        if let Some(ref separator) = theme.separator {
             let separator = I3BarBlock {
                 full_text: separator.clone(),
                 background: sep_bg,
                 color: sep_fg,
                 ..Default::default()
             };
-            rendered_blocks.push(format!("{},{}", separator.render(), block_str));
-        } else {
-            rendered_blocks.push(block_str);
+            rendered_blocks.push(separator);
         }
 
         // The last widget's BG is used to get the BG color for the next separator
         last_bg = rendered_widgets.last().unwrap().background.clone();
+        rendered_blocks.append(&mut rendered_widgets);
     }
 
-    println!("[{}],", rendered_blocks.join(","));
+    printer.print(&rendered_blocks);
 
     Ok(())
 }
@@ -0,0 +1,291 @@
+//! The formats the bar can be printed in.
+//!
+//! Blocks always render i3bar widgets. For every other bar the widgets are translated right
+//! before they are printed, so the same configuration can drive all of them.
+
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{de, Deserialize, Deserializer};
+
+use super::i3bar_block::I3BarBlock;
+use crate::util::{escape_pango_text, unescape_pango_text};
+
+/// Drawn between blocks if the theme leaves the separators to the bar, which only i3bar does
+const NATIVE_SEPARATOR: &str = "|";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Output {
+    /// The JSON stream of the i3bar protocol, also understood by swaybar
+    I3bar,
+    /// A line of plain text, e.g. for a terminal
+    Plain,
+    /// A line with tmux style directives, for `status-right` and friends
+    Tmux,
+    /// A line with lemonbar formatting tags and click areas
+    Lemonbar,
+    /// A line of JSON for a custom waybar module with `"return-type": "json"`
+    Waybar,
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Output::I3bar
+    }
+}
+
+impl FromStr for Output {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "i3bar" | "swaybar" => Self::I3bar,
+            "plain" => Self::Plain,
+            "tmux" => Self::Tmux,
+            "lemonbar" => Self::Lemonbar,
+            "waybar" => Self::Waybar,
+            x => return Err(format!("unknown output '{}'", x)),
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Output {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let output = String::deserialize(deserializer)?;
+        output.parse().map_err(de::Error::custom)
+    }
+}
+
+/// Prints lines of widgets in the format of an `Output`
+#[derive(Debug, Clone)]
+pub struct Printer {
+    output: Output,
+    /// The command lemonbar outputs for clicks, it is completed with the arguments of `ctl click`
+    click_command: String,
+}
+
+impl Printer {
+    /// `socket_path` is the control socket the click areas of lemonbar send their clicks to
+    pub fn new(output: Output, socket_path: &Path) -> Self {
+        let exe = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.to_str().map(String::from))
+            .unwrap_or_else(|| "i3status-rs".to_string());
+        Self {
+            output,
+            click_command: format!("{} ctl --socket {} click", exe, socket_path.display()),
+        }
+    }
+
+    pub fn output(&self) -> Output {
+        self.output
+    }
+
+    /// Print the header of the protocol. Unless `never_pause` is set, i3bar asks us to pause
+    /// with `SIGTSTP` while the bar is hidden and to continue with `SIGCONT` (instead of the
+    /// default `SIGSTOP`, which would freeze every thread of the process wherever it is).
+    /// The other bars have no header.
+    pub fn init(&self, never_pause: bool) {
+        if self.output != Output::I3bar {
+            return;
+        }
+        if never_pause {
+            println!("{{\"version\": 1, \"click_events\": true, \"stop_signal\": 0}}\n[");
+        } else {
+            println!(
+                "{{\"version\": 1, \"click_events\": true, \"stop_signal\": {}, \"cont_signal\": {}}}\n[",
+                signal_hook::consts::SIGTSTP,
+                signal_hook::consts::SIGCONT
+            );
+        }
+    }
+
+    /// Print one line of the bar
+    pub fn print(&self, widgets: &[I3BarBlock]) {
+        println!("{}", self.render(widgets));
+    }
+
+    fn render(&self, widgets: &[I3BarBlock]) -> String {
+        match self.output {
+            Output::I3bar => {
+                let widgets: Vec<String> = widgets.iter().map(I3BarBlock::render).collect();
+                format!("[{}],", widgets.join(","))
+            }
+            Output::Plain => join_widgets(widgets, plain_text, |separator| separator.to_string()),
+            Output::Tmux => {
+                let mut line = join_widgets(
+                    widgets,
+                    |widget| {
+                        format!(
+                            "#[fg={},bg={}]{}",
+                            widget.color.as_deref().map_or("default", rgb),
+                            widget.background.as_deref().map_or("default", rgb),
+                            plain_text(widget).replace('#', "##")
+                        )
+                    },
+                    |separator| format!("#[default]{}", separator),
+                );
+                line.push_str("#[default]");
+                line
+            }
+            Output::Lemonbar => {
+                let mut line = join_widgets(
+                    widgets,
+                    |widget| {
+                        let mut text = format!(
+                            "%{{F{}}}%{{B{}}}{}",
+                            widget.color.as_deref().map_or("-".to_string(), argb),
+                            widget.background.as_deref().map_or("-".to_string(), argb),
+                            plain_text(widget).replace('%', "%%")
+                        );
+                        if let Some(ref name) = widget.name {
+                            for (index, button) in
+                                ["left", "middle", "right", "up", "down"].iter().enumerate()
+                            {
+                                let command = format!(
+                                    "{} {} {} {}",
+                                    self.click_command,
+                                    name,
+                                    button,
+                                    widget.instance.as_deref().unwrap_or_default()
+                                );
+                                text = format!(
+                                    "%{{A{}:{}:}}{}%{{A}}",
+                                    index + 1,
+                                    command.trim_end().replace(':', "\\:"),
+                                    text
+                                );
+                            }
+                        }
+                        text
+                    },
+                    |separator| format!("%{{F-}}%{{B-}}{}", separator),
+                );
+                line.push_str("%{F-}%{B-}");
+                line
+            }
+            Output::Waybar => {
+                let text = join_widgets(
+                    widgets,
+                    |widget| {
+                        let mut attributes = String::new();
+                        if let Some(ref color) = widget.color {
+                            attributes.push_str(&format!(" foreground=\"{}\"", rgb(color)));
+                        }
+                        if let Some(ref background) = widget.background {
+                            attributes.push_str(&format!(" background=\"{}\"", rgb(background)));
+                        }
+                        format!("<span{}>{}</span>", attributes, pango_text(widget))
+                    },
+                    |separator| separator.to_string(),
+                );
+                let class = if widgets.iter().any(|widget| widget.urgent == Some(true)) {
+                    "urgent"
+                } else {
+                    ""
+                };
+                serde_json::json!({ "text": text, "class": class }).to_string()
+            }
+        }
+    }
+}
+
+/// Concatenate the rendered widgets. Where i3bar would draw a separator (the widget does not
+/// ask for none), `separator` renders one.
+fn join_widgets(
+    widgets: &[I3BarBlock],
+    widget: impl Fn(&I3BarBlock) -> String,
+    separator: impl Fn(&str) -> String,
+) -> String {
+    let mut line = String::new();
+    for (i, w) in widgets.iter().enumerate() {
+        line.push_str(&widget(w));
+        if w.separator != Some(false) && i + 1 < widgets.len() {
+            line.push_str(&separator(NATIVE_SEPARATOR));
+        }
+    }
+    line
+}
+
+/// The text of a widget with pango markup removed
+fn plain_text(widget: &I3BarBlock) -> String {
+    if widget.markup.as_deref() != Some("pango") {
+        return widget.full_text.clone();
+    }
+    let mut text = String::with_capacity(widget.full_text.len());
+    let mut in_tag = false;
+    for c in widget.full_text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    unescape_pango_text(&text)
+}
+
+/// The text of a widget as pango markup
+fn pango_text(widget: &I3BarBlock) -> String {
+    if widget.markup.as_deref() == Some("pango") {
+        widget.full_text.clone()
+    } else {
+        escape_pango_text(widget.full_text.clone())
+    }
+}
+
+/// `#RRGGBB[AA]` without the alpha channel
+fn rgb(color: &str) -> &str {
+    color.get(..7).unwrap_or(color)
+}
+
+/// `#RRGGBB[AA]` in the `#AARRGGBB` notation of lemonbar
+fn argb(color: &str) -> String {
+    match (color.get(1..7), color.get(7..9)) {
+        (Some(rgb), Some(alpha)) => format!("#{}{}", alpha, rgb),
+        _ => color.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render() {
+        let widgets = [
+            I3BarBlock {
+                full_text: " 50% &amp; <b>up</b> ".to_string(),
+                color: Some("#FFFFFF".to_string()),
+                background: Some("#00000080".to_string()),
+                name: Some("vol".to_string()),
+                instance: Some("0".to_string()),
+                separator: None,
+                ..Default::default()
+            },
+            I3BarBlock {
+                full_text: " #1 ".to_string(),
+                ..Default::default()
+            },
+        ];
+        let render = |output| Printer::new(output, Path::new("/s")).render(&widgets);
+
+        assert_eq!(render(Output::Plain), " 50% & up | #1 ");
+        assert_eq!(
+            render(Output::Tmux),
+            "#[fg=#FFFFFF,bg=#000000] 50% & up #[default]|\
+             #[fg=default,bg=default] ##1 #[default]"
+        );
+        let lemonbar = render(Output::Lemonbar);
+        assert!(lemonbar.starts_with("%{A5:"));
+        assert!(lemonbar.contains("ctl --socket /s click vol left 0:}%{F#FFFFFF}%{B#80000000}"));
+        assert!(lemonbar.ends_with("%{F-}%{B-} #1 %{F-}%{B-}"));
+        assert_eq!(
+            render(Output::Waybar),
+            r##"{"class":"","text":"<span foreground=\"#FFFFFF\" background=\"#000000\"> 50% &amp; <b>up</b> </span>|<span> #1 </span>"}"##
+        );
+    }
+}
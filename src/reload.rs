@@ -0,0 +1,434 @@
+//! Live reloading of the configuration file.
+//!
+//! The config file and the files it includes are watched with inotify and, once one of them
+//! changes, the new `Config` is diffed against the running one. Blocks whose TOML table did not
+//! change are kept (together with their state), everything else is rebuilt in a new
+//! `BlockWorker`.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use inotify::{Event, Inotify, WatchDescriptor, WatchMask};
+use toml::value::Value;
+
+use crate::blocks::check_block;
+use crate::config::{Config, ConfigFile, SharedConfig};
+use crate::errors::*;
+use crate::scheduler::UpdateScheduler;
+use crate::worker::BlockWorker;
+
+/// Starts a thread that watches the config file and the files it includes, and sends a message on
+/// the provided channel every time one of them has been written to or replaced. The included files
+/// are looked up again after every change, so that added includes are watched as well. If the
+/// files can't be watched any longer, the error is sent instead and the thread exits.
+pub fn watch_config(path: &Path, sender: Sender<Result<()>>) -> Result<()> {
+    let path = path.to_owned();
+    let mut notify = Inotify::init().internal_error("config watcher", "failed to start inotify")?;
+    let mut watched = Watched::default();
+    watched.watch(&mut notify, &ConfigFile::files(&path))?;
+
+    thread::Builder::new()
+        .name("config_watcher".into())
+        .spawn(move || {
+            let mut buffer = [0; 1024];
+            loop {
+                let changed = match notify
+                    .read_events_blocking(&mut buffer)
+                    .internal_error("config watcher", "failed to read inotify events")
+                {
+                    Ok(mut events) => events.any(|event| watched.contains(&event)),
+                    Err(error) => {
+                        let _ = sender.send(Err(error));
+                        break;
+                    }
+                };
+
+                if changed {
+                    // Editors usually emit a burst of events on save, let the file settle.
+                    // Any further events only lead to a reload without changes.
+                    thread::sleep(Duration::from_millis(100));
+                    let watching = watched.watch(&mut notify, &ConfigFile::files(&path));
+                    if sender.send(watching.map(|_| ())).is_err() {
+                        break;
+                    }
+                }
+            }
+        })
+        .internal_error("config watcher", "failed to spawn thread")?;
+
+    Ok(())
+}
+
+/// The files watched by `watch_config`, by the directory they are in
+#[derive(Default)]
+struct Watched {
+    dirs: HashMap<WatchDescriptor, PathBuf>,
+    files: HashSet<(PathBuf, OsString)>,
+}
+
+impl Watched {
+    /// Watch `files` in addition to the files watched so far
+    fn watch(&mut self, notify: &mut Inotify, files: &[PathBuf]) -> Result<()> {
+        for file in files {
+            let name = file
+                .file_name()
+                .internal_error("config watcher", "config path has no file name")?
+                .to_owned();
+            let dir = match file.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir.to_owned(),
+                _ => Path::new(".").to_owned(),
+            };
+            // Most editors don't modify the file in place, but write a new file and rename it
+            // over the old one, so we have to watch the parent directory instead of the file
+            // itself.
+            let wd = notify
+                .add_watch(
+                    &dir,
+                    WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::CREATE,
+                )
+                .internal_error("config watcher", "failed to watch config directory")?;
+            self.dirs.insert(wd, dir.clone());
+            self.files.insert((dir, name));
+        }
+        Ok(())
+    }
+
+    fn contains(&self, event: &Event<&OsStr>) -> bool {
+        match (self.dirs.get(&event.wd), event.name) {
+            (Some(dir), Some(name)) => self.files.contains(&(dir.clone(), name.to_owned())),
+            _ => false,
+        }
+    }
+}
+
+/// What to do with a block of the new config.
+enum Slot {
+    /// Reuse the running block at this index.
+    Keep(usize),
+    /// Use a freshly created block.
+    New(Box<BlockWorker>),
+}
+
+/// Applies `new_config` to the running bar.
+///
+/// Blocks whose name and configuration table are unchanged keep running as they are. Changed
+/// and added blocks are created by `spawn` with fresh ids (taken from `next_id`) and scheduled for an
+/// immediate update, removed blocks are dropped. If a setting that is shared by all blocks
+/// (theme, icons, ...) changed, every block is rebuilt.
+///
+/// Nothing is modified if the config of any of the new blocks is invalid. The blocks themselves
+/// are created in their threads later on, errors that only show then are shown by the block.
+pub fn apply_config(
+    new_config: Config,
+    config: &mut Config,
+    shared_config: &mut SharedConfig,
+    blocks: &mut Vec<BlockWorker>,
+    scheduler: &mut UpdateScheduler,
+    next_id: &mut usize,
+    mut spawn: impl FnMut(usize, &str, Value, SharedConfig) -> Result<BlockWorker>,
+) -> Result<()> {
+    let rebuild_all = !config.shared_eq(&new_config);
+    let new_shared_config = if rebuild_all {
+        SharedConfig::new(&new_config)
+    } else {
+        shared_config.clone()
+    };
+
+    // Match every block of the new config against a not yet reused block of the running one
+    let mut reused = vec![false; config.blocks.len()];
+    let mut id = *next_id;
+    let mut slots = Vec::with_capacity(new_config.blocks.len());
+    for (block_name, block_config) in &new_config.blocks {
+        let old =
+            if rebuild_all {
+                None
+            } else {
+                config.blocks.iter().zip(&reused).position(|(old, reused)| {
+                    !reused && old.0 == *block_name && old.1 == *block_config
+                })
+            };
+        match old {
+            Some(i) => {
+                reused[i] = true;
+                slots.push(Slot::Keep(i));
+            }
+            None => {
+                check_block(block_name, block_config.clone(), new_shared_config.clone())?;
+                slots.push(Slot::New(Box::new(spawn(
+                    id,
+                    block_name,
+                    block_config.clone(),
+                    new_shared_config.clone(),
+                )?)));
+                id += 1;
+            }
+        }
+    }
+
+    // All new blocks were created successfully, it is safe to swap them in now
+    let mut old_blocks: Vec<Option<BlockWorker>> = blocks.drain(..).map(Some).collect();
+    for slot in slots {
+        match slot {
+            Slot::Keep(i) => blocks.push(
+                old_blocks[i]
+                    .take()
+                    .internal_error("reload", "block was reused twice")?,
+            ),
+            Slot::New(block) => {
+                scheduler.schedule(block.id());
+                blocks.push(*block);
+            }
+        }
+    }
+
+    *next_id = id;
+    *shared_config = new_shared_config;
+    *config = new_config;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::Task;
+    use crate::worker::{Command, Response};
+    use std::time::Instant;
+
+    fn blocks_from(
+        config: &Config,
+        tx: &Sender<Task>,
+        tx_responses: &Sender<Response>,
+    ) -> Vec<BlockWorker> {
+        config
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(id, (name, block_config))| {
+                BlockWorker::spawn(
+                    id,
+                    name,
+                    block_config.clone(),
+                    SharedConfig::new(config),
+                    tx.clone(),
+                    tx_responses.clone(),
+                )
+                .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn only_changed_blocks_are_rebuilt() {
+        let mut config: Config = toml::from_str(
+            r#"
+            [[block]]
+            block = "template"
+            interval = 1
+            [[block]]
+            block = "template"
+            interval = 2
+            [[block]]
+            block = "template"
+            interval = 3
+            "#,
+        )
+        .unwrap();
+        let new_config: Config = toml::from_str(
+            r#"
+            [[block]]
+            block = "template"
+            interval = 3
+            [[block]]
+            block = "template"
+            interval = 4
+            [[block]]
+            block = "template"
+            interval = 1
+            "#,
+        )
+        .unwrap();
+
+        let (tx, _rx) = crossbeam_channel::unbounded();
+        let (tx_responses, _rx_responses) = crossbeam_channel::unbounded();
+        let mut shared_config = SharedConfig::new(&config);
+        let mut blocks = blocks_from(&config, &tx, &tx_responses);
+        let mut scheduler = UpdateScheduler::new(&blocks);
+        let mut next_id = blocks.len();
+
+        assert!(apply_config(
+            new_config.clone(),
+            &mut config,
+            &mut shared_config,
+            &mut blocks,
+            &mut scheduler,
+            &mut next_id,
+            |id, name, block_config, shared_config| BlockWorker::spawn(
+                id,
+                name,
+                block_config,
+                shared_config,
+                tx.clone(),
+                tx_responses.clone()
+            ),
+        )
+        .is_ok());
+
+        let ids: Vec<usize> = blocks.iter().map(|block| block.id()).collect();
+        assert_eq!(ids, vec![2, 3, 0]);
+        assert_eq!(next_id, 4);
+        assert_eq!(config, new_config);
+    }
+
+    #[test]
+    fn invalid_config_changes_nothing() {
+        let mut config: Config = toml::from_str(
+            r#"
+            [[block]]
+            block = "template"
+            interval = 1
+            "#,
+        )
+        .unwrap();
+        let new_config: Config = toml::from_str(
+            r#"
+            [[block]]
+            block = "template"
+            interval = 2
+            [[block]]
+            block = "template"
+            no_such_option = 3
+            "#,
+        )
+        .unwrap();
+
+        let (tx, _rx) = crossbeam_channel::unbounded();
+        let (tx_responses, _rx_responses) = crossbeam_channel::unbounded();
+        let mut shared_config = SharedConfig::new(&config);
+        let mut blocks = blocks_from(&config, &tx, &tx_responses);
+        let mut scheduler = UpdateScheduler::new(&blocks);
+        let mut next_id = blocks.len();
+        let old_config = config.clone();
+
+        assert!(apply_config(
+            new_config,
+            &mut config,
+            &mut shared_config,
+            &mut blocks,
+            &mut scheduler,
+            &mut next_id,
+            |id, name, block_config, shared_config| BlockWorker::spawn(
+                id,
+                name,
+                block_config,
+                shared_config,
+                tx.clone(),
+                tx_responses.clone()
+            ),
+        )
+        .is_err());
+
+        let ids: Vec<usize> = blocks.iter().map(|block| block.id()).collect();
+        assert_eq!(ids, vec![0]);
+        assert_eq!(next_id, 1);
+        assert_eq!(config, old_config);
+    }
+
+    #[test]
+    fn removed_blocks_stop_taking_update_requests() {
+        let mut config: Config = toml::from_str(
+            r#"
+            [[block]]
+            block = "template"
+            interval = 1
+            "#,
+        )
+        .unwrap();
+        let new_config: Config = toml::from_str(
+            r#"
+            [[block]]
+            block = "template"
+            interval = 2
+            "#,
+        )
+        .unwrap();
+
+        let (tx, _rx) = crossbeam_channel::unbounded();
+        let (tx_responses, _rx_responses) = crossbeam_channel::unbounded();
+        let mut shared_config = SharedConfig::new(&config);
+        let mut blocks = blocks_from(&config, &tx, &tx_responses);
+        let mut scheduler = UpdateScheduler::new(&blocks);
+        let mut next_id = blocks.len();
+
+        blocks[0].send(Command::Update { scheduled: false });
+        let tx_block_update = blocks[0].block_update_sender().unwrap();
+        let task = || Task {
+            id: 0,
+            update_time: Instant::now(),
+        };
+        assert!(tx_block_update.send(task()).is_ok());
+
+        assert!(apply_config(
+            new_config,
+            &mut config,
+            &mut shared_config,
+            &mut blocks,
+            &mut scheduler,
+            &mut next_id,
+            |id, name, block_config, shared_config| BlockWorker::spawn(
+                id,
+                name,
+                block_config,
+                shared_config,
+                tx.clone(),
+                tx_responses.clone()
+            ),
+        )
+        .is_ok());
+
+        // Background threads of the old block give up once their requests fail
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while tx_block_update.send(task()).is_ok() {
+            assert!(Instant::now() < deadline, "the old block is still running");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn included_files_are_watched() {
+        use assert_fs::prelude::*;
+
+        let dir = assert_fs::TempDir::new().unwrap();
+        let config = dir.child("config.toml");
+        config.write_str("include = \"common.toml\"").unwrap();
+        dir.child("common.toml").write_str("").unwrap();
+        let (tx, rx) = crossbeam_channel::unbounded();
+        watch_config(config.path(), tx).unwrap();
+        let changed = || {
+            let changed = rx.recv_timeout(Duration::from_secs(5)).is_ok();
+            // Let the burst of events of a write settle
+            thread::sleep(Duration::from_millis(300));
+            while rx.try_recv().is_ok() {}
+            changed
+        };
+
+        dir.child("common.toml").write_str("[[block]]").unwrap();
+        assert!(changed());
+
+        // A file that is included from now on is watched as well
+        dir.child("other.toml").write_str("").unwrap();
+        while rx.try_recv().is_ok() {}
+        config
+            .write_str("include = [\"common.toml\", \"other.toml\"]")
+            .unwrap();
+        assert!(changed());
+        dir.child("other.toml").write_str("[[block]]").unwrap();
+        assert!(changed());
+
+        dir.child("unrelated.toml").write_str("").unwrap();
+        assert!(rx.recv_timeout(Duration::from_millis(300)).is_err());
+    }
+}
@@ -2,11 +2,14 @@ use crate::blocks::Update;
 use std::cmp;
 use std::collections::BinaryHeap;
 use std::fmt;
-use std::thread;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use crate::blocks::Block;
+use crossbeam_channel::Sender;
+
 use crate::errors::*;
+use crate::worker::{BlockWorker, Command};
 
 #[derive(Debug, Clone)]
 pub struct Task {
@@ -14,6 +17,42 @@ pub struct Task {
     pub update_time: Instant,
 }
 
+/// Asks for updates of a block from the callbacks of a background thread, like D-Bus signal
+/// handlers, which cannot end the thread themselves. Once a request fails because the block is
+/// gone, `is_gone` tells the thread to stop.
+#[derive(Clone)]
+pub struct UpdateRequester {
+    id: usize,
+    tx_update_request: Sender<Task>,
+    gone: Arc<AtomicBool>,
+}
+
+impl UpdateRequester {
+    pub fn new(id: usize, tx_update_request: Sender<Task>) -> Self {
+        Self {
+            id,
+            tx_update_request,
+            gone: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Ask for an immediate update of the block
+    pub fn request(&self) {
+        let task = Task {
+            id: self.id,
+            update_time: Instant::now(),
+        };
+        if self.tx_update_request.send(task).is_err() {
+            self.gone.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// Whether a request has failed because the block is gone
+    pub fn is_gone(&self) -> bool {
+        self.gone.load(Ordering::SeqCst)
+    }
+}
+
 impl fmt::Display for Task {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:?}", self)
@@ -45,7 +84,7 @@ pub struct UpdateScheduler {
 }
 
 impl UpdateScheduler {
-    pub fn new(blocks: &[Box<dyn Block>]) -> UpdateScheduler {
+    pub fn new(blocks: &[BlockWorker]) -> UpdateScheduler {
         let mut schedule = BinaryHeap::new();
 
         let now = Instant::now();
@@ -59,6 +98,25 @@ impl UpdateScheduler {
         UpdateScheduler { schedule }
     }
 
+    /// Schedule an immediate update of a block, e.g. after it has been (re)created
+    pub fn schedule(&mut self, id: usize) {
+        self.schedule.push(Task {
+            id,
+            update_time: Instant::now(),
+        });
+    }
+
+    /// Schedule the next update of a block according to the result of its last update
+    pub fn reschedule(&mut self, id: usize, last_update: Instant, update: Option<Update>) {
+        match update {
+            Some(Update::Every(d)) => self.schedule.push(Task {
+                id,
+                update_time: last_update + d,
+            }),
+            Some(Update::Once) | None => {} // do not schedule this task again
+        }
+    }
+
     pub fn time_to_next_update(&self) -> Option<Duration> {
         if let Some(peeked) = self.schedule.peek() {
             let next_update = peeked.update_time;
@@ -74,48 +132,21 @@ impl UpdateScheduler {
         }
     }
 
-    pub fn do_scheduled_updates(&mut self, blocks: &mut Vec<Box<dyn Block>>) -> Result<()> {
-        let t = self
-            .schedule
-            .pop()
-            .internal_error("scheduler", "schedule is empty")?;
-        let mut tasks_next = vec![t.clone()];
-
-        while !self.schedule.is_empty()
-            && t.update_time
-                == self
-                    .schedule
-                    .peek()
-                    .internal_error("scheduler", "schedule is empty")?
-                    .update_time
-        {
-            tasks_next.push(
-                self.schedule
-                    .pop()
-                    .internal_error("scheduler", "schedule is empty")?,
-            )
-        }
-
-        let now = Instant::now();
-        if t.update_time > now {
-            thread::sleep(t.update_time - now);
-        }
-
+    /// Ask every block whose update is due to update itself. The blocks are rescheduled once
+    /// their answers arrive.
+    pub fn do_scheduled_updates(&mut self, blocks: &mut [BlockWorker]) -> Result<()> {
         let now = Instant::now();
-
-        for task in tasks_next {
-            if let Some(dur) = blocks
-                .get_mut(task.id as usize)
-                .internal_error("scheduler", "could not get required block")?
-                .update()?
-            {
-                match dur {
-                    Update::Every(d) => self.schedule.push(Task {
-                        id: task.id,
-                        update_time: now + d,
-                    }),
-                    Update::Once => {} // do not schedule this task again
-                }
+        while let Some(task) = self.schedule.peek() {
+            if task.update_time > now {
+                break;
+            }
+            let task = self
+                .schedule
+                .pop()
+                .internal_error("scheduler", "schedule is empty")?;
+            // The block may have been removed by a config reload in the meantime
+            if let Some(block) = blocks.iter_mut().find(|block| block.id() == task.id) {
+                block.send(Command::Update { scheduled: true });
             }
         }
 
@@ -2,8 +2,10 @@ use crate::errors::*;
 use crossbeam_channel::Sender;
 use std::thread;
 
-/// Starts a thread that listens for provided signals and sends these on the provided channel
-pub fn process_signals(sender: Sender<i32>) {
+/// Starts a thread that listens for provided signals and sends these on the provided channel.
+/// `SIGTSTP` and `SIGCONT` are only taken over if `pausable` is set, as only i3bar uses them to
+/// pause the bar. Otherwise they keep stopping the process, like Ctrl-Z in a terminal does.
+pub fn process_signals(sender: Sender<i32>, pausable: bool) {
     thread::Builder::new()
         .name("signals".into())
         .spawn(move || {
@@ -17,6 +19,11 @@ pub fn process_signals(sender: Sender<i32>) {
                 let mut signals = (sigmin..sigmax).collect::<Vec<_>>();
                 signals.push(signal_hook::consts::SIGUSR1);
                 signals.push(signal_hook::consts::SIGUSR2);
+                // i3bar pauses and continues us with these
+                if pausable {
+                    signals.push(signal_hook::consts::SIGTSTP);
+                    signals.push(signal_hook::consts::SIGCONT);
+                }
                 let mut signals = signal_hook::iterator::Signals::new(&signals).unwrap();
                 for sig in signals.forever() {
                     sender.send(sig).unwrap();
@@ -1,5 +1,5 @@
 use std::io;
-use std::process::{Command, Stdio};
+use std::process::{Command, ExitStatus, Stdio};
 use std::thread;
 
 /// Spawns a new child process. This closes stdin and stdout, and returns to the caller after the
@@ -16,3 +16,12 @@ pub fn spawn_child_async(name: &str, args: &[&str]) -> io::Result<()> {
         .unwrap();
     Ok(())
 }
+
+/// Runs a child process like `spawn_child_async`, but waits for it to exit.
+pub fn run_child(name: &str, args: &[&str]) -> io::Result<ExitStatus> {
+    Command::new(name)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()
+}
@@ -1,12 +1,16 @@
+use std::collections::HashMap;
 use std::default::Default;
 use std::fmt;
 
 use serde::de::{self, Deserialize, Deserializer, MapAccess, Visitor};
 use serde_derive::Deserialize;
+use toml::value::Table;
 
+use crate::errors::*;
 use crate::util;
+use crate::widgets::State;
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 #[serde(default, deny_unknown_fields)]
 pub struct InternalTheme {
     pub idle_bg: Option<String>,
@@ -24,6 +28,8 @@ pub struct InternalTheme {
     pub separator_fg: Option<String>,
     pub alternating_tint_bg: Option<String>,
     pub alternating_tint_fg: Option<String>,
+    /// How the states look that are not built in, and the borders and urgency of any state
+    pub states: HashMap<String, StateTheme>,
 }
 
 impl Default for InternalTheme {
@@ -44,41 +50,387 @@ impl Default for InternalTheme {
             separator_fg: None,
             alternating_tint_bg: None,
             alternating_tint_fg: None,
+            states: HashMap::new(),
         }
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct Theme(pub InternalTheme);
+/// How widgets in a state look, declared under `[states.<name>]`
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct StateTheme {
+    pub bg: Option<String>,
+    pub fg: Option<String>,
+    pub border: Option<String>,
+    /// Ask the bar to draw the widget as urgent
+    pub urgent: Option<bool>,
+}
+
+impl StateTheme {
+    fn apply(&mut self, overrides: StateTheme) {
+        self.bg = overrides.bg.or_else(|| self.bg.take());
+        self.fg = overrides.fg.or_else(|| self.fg.take());
+        self.border = overrides.border.or_else(|| self.border.take());
+        self.urgent = overrides.urgent.or(self.urgent);
+    }
+}
+
+/// The keys of a theme, as they appear in theme files and overrides
+const KEYS: &[&str] = &[
+    "idle_bg",
+    "idle_fg",
+    "info_bg",
+    "info_fg",
+    "good_bg",
+    "good_fg",
+    "warning_bg",
+    "warning_fg",
+    "critical_bg",
+    "critical_fg",
+    "separator",
+    "separator_bg",
+    "separator_fg",
+    "alternating_tint_bg",
+    "alternating_tint_fg",
+];
+
+/// How deep themes may extend each other and palette colors may refer to each other
+const MAX_DEPTH: usize = 16;
+
+impl InternalTheme {
+    fn get_mut(&mut self, key: &str) -> Option<&mut Option<String>> {
+        Some(match key {
+            "idle_bg" => &mut self.idle_bg,
+            "idle_fg" => &mut self.idle_fg,
+            "info_bg" => &mut self.info_bg,
+            "info_fg" => &mut self.info_fg,
+            "good_bg" => &mut self.good_bg,
+            "good_fg" => &mut self.good_fg,
+            "warning_bg" => &mut self.warning_bg,
+            "warning_fg" => &mut self.warning_fg,
+            "critical_bg" => &mut self.critical_bg,
+            "critical_fg" => &mut self.critical_fg,
+            "separator" => &mut self.separator,
+            "separator_bg" => &mut self.separator_bg,
+            "separator_fg" => &mut self.separator_fg,
+            "alternating_tint_bg" => &mut self.alternating_tint_bg,
+            "alternating_tint_fg" => &mut self.alternating_tint_fg,
+            _ => return None,
+        })
+    }
+
+    /// Replace every key that is set in `overrides`
+    fn apply(&mut self, mut overrides: InternalTheme) {
+        for key in KEYS {
+            if let (Some(field), Some(value)) = (
+                self.get_mut(key),
+                overrides.get_mut(key).and_then(Option::take),
+            ) {
+                *field = Some(value);
+            }
+        }
+        for (name, mut state) in overrides.states {
+            let name = State::declare(&name);
+            // The colors of the built-in states are kept in their flat keys, so that there is
+            // only one place to override them
+            if name.is_builtin() {
+                for (part, color) in [("bg", state.bg.take()), ("fg", state.fg.take())] {
+                    if let (Some(field), Some(color)) =
+                        (self.get_mut(&format!("{}_{}", name.name(), part)), color)
+                    {
+                        *field = Some(color);
+                    }
+                }
+            }
+            self.states
+                .entry(name.name().to_string())
+                .or_default()
+                .apply(state);
+        }
+    }
+}
+
+/// A theme with all colors resolved, and the palette they were resolved with
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    theme: InternalTheme,
+    palette: HashMap<String, String>,
+}
 
 impl Default for Theme {
     fn default() -> Self {
-        Self::from_file("plain").unwrap_or_else(|| Self(InternalTheme::default()))
+        Self::from_file("plain").unwrap_or_else(|_| Self {
+            theme: InternalTheme::default(),
+            palette: HashMap::new(),
+        })
     }
 }
 
 impl std::ops::Deref for Theme {
     type Target = InternalTheme;
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.theme
     }
 }
 
 impl std::ops::DerefMut for Theme {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.theme
     }
 }
 
 impl Theme {
-    pub fn from_file(file: &str) -> Option<Theme> {
-        let file = util::find_file(file, Some("themes"), Some("toml"))?;
-        Some(Theme(util::deserialize_file(&file).ok()?))
+    pub fn from_file(file: &str) -> Result<Theme> {
+        let (theme, palette) = Self::load(file, 0)?;
+        Self::resolve(theme, palette)
+    }
+
+    /// Read a theme file and the themes it extends, without resolving its colors. Colors are
+    /// resolved only once the palette is complete, so that a theme can change the colors of the
+    /// theme it extends by redefining their palette.
+    fn load(file: &str, depth: usize) -> Result<(InternalTheme, HashMap<String, String>)> {
+        if depth > MAX_DEPTH {
+            return Err(ConfigurationError(
+                format!("Theme '{}' extends itself.", file),
+                String::new(),
+            ));
+        }
+        let path = util::find_file(file, Some("themes"), Some("toml")).ok_or_else(|| {
+            ConfigurationError(format!("Theme '{}' not found.", file), String::new())
+        })?;
+        let mut table: Table = util::deserialize_file(&path)?;
+
+        let extend = match table.remove("extend") {
+            Some(extend) => Some(String::deserialize(extend).configuration_error(&format!(
+                "'extend' of theme '{}' must be the name of a theme",
+                file
+            ))?),
+            None => None,
+        };
+        let own_palette: HashMap<String, String> = match table.remove("palette") {
+            Some(palette) => HashMap::deserialize(palette).configuration_error(&format!(
+                "'palette' of theme '{}' must be a table of colors",
+                file
+            ))?,
+            None => HashMap::new(),
+        };
+        let own_theme = InternalTheme::deserialize(toml::Value::Table(table))
+            .configuration_error(&format!("failed to parse theme '{}'", file))?;
+
+        let (mut theme, mut palette) = match extend {
+            Some(extend) => Self::load(&extend, depth + 1)?,
+            None => (InternalTheme::default(), HashMap::new()),
+        };
+        theme.apply(own_theme);
+        palette.extend(own_palette);
+        Ok((theme, palette))
+    }
+
+    fn resolve(mut theme: InternalTheme, palette: HashMap<String, String>) -> Result<Theme> {
+        for key in KEYS {
+            // The separator is a string to show, not a color
+            if *key == "separator" {
+                continue;
+            }
+            if let Some(Some(value)) = theme.get_mut(key) {
+                *value = resolve_color(value, &palette, 0)?;
+            }
+        }
+        for state in theme.states.values_mut() {
+            for color in [&mut state.bg, &mut state.fg, &mut state.border]
+                .iter_mut()
+                .filter_map(|color| color.as_mut())
+            {
+                *color = resolve_color(color, &palette, 0)?;
+            }
+        }
+        Ok(Theme { theme, palette })
+    }
+
+    /// Set `key` to `value`, which may refer to the palette of the theme. Besides the keys of
+    /// theme files, `<state>_border` and `<state>_urgent` can be set for any state, and
+    /// `<state>_bg` and `<state>_fg` for the states that are not built in.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let error = || {
+            ConfigurationError(
+                format!("Theme element \"{}\" cannot be overriden", key),
+                String::new(),
+            )
+        };
+        if !KEYS.contains(&key) {
+            let (state, part) = key.rsplit_once('_').ok_or_else(error)?;
+            let state = State::from_name(state)
+                .filter(|state| self.has_state(*state))
+                .ok_or_else(error)?;
+            let value = match part {
+                "urgent" => {
+                    let urgent = value.parse().map_err(|_| {
+                        ConfigurationError(
+                            format!("\"{}\" must be true or false", key),
+                            String::new(),
+                        )
+                    })?;
+                    self.theme
+                        .states
+                        .entry(state.name().to_string())
+                        .or_default()
+                        .urgent = Some(urgent);
+                    return Ok(());
+                }
+                "bg" | "fg" | "border" => resolve_color(value, &self.palette, 0)?,
+                _ => return Err(error()),
+            };
+            let style = self
+                .theme
+                .states
+                .entry(state.name().to_string())
+                .or_default();
+            *match part {
+                "bg" => &mut style.bg,
+                "fg" => &mut style.fg,
+                _ => &mut style.border,
+            } = Some(value);
+            return Ok(());
+        }
+
+        let value = if key == "separator" {
+            value.to_string()
+        } else {
+            resolve_color(value, &self.palette, 0)?
+        };
+        *self.theme.get_mut(key).ok_or_else(error)? = Some(value);
+        Ok(())
     }
+
+    /// Whether widgets can be put in `state`: it is built in, well known or declared by the theme
+    pub fn has_state(&self, state: State) -> bool {
+        state.is_well_known() || self.states.contains_key(state.name())
+    }
+
+    /// How widgets in `state` look. A state the theme does not declare colors for looks like the
+    /// built-in state it falls back to.
+    pub fn state(&self, state: State) -> StateTheme {
+        let (bg, fg) = match state.fallback() {
+            State::Info => (&self.info_bg, &self.info_fg),
+            State::Good => (&self.good_bg, &self.good_fg),
+            State::Warning => (&self.warning_bg, &self.warning_fg),
+            State::Critical => (&self.critical_bg, &self.critical_fg),
+            _ => (&self.idle_bg, &self.idle_fg),
+        };
+        let mut style = StateTheme {
+            bg: bg.clone(),
+            fg: fg.clone(),
+            ..StateTheme::default()
+        };
+        if let Some(own) = self.states.get(state.name()) {
+            style.apply(own.clone());
+        }
+        style
+    }
+}
+
+/// Split the arguments of a color function at the commas that are not nested in parentheses
+fn split_arguments(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in args.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(args[start..].trim());
+    parts
+}
+
+/// Resolve a color of a theme. It can be
+///
+/// - a `#RRGGBB` or `#RRGGBBAA` color, or anything else that is not a color (like `auto`), which
+///   is kept as it is
+/// - `$name`, a color of the palette
+/// - `lighten(<color>, <percent>)`, `darken(<color>, <percent>)`, `mix(<color>, <color>[, <percent>])`
+///   or `alpha(<color>, <percent>)`, where `<color>` is any of these
+fn resolve_color(value: &str, palette: &HashMap<String, String>, depth: usize) -> Result<String> {
+    let value = value.trim();
+    let error = |reason: &str| {
+        ConfigurationError(
+            format!("invalid theme color '{}': {}", value, reason),
+            String::new(),
+        )
+    };
+    if depth > MAX_DEPTH {
+        return Err(error("the palette refers to itself"));
+    }
+
+    if let Some(name) = value.strip_prefix('$') {
+        return match palette.get(name) {
+            Some(color) => resolve_color(color, palette, depth + 1),
+            None => Err(error("no such color in the palette")),
+        };
+    }
+
+    let (function, args) = match value
+        .strip_suffix(')')
+        .and_then(|value| value.split_once('('))
+    {
+        Some((function, args)) => (function.trim(), split_arguments(args)),
+        None => return Ok(value.to_string()),
+    };
+    let color = |i: usize| -> Result<(u8, u8, u8, u8)> {
+        let color = resolve_color(args.get(i).copied().unwrap_or_default(), palette, depth + 1)?;
+        match util::color_from_rgba(&color) {
+            Ok(color) => Ok(color),
+            Err(_) => Err(error(&format!("'{}' is not a color", color))),
+        }
+    };
+    let percent = |i: usize, default: Option<f64>| -> Result<f64> {
+        match args.get(i).map(|percent| percent.parse::<f64>()) {
+            Some(Ok(percent)) => Ok(percent.clamp(0., 100.) / 100.),
+            None if default.is_some() => Ok(default.unwrap_or_default()),
+            _ => Err(error("expected a percentage")),
+        }
+    };
+    // Move every channel of `a` towards `b` by `amount`
+    let mix = |a: (u8, u8, u8, u8), b: (u8, u8, u8, u8), amount: f64| {
+        let channel = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * amount).round() as u8;
+        (
+            channel(a.0, b.0),
+            channel(a.1, b.1),
+            channel(a.2, b.2),
+            channel(a.3, b.3),
+        )
+    };
+
+    let color = match (function, args.len()) {
+        ("lighten", 2) => {
+            let c = color(0)?;
+            mix(c, (255, 255, 255, c.3), percent(1, None)?)
+        }
+        ("darken", 2) => {
+            let c = color(0)?;
+            mix(c, (0, 0, 0, c.3), percent(1, None)?)
+        }
+        ("mix", 2) | ("mix", 3) => mix(color(0)?, color(1)?, percent(2, Some(0.5))?),
+        ("alpha", 2) => {
+            let c = color(0)?;
+            (c.0, c.1, c.2, (percent(1, None)? * 255.).round() as u8)
+        }
+        ("lighten", _) | ("darken", _) | ("mix", _) | ("alpha", _) => {
+            return Err(error("wrong number of arguments"))
+        }
+        _ => return Err(error("unknown function")),
+    };
+    Ok(util::color_to_rgba(color))
 }
 
 impl<'de> Deserialize<'de> for Theme {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    fn deserialize<D>(deserializer: D) -> StdResult<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
@@ -88,6 +440,7 @@ impl<'de> Deserialize<'de> for Theme {
             Name,
             File,
             Overrides,
+            Palette,
         }
 
         struct ThemeVisitor;
@@ -104,12 +457,11 @@ impl<'de> Deserialize<'de> for Theme {
             /// ```toml
             /// theme = "slick"
             /// ```
-            fn visit_str<E>(self, file: &str) -> Result<Theme, E>
+            fn visit_str<E>(self, file: &str) -> StdResult<Theme, E>
             where
                 E: de::Error,
             {
-                Theme::from_file(file)
-                    .ok_or_else(|| de::Error::custom(format!("Theme '{}' not found.", file)))
+                Theme::from_file(file).map_err(de::Error::custom)
             }
 
             /// Handle configs like:
@@ -118,12 +470,13 @@ impl<'de> Deserialize<'de> for Theme {
             /// [theme]
             /// name = "modern"
             /// ```
-            fn visit_map<V>(self, mut map: V) -> Result<Theme, V::Error>
+            fn visit_map<V>(self, mut map: V) -> StdResult<Theme, V::Error>
             where
                 V: MapAccess<'de>,
             {
                 let mut theme = None;
                 let mut overrides: Option<InternalTheme> = None;
+                let mut palette: Option<HashMap<String, String>> = None;
                 while let Some(key) = map.next_key()? {
                     match key {
                         // TODO merge name and file into one option (let's say "theme")
@@ -145,34 +498,25 @@ impl<'de> Deserialize<'de> for Theme {
                             }
                             overrides = Some(map.next_value()?);
                         }
+                        Field::Palette => {
+                            if palette.is_some() {
+                                return Err(de::Error::duplicate_field("palette"));
+                            }
+                            palette = Some(map.next_value()?);
+                        }
                     }
                 }
 
                 let theme = theme.unwrap_or_else(|| "plain".to_string());
-                let mut theme = Theme::from_file(&theme)
-                    .ok_or_else(|| de::Error::custom(format!("Theme '{}' not found.", theme)))?;
-
+                let (mut theme, mut theme_palette) =
+                    Theme::load(&theme, 0).map_err(de::Error::custom)?;
                 if let Some(overrides) = overrides {
-                    theme.0.idle_bg = overrides.idle_bg.or(theme.0.idle_bg);
-                    theme.0.idle_fg = overrides.idle_fg.or(theme.0.idle_fg);
-                    theme.0.info_bg = overrides.info_bg.or(theme.0.info_bg);
-                    theme.0.info_fg = overrides.info_fg.or(theme.0.info_fg);
-                    theme.0.good_bg = overrides.good_bg.or(theme.0.good_bg);
-                    theme.0.good_fg = overrides.good_fg.or(theme.0.good_fg);
-                    theme.0.warning_bg = overrides.warning_bg.or(theme.0.warning_bg);
-                    theme.0.warning_fg = overrides.warning_fg.or(theme.0.warning_fg);
-                    theme.0.critical_bg = overrides.critical_bg.or(theme.0.critical_bg);
-                    theme.0.critical_fg = overrides.critical_fg.or(theme.0.critical_fg);
-                    theme.0.separator = overrides.separator.or(theme.0.separator);
-                    theme.0.separator_bg = overrides.separator_bg.or(theme.0.separator_bg);
-                    theme.0.separator_fg = overrides.separator_fg.or(theme.0.separator_fg);
-                    theme.0.alternating_tint_bg = overrides
-                        .alternating_tint_bg
-                        .or(theme.0.alternating_tint_bg);
-                    theme.0.alternating_tint_fg = overrides
-                        .alternating_tint_fg
-                        .or(theme.0.alternating_tint_fg);
+                    theme.apply(overrides);
                 }
+                if let Some(palette) = palette {
+                    theme_palette.extend(palette);
+                }
+                let theme = Theme::resolve(theme, theme_palette).map_err(de::Error::custom)?;
                 Ok(theme)
             }
         }
@@ -180,3 +524,108 @@ impl<'de> Deserialize<'de> for Theme {
         deserializer.deserialize_any(ThemeVisitor)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+
+    #[test]
+    fn extend_and_palette() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let base = dir.child("base.toml");
+        base.write_str(
+            r##"
+            idle_bg = "$bg"
+            idle_fg = "lighten($bg, 50)"
+            good_bg = "mix($bg, #FFFFFF80)"
+            separator = "$not_a_color"
+            separator_fg = "auto"
+            [palette]
+            bg = "#202020"
+            "##,
+        )
+        .unwrap();
+        let child = dir.child("child.toml");
+        child
+            .write_str(&format!(
+                r##"
+                extend = "{}"
+                critical_bg = "alpha(darken($red, 50), 50)"
+                [palette]
+                bg = "#000000"
+                red = "#ff0000"
+                "##,
+                base.path().display()
+            ))
+            .unwrap();
+
+        let mut theme = Theme::from_file(child.path().to_str().unwrap()).unwrap();
+        assert_eq!(theme.idle_bg.as_deref(), Some("#000000"));
+        assert_eq!(theme.idle_fg.as_deref(), Some("#808080FF"));
+        assert_eq!(theme.good_bg.as_deref(), Some("#808080C0"));
+        assert_eq!(theme.critical_bg.as_deref(), Some("#80000080"));
+        assert_eq!(theme.separator.as_deref(), Some("$not_a_color"));
+        assert_eq!(theme.separator_fg.as_deref(), Some("auto"));
+
+        theme.set("separator_bg", "$red").unwrap();
+        assert_eq!(theme.separator_bg.as_deref(), Some("#ff0000"));
+        assert!(theme.set("idle_bg", "$blue").is_err());
+        assert!(theme.set("idle_bg", "lighten($red)").is_err());
+        assert!(theme.set("no_such_key", "#000000").is_err());
+    }
+
+    #[test]
+    fn states() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let file = dir.child("states.toml");
+        file.write_str(
+            r##"
+            idle_bg = "#000000"
+            warning_bg = "#ffff00"
+            [states.critical]
+            bg = "#ff0000"
+            urgent = true
+            [states.away]
+            fg = "$grey"
+            border = "#0000ff"
+            [palette]
+            grey = "#808080"
+            "##,
+        )
+        .unwrap();
+
+        let mut theme = Theme::from_file(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(theme.critical_bg.as_deref(), Some("#ff0000"));
+        assert_eq!(theme.state(State::Critical).urgent, Some(true));
+        let away = theme.state(State::from_name("away").unwrap());
+        assert_eq!(away.bg.as_deref(), Some("#000000"));
+        assert_eq!(away.fg.as_deref(), Some("#808080"));
+        assert_eq!(away.border.as_deref(), Some("#0000ff"));
+        // Well-known states look like the state they replace until the theme declares them
+        assert_eq!(
+            theme
+                .state(State::from_name("muted").unwrap())
+                .bg
+                .as_deref(),
+            Some("#ffff00")
+        );
+
+        theme.set("muted_bg", "$grey").unwrap();
+        theme.set("away_urgent", "true").unwrap();
+        assert_eq!(
+            theme
+                .state(State::from_name("muted").unwrap())
+                .bg
+                .as_deref(),
+            Some("#808080")
+        );
+        assert_eq!(
+            theme.state(State::from_name("away").unwrap()).urgent,
+            Some(true)
+        );
+        assert_eq!(State::from_name("gone"), None);
+        assert!(theme.set("gone_bg", "#000000").is_err());
+        assert!(theme.set("away_urgent", "maybe").is_err());
+    }
+}
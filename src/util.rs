@@ -83,6 +83,16 @@ pub fn escape_pango_text(text: String) -> String {
         .collect()
 }
 
+/// The reverse of `escape_pango_text`
+pub fn unescape_pango_text(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
 pub fn battery_level_to_icon(charge_level: Result<u64>) -> &'static str {
     match charge_level {
         Ok(0..=5) => "bat_empty",
@@ -1,10 +1,12 @@
 pub mod rotatingtext;
 pub mod text;
 
+use std::collections::HashSet;
 use std::str::FromStr;
+use std::sync::Mutex;
 
-use serde::de::value::{Error, StrDeserializer};
-use serde::de::{Deserialize, IntoDeserializer};
+use lazy_static::lazy_static;
+use serde::de::{self, Deserialize, Deserializer};
 use serde_derive::Deserialize;
 
 use crate::protocol::i3bar_block::I3BarBlock;
@@ -44,37 +46,145 @@ impl Spacing {
     }
 }
 
-#[derive(Debug, Copy, Clone, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum State {
     Idle,
     Info,
     Good,
     Warning,
     Critical,
+    /// A state that is not built in, like `muted`. Themes declare how it looks under
+    /// `[states.<name>]`.
+    Custom(&'static str),
+}
+
+/// Extra states blocks use on their own. Until a theme declares them, they look like the
+/// built-in state they replace.
+const WELL_KNOWN_STATES: &[(&str, State)] = &[
+    ("muted", State::Warning),
+    ("charging", State::Good),
+    ("urgent", State::Critical),
+    ("inactive", State::Idle),
+];
+
+lazy_static! {
+    /// The names of the states themes declared
+    static ref NAMES: Mutex<HashSet<&'static str>> = Mutex::new(HashSet::new());
+}
+
+/// Keep the name of a custom state for the rest of the program, so that `State` stays `Copy`.
+/// Only themes add names, so there are no more of them than the configs declare.
+fn intern(name: &str) -> &'static str {
+    let mut names = NAMES.lock().unwrap();
+    match names.get(name) {
+        Some(name) => name,
+        None => {
+            let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+            names.insert(name);
+            name
+        }
+    }
 }
 
 impl State {
-    pub fn theme_keys(self, theme: &Theme) -> (&Option<String>, &Option<String>) {
-        use self::State::*;
+    fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "idle" | "Idle" => Some(Self::Idle),
+            "info" | "Info" => Some(Self::Info),
+            "good" | "Good" => Some(Self::Good),
+            "warning" | "Warning" => Some(Self::Warning),
+            "critical" | "Critical" => Some(Self::Critical),
+            _ => None,
+        }
+    }
+
+    /// The state called `name`, if it is built in, well known or was declared by a theme. The
+    /// built-in states are also accepted capitalized, as they used to be.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::builtin(name).or_else(|| {
+            WELL_KNOWN_STATES
+                .iter()
+                .map(|(known, _)| *known)
+                .find(|known| *known == name)
+                .or_else(|| NAMES.lock().unwrap().get(name).copied())
+                .map(Self::Custom)
+        })
+    }
+
+    /// Declare the state called `name`, for themes. Whether the current theme has the state
+    /// is up to `Theme::has_state`.
+    pub fn declare(name: &str) -> Self {
+        Self::builtin(name).unwrap_or_else(|| Self::Custom(intern(name)))
+    }
+
+    pub fn name(self) -> &'static str {
         match self {
-            Idle => (&theme.idle_bg, &theme.idle_fg),
-            Info => (&theme.info_bg, &theme.info_fg),
-            Good => (&theme.good_bg, &theme.good_fg),
-            Warning => (&theme.warning_bg, &theme.warning_fg),
-            Critical => (&theme.critical_bg, &theme.critical_fg),
+            Self::Idle => "idle",
+            Self::Info => "info",
+            Self::Good => "good",
+            Self::Warning => "warning",
+            Self::Critical => "critical",
+            Self::Custom(name) => name,
         }
     }
+
+    pub fn is_builtin(self) -> bool {
+        !matches!(self, Self::Custom(_))
+    }
+
+    /// The built-in state whose colors a state has unless the theme declares its own
+    pub fn fallback(self) -> Self {
+        match self {
+            Self::Custom(name) => WELL_KNOWN_STATES
+                .iter()
+                .find(|(known, _)| *known == name)
+                .map_or(Self::Idle, |(_, state)| *state),
+            state => state,
+        }
+    }
+
+    /// Whether blocks may use the state even if no theme declares it
+    pub fn is_well_known(self) -> bool {
+        match self {
+            Self::Custom(name) => WELL_KNOWN_STATES.iter().any(|(known, _)| *known == name),
+            _ => true,
+        }
+    }
+
+    /// Color `widget` like the theme shows this state
+    pub fn apply(self, theme: &Theme, widget: &mut I3BarBlock) {
+        let style = theme.state(self);
+        widget.background = style.bg;
+        widget.color = style.fg;
+        widget.border = style.border.or_else(|| I3BarBlock::default().border);
+        widget.urgent = style.urgent.filter(|urgent| *urgent);
+    }
 }
 
 impl FromStr for State {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let deserializer: StrDeserializer<Error> = s.into_deserializer();
-        State::deserialize(deserializer).map_err(|_| ())
+        Self::from_name(s).ok_or(())
+    }
+}
+
+impl<'de> Deserialize<'de> for State {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+        Self::from_name(&name).ok_or_else(|| de::Error::custom(format!("unknown state '{}'", name)))
     }
 }
 
 pub trait I3BarWidget {
     fn get_data(&self) -> I3BarBlock;
 }
+
+impl I3BarWidget for I3BarBlock {
+    fn get_data(&self) -> I3BarBlock {
+        self.clone()
+    }
+}
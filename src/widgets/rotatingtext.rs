@@ -18,6 +18,7 @@ pub struct RotatingTextWidget {
     rotation_speed: Duration,
     next_rotation: Option<Instant>,
     content: String,
+    /// The name of the icon, which is looked up for the current state on every update
     icon: Option<String>,
     state: State,
     spacing: Spacing,
@@ -64,7 +65,7 @@ impl RotatingTextWidget {
     }
 
     pub fn with_icon(mut self, name: &str) -> Result<Self> {
-        self.icon = Some(self.shared_config.get_icon(name)?);
+        self.icon = Some(name.to_string());
         self.update();
         Ok(self)
     }
@@ -99,7 +100,7 @@ impl RotatingTextWidget {
     }
 
     pub fn set_icon(&mut self, name: &str) -> Result<()> {
-        self.icon = Some(self.shared_config.get_icon(name)?);
+        self.icon = Some(name.to_string());
         self.update();
         Ok(())
     }
@@ -148,12 +149,13 @@ impl RotatingTextWidget {
     }
 
     fn update(&mut self) {
-        let (key_bg, key_fg) = self.state.theme_keys(&self.shared_config.theme);
-
-        let mut icon = self.icon.clone().unwrap_or_else(|| match self.spacing {
-            Spacing::Normal => String::from(" "),
-            _ => String::from(""),
-        });
+        let mut icon = match self.icon {
+            Some(ref icon) => self.shared_config.get_state_icon(icon, self.state),
+            None => match self.spacing {
+                Spacing::Normal => String::from(" "),
+                _ => String::from(""),
+            },
+        };
 
         self.inner.full_text = format!(
             "{}{}{}",
@@ -172,8 +174,7 @@ impl RotatingTextWidget {
                 Some(I3BarBlockMinWidth::Text(icon))
             }
         };
-        self.inner.background = key_bg.clone();
-        self.inner.color = key_fg.clone();
+        self.state.apply(&self.shared_config.theme, &mut self.inner);
     }
 
     pub fn next(&mut self) -> Result<(bool, Option<Duration>)> {
@@ -9,6 +9,7 @@ pub struct TextWidget {
     pub instance: usize,
     content: Option<String>,
     content_short: Option<String>,
+    /// The name of the icon, which is looked up for the current state on every update
     icon: Option<String>,
     state: State,
     spacing: Spacing,
@@ -19,14 +20,12 @@ pub struct TextWidget {
 
 impl TextWidget {
     pub fn new(id: usize, instance: usize, shared_config: SharedConfig) -> Self {
-        let (key_bg, key_fg) = State::Idle.theme_keys(&shared_config.theme); // Initial colors
-        let inner = I3BarBlock {
+        let mut inner = I3BarBlock {
             name: Some(id.to_string()),
             instance: Some(instance.to_string()),
-            color: key_fg.clone(),
-            background: key_bg.clone(),
             ..I3BarBlock::default()
         };
+        State::Idle.apply(&shared_config.theme, &mut inner); // Initial colors
 
         TextWidget {
             id,
@@ -43,7 +42,7 @@ impl TextWidget {
     }
 
     pub fn with_icon(mut self, name: &str) -> Result<Self> {
-        self.icon = Some(self.shared_config.get_icon(name)?);
+        self.icon = Some(name.to_string());
         self.update();
         Ok(self)
     }
@@ -68,7 +67,7 @@ impl TextWidget {
     }
 
     pub fn set_icon(&mut self, name: &str) -> Result<()> {
-        self.icon = Some(self.shared_config.get_icon(name)?);
+        self.icon = Some(name.to_string());
         self.update();
         Ok(())
     }
@@ -108,7 +107,8 @@ impl TextWidget {
         format!(
             "{}{}{}",
             self.icon
-                .clone()
+                .as_ref()
+                .map(|icon| self.shared_config.get_state_icon(icon, self.state))
                 .unwrap_or_else(|| spacing.to_string_leading()),
             content,
             spacing.to_string_trailing()
@@ -116,16 +116,13 @@ impl TextWidget {
     }
 
     fn update(&mut self) {
-        let (key_bg, key_fg) = self.state.theme_keys(&self.shared_config.theme);
-
         self.inner.full_text =
             self.format_text(self.content.clone().unwrap_or_default(), self.spacing);
         self.inner.short_text = match &self.content_short {
             Some(text) => Some(self.format_text(text.clone(), self.spacing_short)),
             _ => None,
         };
-        self.inner.background = key_bg.clone();
-        self.inner.color = key_fg.clone();
+        self.state.apply(&self.shared_config.theme, &mut self.inner);
     }
 }
 
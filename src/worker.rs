@@ -0,0 +1,678 @@
+//! Every block lives in a thread of its own, so that a slow `update()` (a network request, a
+//! subprocess, ...) cannot stall the rest of the bar or the handling of click events.
+//!
+//! The main loop only talks to a block through its `BlockWorker`: commands are sent to the
+//! block's thread, which answers with a `Response` containing the result and the freshly rendered
+//! widgets. Until the answer arrives, the bar keeps showing the last widgets of the block, or a
+//! placeholder if the block has not answered yet. Blocks are created in their thread as well, so
+//! a block that takes long to set up does not hold up the main loop either.
+//!
+//! The threads a block starts in the background, like D-Bus listeners or subprocess watchers,
+//! ask for updates on a channel of the block's own thread, which passes the requests on to the
+//! main loop. The channel closes when the block's thread ends, because the block was removed by a
+//! config reload, restarted or has panicked, and the background threads stop once their next
+//! request fails.
+//!
+//! Errors are isolated the same way: a block that fails to be created, returns an error or panics
+//! is shown in an error state and retried later, while every other block keeps working.
+
+use std::cmp;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::{select, Receiver, Sender};
+use toml::value::Value;
+
+use crate::blocks::base_block::BaseBlockConfig;
+use crate::blocks::{create_block, Block, Update};
+use crate::config::SharedConfig;
+use crate::errors::*;
+use crate::formatting;
+use crate::protocol::i3bar_block::I3BarBlock;
+use crate::protocol::i3bar_event::I3BarEvent;
+use crate::scheduler::{Task, UpdateScheduler};
+use crate::themes::Theme;
+use crate::util::escape_pango_text;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// How long a block may be busy with a single command if it has no `timeout` configured
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+/// How long to wait before retrying a failed block that has no update interval
+const DEFAULT_RETRY: Duration = Duration::from_secs(5);
+/// The retry delay doubles with every failure, but does not grow beyond this
+/// (or the block's own update interval, if that is longer)
+const MAX_RETRY: Duration = Duration::from_secs(300);
+
+pub enum Command {
+    /// Call `update()`. Only the results of scheduled updates are used to reschedule the block.
+    Update {
+        scheduled: bool,
+    },
+    Click(I3BarEvent),
+    Signal(i32),
+}
+
+pub struct Response {
+    pub id: usize,
+    generation: usize,
+    /// Whether this is the result of a scheduled update
+    scheduled: bool,
+    /// When the block started to process the command
+    started: Instant,
+    pub result: Result<Option<Update>>,
+    widgets: Vec<I3BarBlock>,
+    /// Whether the thread keeps running. If not, because the block could not be created or
+    /// panicked, the commands it has not answered are dropped, and the block is created anew
+    /// with the next command.
+    alive: bool,
+}
+
+pub struct BlockWorker {
+    id: usize,
+    name: String,
+    /// The `id` given in the config, which replaces the numeric id in the i3bar protocol
+    named_id: Option<String>,
+    config: Value,
+    shared_config: SharedConfig,
+    /// The theme with the `theme_overrides` of the block applied
+    theme: Arc<Theme>,
+    tx_update_request: Sender<Task>,
+    tx_response: Sender<Response>,
+    timeout: Duration,
+    /// Changes every time the thread is (re)started, so that responses of an abandoned thread
+    /// can be told apart
+    generation: usize,
+    /// `None` until the thread of the block has been started, or after it has died
+    sender: Option<Sender<Command>>,
+    /// The channel the current thread of the block takes update requests on, to check that it
+    /// closes
+    #[cfg(test)]
+    tx_block_update: Option<Sender<Task>>,
+    /// Number of commands the thread has not answered yet
+    pending: usize,
+    /// Whether one of them is a scheduled update
+    update_pending: bool,
+    /// When the thread last started to work on a command
+    busy_since: Instant,
+    widgets: Vec<I3BarBlock>,
+    /// The update interval the block asked for the last time it updated successfully
+    interval: Option<Duration>,
+    /// The error of the last command, if it failed
+    error: Option<Error>,
+    /// Number of failed commands in a row
+    failures: u32,
+    /// Whether the error widget shows the full error message
+    error_expanded: bool,
+}
+
+impl BlockWorker {
+    /// Prepare a block. The block itself is created in a new thread once it gets its first
+    /// command.
+    pub fn spawn(
+        id: usize,
+        name: &str,
+        config: Value,
+        shared_config: SharedConfig,
+        tx_update_request: Sender<Task>,
+        tx_response: Sender<Response>,
+    ) -> Result<Self> {
+        let base_config = BaseBlockConfig::peek(&config)?;
+        let timeout = base_config.timeout.unwrap_or(DEFAULT_TIMEOUT);
+        let theme = match base_config.theme_overrides {
+            Some(ref overrides) => {
+                let mut block_config = shared_config.clone();
+                block_config.theme_override(overrides)?;
+                block_config.theme
+            }
+            None => shared_config.theme.clone(),
+        };
+        let mut worker = Self {
+            id,
+            name: name.to_string(),
+            named_id: base_config.id,
+            config,
+            shared_config,
+            theme,
+            tx_update_request,
+            tx_response,
+            timeout,
+            generation: 0,
+            sender: None,
+            #[cfg(test)]
+            tx_block_update: None,
+            pending: 0,
+            update_pending: false,
+            busy_since: Instant::now(),
+            widgets: Vec::new(),
+            interval: None,
+            error: None,
+            failures: 0,
+            error_expanded: false,
+        };
+        worker.widgets = worker.placeholder_widgets();
+        Ok(worker)
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn named_id(&self) -> Option<&str> {
+        self.named_id.as_deref()
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// The widgets of the last completed command, or a placeholder until the block has answered
+    /// for the first time
+    pub fn widgets(&self) -> &[I3BarBlock] {
+        &self.widgets
+    }
+
+    /// The channel the current thread of the block takes update requests on
+    #[cfg(test)]
+    pub fn block_update_sender(&self) -> Option<Sender<Task>> {
+        self.tx_block_update.clone()
+    }
+
+    /// Send a command to the block, starting a thread that (re)creates the block first if
+    /// necessary. If that fails, the error is delivered like any other response.
+    pub fn send(&mut self, command: Command) {
+        if self.pending == 0 {
+            self.busy_since = Instant::now();
+        }
+        self.pending += 1;
+        if matches!(command, Command::Update { scheduled: true }) {
+            self.update_pending = true;
+        }
+
+        let command = match &self.sender {
+            Some(sender) => match sender.send(command) {
+                Ok(()) => return,
+                // The thread has died, create the block anew
+                Err(error) => error.into_inner(),
+            },
+            None => command,
+        };
+
+        let scheduled = matches!(command, Command::Update { scheduled: true });
+        match start_thread(
+            self.id,
+            &self.name,
+            &self.config,
+            &self.shared_config,
+            &self.tx_update_request,
+            &self.tx_response,
+        ) {
+            Ok((generation, sender, _tx_block_update)) => {
+                #[cfg(test)]
+                {
+                    self.tx_block_update = Some(_tx_block_update);
+                }
+                self.generation = generation;
+                // The thread has just been started, so it is still listening
+                let _ = sender.send(command);
+                self.sender = Some(sender);
+            }
+            Err(error) => {
+                self.sender = None;
+                let _ = self.tx_response.send(Response {
+                    id: self.id,
+                    generation: self.generation,
+                    scheduled,
+                    started: Instant::now(),
+                    result: Err(error),
+                    widgets: Vec::new(),
+                    alive: false,
+                });
+            }
+        }
+    }
+
+    /// Clicks on a block in the error state toggle the full error message instead of being
+    /// passed on to the block.
+    pub fn click(&mut self, event: I3BarEvent) {
+        if self.error.is_some() {
+            self.error_expanded = !self.error_expanded;
+            let widgets = self.error_widgets();
+            self.set_widgets(widgets);
+        } else {
+            self.send(Command::Click(event));
+        }
+    }
+
+    /// Handle a response of the block's thread and schedule the block's next update if it
+    /// was a scheduled one. Returns `false` if the response was sent by a thread that has been
+    /// abandoned since.
+    pub fn accept(&mut self, response: Response, scheduler: &mut UpdateScheduler) -> bool {
+        if response.generation != self.generation {
+            return false;
+        }
+        // If the thread has exited, the commands it has not answered are lost, and a scheduled
+        // update among them has to be rescheduled here instead
+        let scheduled = response.scheduled || (!response.alive && self.update_pending);
+        if response.alive {
+            self.pending = self.pending.saturating_sub(1);
+        } else {
+            self.sender = None;
+            self.pending = 0;
+        }
+        if scheduled {
+            self.update_pending = false;
+        }
+        self.busy_since = Instant::now();
+
+        match response.result {
+            Ok(update) => {
+                self.error = None;
+                self.failures = 0;
+                self.set_widgets(response.widgets);
+                if scheduled {
+                    self.interval = match update {
+                        Some(Update::Every(interval)) => Some(interval),
+                        _ => None,
+                    };
+                    scheduler.reschedule(self.id, response.started, update);
+                }
+            }
+            Err(error) => {
+                if self.error.is_none() {
+                    self.error_expanded = false;
+                }
+                self.error = Some(error);
+                self.failures += 1;
+                let widgets = self.error_widgets();
+                self.set_widgets(widgets);
+                if scheduled {
+                    scheduler.reschedule(
+                        self.id,
+                        response.started,
+                        Some(Update::Every(self.retry_delay())),
+                    );
+                }
+            }
+        }
+        true
+    }
+
+    fn set_widgets(&mut self, mut widgets: Vec<I3BarBlock>) {
+        if let Some(named_id) = &self.named_id {
+            for widget in &mut widgets {
+                widget.name = Some(named_id.clone());
+            }
+        }
+        self.widgets = widgets;
+    }
+
+    /// The retry delay of a failed block: its update interval, doubled for every failure in a row
+    fn retry_delay(&self) -> Duration {
+        let interval = self.interval.unwrap_or(DEFAULT_RETRY);
+        let factor = 2u32.saturating_pow(self.failures.saturating_sub(1));
+        cmp::min(
+            interval.checked_mul(factor).unwrap_or(MAX_RETRY),
+            cmp::max(interval, MAX_RETRY),
+        )
+    }
+
+    fn placeholder_widgets(&self) -> Vec<I3BarBlock> {
+        let mut widget = TextWidget::new(self.id, 0, self.shared_config.clone());
+        widget.set_text(format!("{} …", self.name));
+        vec![widget.get_data()]
+    }
+
+    fn error_widgets(&self) -> Vec<I3BarBlock> {
+        let error = match &self.error {
+            Some(error) => error,
+            None => return Vec::new(),
+        };
+        let text = if self.error_expanded {
+            format!("{:?}", error)
+        } else {
+            format!("{}", error)
+        };
+        let mut widget =
+            TextWidget::new(self.id, 0, self.shared_config.clone()).with_state(State::Critical);
+        widget.set_texts((
+            escape_pango_text(text),
+            Some(format!("{}: error", self.name)),
+        ));
+        vec![widget.get_data()]
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.pending == 0
+    }
+
+    /// When the command the block is currently busy with will time out
+    pub fn deadline(&self) -> Option<Instant> {
+        if self.pending == 0 {
+            None
+        } else {
+            Some(self.busy_since + self.timeout)
+        }
+    }
+
+    /// Abandon the current thread. The block is created anew in a fresh thread with the next
+    /// command, the old thread exits as soon as it gets unstuck. Until the new thread responds,
+    /// the block shows the timeout as its error.
+    pub fn restart(&mut self) {
+        if self.error.is_none() {
+            self.error_expanded = false;
+        }
+        self.error = Some(BlockError(
+            self.name.clone(),
+            format!("did not respond within {:?}, restarting", self.timeout),
+        ));
+        self.failures += 1;
+        let widgets = self.error_widgets();
+        self.set_widgets(widgets);
+        self.sender = None;
+        self.pending = 0;
+        self.update_pending = false;
+        // Make sure late responses of the old thread are ignored
+        self.generation = next_generation();
+    }
+}
+
+fn next_generation() -> usize {
+    static GENERATION: AtomicUsize = AtomicUsize::new(1);
+    GENERATION.fetch_add(1, Ordering::SeqCst)
+}
+
+/// Start a thread that creates the block and runs its commands. Returns the generation of the
+/// thread, the channel to send it commands on and the channel the block asks for updates on.
+fn start_thread(
+    id: usize,
+    name: &str,
+    config: &Value,
+    shared_config: &SharedConfig,
+    tx_update_request: &Sender<Task>,
+    tx_response: &Sender<Response>,
+) -> Result<(usize, Sender<Command>, Sender<Task>)> {
+    let generation = next_generation();
+
+    let (tx_command, rx_command) = crossbeam_channel::unbounded();
+    let (tx_block_update, rx_block_update) = crossbeam_channel::unbounded();
+
+    let block_name = name.to_string();
+    let config = config.clone();
+    let shared_config = shared_config.clone();
+    let tx_update_request = tx_update_request.clone();
+    let tx_response = tx_response.clone();
+    let tx_block = tx_block_update.clone();
+    thread::Builder::new()
+        .name(format!("block {}", name))
+        .spawn(move || {
+            // Blocks are not `Send`, so they have to be created in the thread they run in
+            match create_block(id, &block_name, config, shared_config, tx_block) {
+                Ok(block) => {
+                    let channels = Channels {
+                        rx_command,
+                        rx_block_update,
+                        tx_update_request,
+                        tx_response,
+                    };
+                    run(block, id, generation, channels)
+                }
+                Err(error) => fail(error, id, generation, tx_response),
+            }
+        })
+        .internal_error("block worker", "failed to spawn thread")?;
+
+    Ok((generation, tx_command, tx_block_update))
+}
+
+/// Lets the main loop know why the block could not be created. The thread exits afterwards,
+/// the block is created anew with the next command.
+fn fail(error: Error, id: usize, generation: usize, tx_response: Sender<Response>) {
+    let _ = tx_response.send(Response {
+        id,
+        generation,
+        scheduled: false,
+        started: Instant::now(),
+        result: Err(error),
+        widgets: Vec::new(),
+        alive: false,
+    });
+}
+
+/// The channels of a block's thread
+struct Channels {
+    rx_command: Receiver<Command>,
+    /// Update requests of the block and its background threads, which are passed on to
+    /// `tx_update_request`
+    rx_block_update: Receiver<Task>,
+    tx_update_request: Sender<Task>,
+    tx_response: Sender<Response>,
+}
+
+fn run(mut block: Box<dyn Block>, id: usize, generation: usize, channels: Channels) {
+    let Channels {
+        rx_command,
+        mut rx_block_update,
+        tx_update_request,
+        tx_response,
+    } = channels;
+    let mut guard = PanicGuard {
+        id,
+        generation,
+        scheduled: false,
+        tx_response: tx_response.clone(),
+    };
+
+    loop {
+        let command = select! {
+            recv(rx_command) -> command => match command {
+                Ok(command) => command,
+                // The block has been removed or abandoned
+                Err(_) => break,
+            },
+            recv(rx_block_update) -> task => {
+                match task {
+                    Ok(task) => {
+                        if tx_update_request.send(task).is_err() {
+                            break;
+                        }
+                    }
+                    // Nothing of the block asks for updates anymore
+                    Err(_) => rx_block_update = crossbeam_channel::never(),
+                }
+                continue;
+            },
+        };
+        let started = Instant::now();
+        let (scheduled, result) = match command {
+            Command::Update { scheduled } => {
+                guard.scheduled = scheduled;
+                // Only updates move sparklines on, not the renders of clicks and signals
+                (scheduled, formatting::sample(|| block.update()))
+            }
+            Command::Click(event) => (false, block.click(&event).map(|_| None)),
+            Command::Signal(signal) => (false, block.signal(signal).map(|_| None)),
+        };
+        guard.scheduled = false;
+        let widgets = block.view().iter().map(|w| w.get_data()).collect();
+        let response = Response {
+            id,
+            generation,
+            scheduled,
+            started,
+            result,
+            widgets,
+            alive: true,
+        };
+        if tx_response.send(response).is_err() {
+            break;
+        }
+    }
+}
+
+/// Lets the main loop know that the block panicked instead of leaving it waiting. The block is
+/// created anew with the next command.
+struct PanicGuard {
+    id: usize,
+    generation: usize,
+    /// Whether the block panicked during a scheduled update
+    scheduled: bool,
+    tx_response: Sender<Response>,
+}
+
+impl Drop for PanicGuard {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            let _ = self.tx_response.send(Response {
+                id: self.id,
+                generation: self.generation,
+                scheduled: self.scheduled,
+                started: Instant::now(),
+                result: Err(InternalError(
+                    "block worker".to_string(),
+                    "block panicked".to_string(),
+                    None,
+                )),
+                widgets: Vec::new(),
+                alive: false,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn worker(name: &str, config: &str) -> (BlockWorker, Receiver<Response>) {
+        let (tx, _rx) = crossbeam_channel::unbounded();
+        let (tx_response, rx_response) = crossbeam_channel::unbounded();
+        let worker = BlockWorker::spawn(
+            0,
+            name,
+            toml::from_str(config).unwrap(),
+            SharedConfig::default(),
+            tx,
+            tx_response,
+        )
+        .unwrap();
+        (worker, rx_response)
+    }
+
+    fn recv(rx_response: &Receiver<Response>) -> Response {
+        rx_response.recv_timeout(Duration::from_secs(5)).unwrap()
+    }
+
+    #[test]
+    fn placeholder_until_created() {
+        let (mut worker, rx_response) = worker("custom", "command = \"echo hello\"");
+        let mut scheduler = UpdateScheduler::new(&[]);
+        assert!(worker.widgets()[0].full_text.contains("custom …"));
+
+        worker.send(Command::Update { scheduled: true });
+        assert!(!worker.is_idle());
+        let response = recv(&rx_response);
+        assert!(response.alive);
+        assert!(worker.accept(response, &mut scheduler));
+        assert!(worker.is_idle());
+        assert!(worker.widgets()[0].full_text.contains("hello"));
+        assert!(scheduler.time_to_next_update().is_some());
+    }
+
+    #[test]
+    fn creation_failure_is_retried() {
+        let (mut worker, rx_response) = worker("no_such_block", "");
+        let mut scheduler = UpdateScheduler::new(&[]);
+
+        worker.send(Command::Update { scheduled: true });
+        let response = recv(&rx_response);
+        assert!(!response.alive);
+        assert!(response.result.is_err());
+        assert!(worker.accept(response, &mut scheduler));
+        assert!(worker.sender.is_none());
+        assert!(worker.is_idle());
+        assert!(worker.error.is_some());
+        assert!(worker.widgets()[0].full_text.contains("no_such_block"));
+        // Even though the thread has exited before answering the update, it is retried
+        assert!(scheduler.time_to_next_update().unwrap() > DEFAULT_RETRY / 2);
+    }
+
+    #[test]
+    fn abandoned_threads_are_ignored() {
+        let (mut worker, rx_response) = worker("custom", "command = \"echo hello\"");
+        let mut scheduler = UpdateScheduler::new(&[]);
+
+        worker.send(Command::Update { scheduled: true });
+        let generation = worker.generation;
+        worker.restart();
+        assert_ne!(worker.generation, generation);
+
+        let response = recv(&rx_response);
+        assert_eq!(response.generation, generation);
+        assert!(!worker.accept(response, &mut scheduler));
+        assert!(worker.widgets()[0].full_text.contains("did not respond"));
+
+        // The next command starts a fresh thread, whose responses are used again
+        worker.send(Command::Update { scheduled: false });
+        let response = recv(&rx_response);
+        assert_eq!(response.generation, worker.generation);
+        assert!(worker.accept(response, &mut scheduler));
+        assert!(worker.widgets()[0].full_text.contains("hello"));
+    }
+
+    #[test]
+    fn stuck_blocks_time_out() {
+        let (mut worker, _rx_response) = worker("custom", "command = \"sleep 5\"\ntimeout = 0.1");
+        assert_eq!(worker.deadline(), None);
+
+        let sent = Instant::now();
+        worker.send(Command::Update { scheduled: true });
+        let deadline = worker.deadline().unwrap();
+        assert!(deadline <= sent + Duration::from_millis(100) + Duration::from_millis(50));
+        assert!(deadline >= sent + Duration::from_millis(50));
+
+        // A second command does not move the deadline of the first one
+        thread::sleep(Duration::from_millis(20));
+        worker.send(Command::Signal(0));
+        assert_eq!(worker.deadline(), Some(deadline));
+
+        worker.restart();
+        assert_eq!(worker.deadline(), None);
+        assert!(worker.is_idle());
+        assert!(worker.sender.is_none());
+        assert!(worker.widgets()[0].full_text.contains("did not respond"));
+    }
+
+    #[test]
+    fn retry_delay_backs_off() {
+        let (tx, _rx) = crossbeam_channel::unbounded();
+        let (tx_response, _rx_response) = crossbeam_channel::unbounded();
+        let mut worker = BlockWorker::spawn(
+            0,
+            "template",
+            Value::Table(Default::default()),
+            SharedConfig::default(),
+            tx,
+            tx_response,
+        )
+        .unwrap();
+
+        worker.failures = 1;
+        assert_eq!(worker.retry_delay(), DEFAULT_RETRY);
+
+        worker.interval = Some(Duration::from_secs(10));
+        worker.failures = 3;
+        assert_eq!(worker.retry_delay(), Duration::from_secs(40));
+        worker.failures = 100;
+        assert_eq!(worker.retry_delay(), MAX_RETRY);
+
+        worker.interval = Some(Duration::from_secs(600));
+        assert_eq!(worker.retry_delay(), Duration::from_secs(600));
+    }
+}